@@ -1,10 +1,11 @@
 //! Account conversions
 
 use crate::{
-    ClaimRewards, InitMiner, QuarryStake, QuarryStakePrimary, QuarryStakeReplica, WithdrawTokens,
+    ClaimRewards, InitMiner, MergeMinerNft, QuarryStake, QuarryStakePrimary, QuarryStakeReplica,
+    WithdrawTokens,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, TokenAccount, Transfer};
+use anchor_spl::token::{self, Transfer};
 
 impl<'info> InitMiner<'info> {
     /// Conversion.
@@ -16,8 +17,7 @@ impl<'info> InitMiner<'info> {
             rewarder: self.rewarder.to_account_info(),
             system_program: self.system_program.to_account_info(),
             payer: self.payer.to_account_info(),
-            token_mint: self.token_mint.to_account_info(),
-            miner_vault: self.miner_vault.to_account_info(),
+            nft_update_authority: self.nft_update_authority.to_account_info(),
             token_program: self.token_program.to_account_info(),
         }
     }
@@ -36,15 +36,10 @@ impl<'info> ClaimRewards<'info> {
             stake: self.stake.gen_user_claim(),
         }
     }
-
-    /// Conversion.
-    pub fn to_user_stake_accounts(&self) -> quarry_mine::cpi::accounts::UserStake<'info> {
-        self.stake.gen_user_stake(&self.stake_token_account)
-    }
 }
 
 impl<'info> QuarryStake<'info> {
-    /// Generates the [quarry_mine::UserStake] accounts.
+    /// Generates the [quarry_mine::UserClaim] accounts.
     fn gen_user_claim(&self) -> quarry_mine::cpi::accounts::UserClaim<'info> {
         quarry_mine::cpi::accounts::UserClaim {
             authority: self.mm.to_account_info(),
@@ -52,60 +47,103 @@ impl<'info> QuarryStake<'info> {
             quarry: self.quarry.to_account_info(),
             token_program: self.token_program.to_account_info(),
             rewarder: self.rewarder.to_account_info(),
-            unused_miner_vault: self.unused_account.to_account_info(),
-            unused_token_account: self.unused_account.to_account_info(),
         }
     }
 
     /// Generates the [quarry_mine::UserStake] accounts.
     fn gen_user_stake(
         &self,
-        token_account: &Account<'info, TokenAccount>,
+        nft: &MergeMinerNft<'info>,
     ) -> quarry_mine::cpi::accounts::UserStake<'info> {
         quarry_mine::cpi::accounts::UserStake {
             authority: self.mm.to_account_info(),
             miner: self.miner.to_account_info(),
             quarry: self.quarry.to_account_info(),
-            miner_vault: self.miner_vault.to_account_info(),
-            token_account: token_account.to_account_info(),
+            token_mint: nft.token_mint.to_account_info(),
+            miner_nft_vault: nft.miner_nft_vault.to_account_info(),
+            token_metadata: nft.token_metadata.to_account_info(),
+            token_edition: nft.token_edition.to_account_info(),
+            token_account: nft.mm_token_account.to_account_info(),
             token_program: self.token_program.to_account_info(),
             rewarder: self.rewarder.to_account_info(),
         }
     }
+
+    /// Generates the [quarry_mine::StakeNft] accounts.
+    fn gen_stake_nft(
+        &self,
+        nft: &MergeMinerNft<'info>,
+        payer: &Signer<'info>,
+        system_program: &Program<'info, System>,
+    ) -> quarry_mine::cpi::accounts::StakeNft<'info> {
+        quarry_mine::cpi::accounts::StakeNft {
+            stake: self.gen_user_stake(nft),
+            stake_receipt: nft.stake_receipt.to_account_info(),
+            payer: payer.to_account_info(),
+            system_program: system_program.to_account_info(),
+        }
+    }
+
+    /// Generates the [quarry_mine::WithdrawNft] accounts.
+    /// The rent of the [quarry_mine::StakeReceipt] is refunded to the [crate::MergeMiner].
+    fn gen_withdraw_nft(
+        &self,
+        nft: &MergeMinerNft<'info>,
+    ) -> quarry_mine::cpi::accounts::WithdrawNft<'info> {
+        quarry_mine::cpi::accounts::WithdrawNft {
+            stake: self.gen_user_stake(nft),
+            stake_receipt: nft.stake_receipt.to_account_info(),
+            rent_receiver: self.mm.to_account_info(),
+        }
+    }
+}
+
+impl<'info> QuarryStakePrimary<'info> {
+    /// Conversion.
+    pub fn to_stake_nft_accounts(&self) -> quarry_mine::cpi::accounts::StakeNft<'info> {
+        self.stake
+            .gen_stake_nft(&self.nft, &self.mm_owner, &self.system_program)
+    }
+
+    /// Conversion.
+    pub fn to_withdraw_nft_accounts(&self) -> quarry_mine::cpi::accounts::WithdrawNft<'info> {
+        self.stake.gen_withdraw_nft(&self.nft)
+    }
 }
 
 impl<'info> QuarryStakeReplica<'info> {
     /// Conversion.
-    pub fn to_user_stake_accounts(&self) -> quarry_mine::cpi::accounts::UserStake<'info> {
-        self.stake.gen_user_stake(&self.replica_mint_token_account)
+    pub fn to_stake_nft_accounts(&self) -> quarry_mine::cpi::accounts::StakeNft<'info> {
+        self.stake
+            .gen_stake_nft(&self.nft, &self.mm_owner, &self.system_program)
+    }
+
+    /// Conversion.
+    pub fn to_withdraw_nft_accounts(&self) -> quarry_mine::cpi::accounts::WithdrawNft<'info> {
+        self.stake.gen_withdraw_nft(&self.nft)
     }
 
-    /// Generates the accounts for minting replica tokens into a pool.
-    pub fn to_mint_accounts(&self) -> token::MintTo<'info> {
-        token::MintTo {
-            mint: self.replica_mint.to_account_info(),
-            to: self.replica_mint_token_account.to_account_info(),
+    /// Generates the accounts for moving the replica NFT from the [crate::MergePool]
+    /// to the [crate::MergeMiner].
+    pub fn to_checkout_accounts(&self) -> token::Transfer<'info> {
+        token::Transfer {
+            from: self.pool_replica_token_account.to_account_info(),
+            to: self.nft.mm_token_account.to_account_info(),
             authority: self.stake.pool.to_account_info(),
         }
     }
 
-    /// Generates the burn accounts for burning the replica tokens.
-    pub fn to_burn_accounts(&self) -> token::Burn<'info> {
-        token::Burn {
-            mint: self.replica_mint.to_account_info(),
-            to: self.replica_mint_token_account.to_account_info(),
+    /// Generates the accounts for returning the replica NFT from the [crate::MergeMiner]
+    /// to the [crate::MergePool].
+    pub fn to_return_accounts(&self) -> token::Transfer<'info> {
+        token::Transfer {
+            from: self.nft.mm_token_account.to_account_info(),
+            to: self.pool_replica_token_account.to_account_info(),
             authority: self.stake.mm.to_account_info(),
         }
     }
 }
 
-impl<'info> QuarryStakePrimary<'info> {
-    /// Conversion.
-    pub fn to_user_stake_accounts(&self) -> quarry_mine::cpi::accounts::UserStake<'info> {
-        self.stake.gen_user_stake(&self.mm_primary_token_account)
-    }
-}
-
 impl<'info> WithdrawTokens<'info> {
     /// Conversion.
     pub fn to_transfer_accounts(&self) -> Transfer<'info> {
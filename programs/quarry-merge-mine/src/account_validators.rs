@@ -2,14 +2,16 @@
 
 use anchor_lang::prelude::*;
 use vipers::validate::Validate;
-use vipers::{assert_ata, assert_keys, invariant};
+use vipers::{assert_ata, assert_keys};
 
 use crate::ClaimRewards;
 use crate::WithdrawTokens;
 use crate::{InitMergeMiner, QuarryStakePrimary};
 use crate::{InitMiner, QuarryStake};
+use crate::{InitReplicaNft, MergePool};
 use crate::{NewPool, QuarryStakeReplica};
 use anchor_lang::Key;
+use quarry_mine::{NftGate, QuarryKind};
 
 /// --------------------------------
 /// Instruction account structs
@@ -17,26 +19,7 @@ use anchor_lang::Key;
 
 impl<'info> Validate<'info> for NewPool<'info> {
     fn validate(&self) -> ProgramResult {
-        // replica_mint checks are now redundant
-        // since it is now an associated mint
-        assert_keys!(
-            self.replica_mint.mint_authority.unwrap(),
-            self.pool,
-            "replica_mint.mint_authority"
-        );
-        invariant!(
-            self.replica_mint.freeze_authority.is_none(),
-            "cannot have freeze authority"
-        );
-        invariant!(
-            self.replica_mint.decimals == self.primary_mint.decimals,
-            "decimals mismatch"
-        );
-        invariant!(
-            self.replica_mint.supply == 0,
-            "replica must have zero supply"
-        );
-
+        require!(self.primary_quarry.kind == QuarryKind::Nft, InvalidMiner);
         Ok(())
     }
 }
@@ -50,8 +33,8 @@ impl<'info> Validate<'info> for InitMergeMiner<'info> {
 impl<'info> Validate<'info> for InitMiner<'info> {
     fn validate(&self) -> ProgramResult {
         require!(
-            self.quarry.token_mint_key == self.pool.primary_mint
-                || self.quarry.token_mint_key == self.pool.replica_mint,
+            self.quarry.key() == self.pool.primary_quarry
+                || is_replica_quarry(&self.pool, &self.quarry),
             InvalidMiner
         );
 
@@ -61,11 +44,22 @@ impl<'info> Validate<'info> for InitMiner<'info> {
             *self.rewarder,
             "quarry.rewarder_key"
         );
-        assert_ata!(
-            self.miner_vault,
-            self.miner,
-            self.quarry.token_mint_key,
-            "miner_vault"
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for InitReplicaNft<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys!(self.mm.pool, self.pool, "mm.pool");
+        require!(
+            is_replica_quarry(&self.pool, &self.quarry),
+            InvalidReplicaQuarry
+        );
+        assert_keys!(
+            self.token_metadata_program,
+            metaplex_token_metadata::ID,
+            "token_metadata_program"
         );
 
         Ok(())
@@ -77,13 +71,10 @@ impl<'info> Validate<'info> for WithdrawTokens<'info> {
         let withdraw_mint = self.mm_token_account.mint;
 
         assert_keys!(self.withdraw_mint, withdraw_mint, "withdraw_mint");
-        // cannot withdraw a replica mint.
-        require!(
-            self.withdraw_mint.key() != self.pool.replica_mint,
-            CannotWithdrawReplicaMint
-        );
+        // replica NFTs are only ever held by the [MergeMiner] while being staked
+        // or unstaked, so they can never be withdrawn.
 
-        if withdraw_mint == self.pool.primary_mint {
+        if withdraw_mint == self.mm.primary_mint {
             // should be no replica balance if withdrawing primary
             require!(self.mm.replica_balance == 0, OutstandingReplicaTokens);
         }
@@ -137,12 +128,6 @@ impl<'info> Validate<'info> for ClaimRewards<'info> {
             *self.rewards_token_mint,
             "claim_fee_token_account.mint"
         );
-        assert_keys!(
-            self.stake_token_account.mint,
-            self.stake.quarry.token_mint_key,
-            "stake_token_account.mint"
-        );
-
         Ok(())
     }
 }
@@ -156,19 +141,19 @@ impl<'info> Validate<'info> for QuarryStakePrimary<'info> {
         self.stake.validate()?;
 
         // For primary staking:
-        // - quarry is a quarry, staking the `primary_mint`
-
+        // - quarry is the primary quarry of the pool
+        // - the NFT is held by the merge miner
         assert_keys!(self.mm_owner, self.stake.mm.owner, "mm_owner");
         assert_keys!(
-            self.stake.quarry.token_mint_key,
-            self.stake.pool.primary_mint,
-            "stake.quarry.token_mint_key"
+            *self.stake.quarry,
+            self.stake.pool.primary_quarry,
+            "stake.quarry"
         );
         assert_ata!(
-            self.mm_primary_token_account,
+            *self.nft.mm_token_account,
             self.stake.mm,
-            self.stake.pool.primary_mint,
-            "mm_primary_token_account"
+            *self.nft.token_mint,
+            "mm_token_account"
         );
 
         Ok(())
@@ -181,24 +166,28 @@ impl<'info> Validate<'info> for QuarryStakeReplica<'info> {
 
         // For replica staking:
         // - rewarder is any rewarder
-        // - quarry is a quarry staking the `replica_mint`
+        // - quarry is a quarry gated by the pool's update authority
+        // - the NFT is the merge miner's replica NFT for the quarry
         assert_keys!(self.mm_owner, self.stake.mm.owner, "mm_owner");
-
-        assert_keys!(
-            self.stake.pool.replica_mint,
-            self.replica_mint,
-            "stake.pool.replica_mint"
-        );
-        assert_keys!(
-            self.stake.quarry.token_mint_key,
-            self.replica_mint,
-            "stake.quarry.token_mint_key"
+        require!(
+            is_replica_quarry(&self.stake.pool, &self.stake.quarry),
+            InvalidReplicaQuarry
         );
+
+        let (replica_mint, _) =
+            find_replica_mint_address(&self.stake.mm.key(), &self.stake.quarry.key());
+        assert_keys!(*self.nft.token_mint, replica_mint, "replica_mint");
         assert_ata!(
-            self.replica_mint_token_account,
+            *self.nft.mm_token_account,
             self.stake.mm,
-            self.replica_mint,
-            "replica_mint_token_account"
+            replica_mint,
+            "mm_token_account"
+        );
+        assert_ata!(
+            *self.pool_replica_token_account,
+            self.stake.pool,
+            replica_mint,
+            "pool_replica_token_account"
         );
 
         Ok(())
@@ -212,14 +201,23 @@ impl<'info> Validate<'info> for QuarryStake<'info> {
         assert_keys!(*self.rewarder, self.quarry.rewarder_key, "rewarder");
         assert_keys!(*self.quarry, self.miner.quarry_key, "quarry");
         assert_keys!(self.miner.authority, self.mm, "miner.authority");
-        assert_keys!(self.miner_vault, self.miner.token_vault_key, "miner_vault");
-        assert_ata!(
-            self.miner_vault,
-            *self.miner,
-            self.quarry.token_mint_key,
-            "miner_vault"
-        );
 
         Ok(())
     }
 }
+
+/// Returns true if the [quarry_mine::Quarry] only accepts the replica NFTs of the [MergePool].
+fn is_replica_quarry(pool: &Account<MergePool>, quarry: &quarry_mine::Quarry) -> bool {
+    quarry.kind == QuarryKind::Nft
+        && quarry.nft_gate == NftGate::UpdateAuthority
+        && quarry.nft_update_authority == pool.key()
+}
+
+/// Finds the address of the replica NFT [anchor_spl::token::Mint] of a [crate::MergeMiner]
+/// for a replica [quarry_mine::Quarry].
+fn find_replica_mint_address(mm: &Pubkey, quarry: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"ReplicaMint", &mm.to_bytes(), &quarry.to_bytes()],
+        &crate::ID,
+    )
+}
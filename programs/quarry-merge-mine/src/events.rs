@@ -7,8 +7,8 @@ use crate::*;
 pub struct NewMergePoolEvent {
     /// The [MergePool].
     pub pool: Pubkey,
-    /// The [Mint] staked into the [MergePool].
-    pub primary_mint: Pubkey,
+    /// The [quarry_mine::Quarry] NFTs are staked into.
+    pub primary_quarry: Pubkey,
 }
 
 /// Emitted when a new [MergeMiner] is created.
@@ -18,8 +18,8 @@ pub struct InitMergeMinerEvent {
     pub pool: Pubkey,
     /// The [MergeMiner].
     pub mm: Pubkey,
-    /// The [Mint] of the primary token.
-    pub primary_mint: Pubkey,
+    /// The [quarry_mine::Quarry] NFTs are staked into.
+    pub primary_quarry: Pubkey,
     /// Owner of the [MergeMiner].
     pub owner: Pubkey,
 }
@@ -35,7 +35,20 @@ pub struct InitMinerEvent {
     pub miner: Pubkey,
 }
 
-/// Emitted when tokens are staked into the primary [quarry_mine::Miner].
+/// Emitted when a replica NFT is issued to a [MergeMiner].
+#[event]
+pub struct InitReplicaNftEvent {
+    /// The [MergePool].
+    pub pool: Pubkey,
+    /// The [MergeMiner].
+    pub mm: Pubkey,
+    /// The replica [quarry_mine::Quarry].
+    pub quarry: Pubkey,
+    /// The [Mint] of the replica NFT.
+    pub replica_mint: Pubkey,
+}
+
+/// Emitted when an NFT is staked into the primary [quarry_mine::Miner].
 #[event]
 pub struct StakePrimaryEvent {
     /// The [MergePool].
@@ -46,11 +59,11 @@ pub struct StakePrimaryEvent {
     pub miner: Pubkey,
     /// The owner of the [MergeMiner].
     pub owner: Pubkey,
-    /// Amount staked.
-    pub amount: u64,
+    /// The [Mint] of the NFT.
+    pub mint: Pubkey,
 }
 
-/// Emitted when tokens are staked into the replica [quarry_mine::Miner].
+/// Emitted when a replica NFT is staked into the replica [quarry_mine::Miner].
 #[event]
 pub struct StakeReplicaEvent {
     /// The [MergePool].
@@ -61,11 +74,11 @@ pub struct StakeReplicaEvent {
     pub miner: Pubkey,
     /// The owner of the [MergeMiner].
     pub owner: Pubkey,
-    /// Amount staked.
-    pub amount: u64,
+    /// The [Mint] of the NFT.
+    pub mint: Pubkey,
 }
 
-/// Emitted when tokens are unstaked from the primary [quarry_mine::Miner].
+/// Emitted when an NFT is unstaked from the primary [quarry_mine::Miner].
 #[event]
 pub struct UnstakePrimaryEvent {
     /// The [MergePool].
@@ -76,11 +89,11 @@ pub struct UnstakePrimaryEvent {
     pub miner: Pubkey,
     /// The owner of the [MergeMiner].
     pub owner: Pubkey,
-    /// Amount unstaked.
-    pub amount: u64,
+    /// The [Mint] of the NFT.
+    pub mint: Pubkey,
 }
 
-/// Emitted when tokens are unstaked from the replica [quarry_mine::Miner].
+/// Emitted when a replica NFT is unstaked from the replica [quarry_mine::Miner].
 #[event]
 pub struct UnstakeReplicaEvent {
     /// The [MergePool].
@@ -91,8 +104,8 @@ pub struct UnstakeReplicaEvent {
     pub miner: Pubkey,
     /// The owner of the [MergeMiner].
     pub owner: Pubkey,
-    /// Amount unstaked.
-    pub amount: u64,
+    /// The [Mint] of the NFT.
+    pub mint: Pubkey,
 }
 
 /// Emitted when tokens are withdrawn from a [MergePool].
@@ -1,4 +1,8 @@
-//! Holds tokens to allow one depositor to mine multiple quarries at the same time.
+//! Holds NFTs to allow one depositor to mine multiple quarries at the same time.
+//!
+//! A [MergeMiner] stakes its NFT once into the primary quarry of its [MergePool].
+//! For every replica quarry, the [MergePool] issues the [MergeMiner] a replica NFT,
+//! which is staked for as long as the primary NFT remains staked.
 #![deny(rustdoc::all)]
 #![allow(rustdoc::missing_doc_code_examples)]
 
@@ -15,6 +19,7 @@ pub mod events;
 pub mod state;
 
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use vipers::validate::Validate;
 
@@ -28,10 +33,10 @@ declare_id!("BKqhDDq6hEiXAp1WgSE778sDj2Bnf4Zg1CPJ2TYoUTMb");
 pub mod quarry_merge_mine {
     use super::*;
 
-    /// Creates a new [MergePool].
+    /// Creates a new [MergePool] for a primary [quarry_mine::Quarry].
     /// Anyone can call this.
     #[access_control(ctx.accounts.validate())]
-    pub fn new_pool(ctx: Context<NewPool>, bump: u8, _mint_bump: u8) -> ProgramResult {
+    pub fn new_pool(ctx: Context<NewPool>, bump: u8) -> ProgramResult {
         processor::init::new_pool(ctx, bump)
     }
 
@@ -48,40 +53,77 @@ pub mod quarry_merge_mine {
         processor::init::init_miner(ctx, bump)
     }
 
+    /// Issues the replica NFT a [MergeMiner] stakes into a replica [quarry_mine::Quarry].
+    /// The replica NFT is held by the [MergePool] whenever it is not staked.
+    #[access_control(ctx.accounts.validate())]
+    pub fn init_replica_nft(ctx: Context<InitReplicaNft>, _mint_bump: u8) -> ProgramResult {
+        processor::init::init_replica_nft(ctx)
+    }
+
     // --------------------------------
     // Deposit
     // --------------------------------
 
-    /// Deposits tokens into the [MergeMiner].
-    /// Before calling this, the owner should call the [token::transfer] instruction
-    /// to transfer to the [MergeMiner]'s [MergeMiner]::primary_token_account.
+    /// Stakes the NFT held by the [MergeMiner] into the primary [quarry_mine::Quarry].
+    /// Before calling this, the owner should call the [anchor_spl::token::transfer] instruction
+    /// to transfer the NFT to the [MergeMiner]'s ATA.
+    ///
+    /// If the primary [quarry_mine::Quarry] has a [quarry_mine::WeightTable], it must be passed
+    /// as the first remaining account.
     #[access_control(ctx.accounts.validate())]
-    pub fn stake_primary_miner(ctx: Context<QuarryStakePrimary>) -> ProgramResult {
-        processor::deposit::stake_primary_miner(ctx)
+    pub fn stake_primary_miner<'info>(
+        ctx: Context<'_, '_, '_, 'info, QuarryStakePrimary<'info>>,
+        metadata_bump: u8,
+        edition_bump: u8,
+        receipt_bump: u8,
+        weight_proof: Option<quarry_mine::WeightProof>,
+    ) -> ProgramResult {
+        processor::deposit::stake_primary_miner(
+            ctx,
+            metadata_bump,
+            edition_bump,
+            receipt_bump,
+            weight_proof,
+        )
     }
 
-    /// Stakes all possible replica tokens into a [quarry_mine::Quarry].
-    /// Before calling this, the owner should call the [anchor_spl::token::transfer] instruction
-    /// to transfer to the [MergeMiner]'s primary token ATA.
+    /// Stakes the [MergeMiner]'s replica NFT into a replica [quarry_mine::Quarry].
+    ///
+    /// If the replica [quarry_mine::Quarry] has a [quarry_mine::WeightTable], it must be passed
+    /// as the first remaining account.
     #[access_control(ctx.accounts.validate())]
-    pub fn stake_replica_miner(ctx: Context<QuarryStakeReplica>) -> ProgramResult {
-        processor::deposit::stake_replica_miner(ctx)
+    pub fn stake_replica_miner<'info>(
+        ctx: Context<'_, '_, '_, 'info, QuarryStakeReplica<'info>>,
+        metadata_bump: u8,
+        edition_bump: u8,
+        receipt_bump: u8,
+    ) -> ProgramResult {
+        processor::deposit::stake_replica_miner(ctx, metadata_bump, edition_bump, receipt_bump)
     }
 
     // --------------------------------
     // Withdraw
     // --------------------------------
 
-    /// Withdraws tokens from the [MergeMiner].
+    /// Unstakes the NFT from the primary [quarry_mine::Quarry] into the [MergeMiner]'s ATA.
     #[access_control(ctx.accounts.validate())]
-    pub fn unstake_primary_miner(ctx: Context<QuarryStakePrimary>, amount: u64) -> ProgramResult {
-        processor::withdraw::unstake_primary_miner(ctx, amount)
+    pub fn unstake_primary_miner(
+        ctx: Context<QuarryStakePrimary>,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        processor::withdraw::unstake_primary_miner(ctx, metadata_bump, edition_bump)
     }
 
-    /// Unstakes all of a [MergeMiner]'s replica tokens for a [quarry_mine::Miner].
+    /// Unstakes the [MergeMiner]'s replica NFT from a replica [quarry_mine::Quarry],
+    /// returning it to the [MergePool].
     #[access_control(ctx.accounts.validate())]
-    pub fn unstake_all_replica_miner(ctx: Context<QuarryStakeReplica>) -> ProgramResult {
-        processor::withdraw::unstake_all_replica_miner(ctx)
+    pub fn unstake_replica_miner(
+        ctx: Context<QuarryStakeReplica>,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        processor::withdraw::unstake_replica_miner(ctx, metadata_bump, edition_bump)
     }
 
     /// Withdraws tokens from the [MergeMiner].
@@ -107,57 +149,36 @@ pub mod quarry_merge_mine {
 
 /// [quarry_merge_mine::new_pool] accounts
 #[derive(Accounts)]
-#[instruction(bump: u8, mint_bump: u8)]
+#[instruction(bump: u8)]
 pub struct NewPool<'info> {
     /// [MergePool].
     #[account(
         init,
         seeds = [
           b"MergePool",
-          primary_mint.key().to_bytes().as_ref()
+          primary_quarry.key().to_bytes().as_ref()
         ],
         bump = bump,
         payer = payer
     )]
     pub pool: Account<'info, MergePool>,
 
-    /// [Mint] of the primary (underlying) token.
-    pub primary_mint: Account<'info, Mint>,
-
-    /// [Mint] of the replica token.
-    #[account(
-        init,
-        seeds = [
-            b"ReplicaMint",
-            pool.key().to_bytes().as_ref()
-        ],
-        mint::decimals = primary_mint.decimals,
-        mint::authority = pool,
-        bump = mint_bump,
-        payer = payer,
-        space = Mint::LEN
-    )]
-    pub replica_mint: Account<'info, Mint>,
+    /// The [quarry_mine::Quarry] NFTs are staked into.
+    pub primary_quarry: Box<Account<'info, quarry_mine::Quarry>>,
 
     /// Payer of the created [MergePool].
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    /// [Token] program.
-    pub token_program: Program<'info, Token>,
-
     /// [System] program.
     pub system_program: Program<'info, System>,
-
-    /// [Rent] sysvar.
-    pub rent: Sysvar<'info, Rent>,
 }
 
 /// [quarry_merge_mine::init_merge_miner] accounts
 #[derive(Accounts)]
 #[instruction(bump: u8)]
 pub struct InitMergeMiner<'info> {
-    /// [MergePool] of the underlying LP token.
+    /// [MergePool] of the underlying NFTs.
     pub pool: Account<'info, MergePool>,
 
     /// Owner of the [MergeMiner].
@@ -204,11 +225,8 @@ pub struct InitMiner<'info> {
     /// [quarry_mine::Rewarder].
     pub rewarder: Box<Account<'info, quarry_mine::Rewarder>>,
 
-    /// [Mint] of the Quarry token.
-    pub token_mint: Box<Account<'info, Mint>>,
-
-    /// [TokenAccount] holding the token [Mint].
-    pub miner_vault: Account<'info, TokenAccount>,
+    /// NFT update authority of the [quarry_mine::Quarry].
+    pub nft_update_authority: UncheckedAccount<'info>,
 
     /// Payer of [quarry_mine::Miner] creation.
     #[account(mut)]
@@ -224,6 +242,72 @@ pub struct InitMiner<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+/// [quarry_merge_mine::init_replica_nft] accounts
+#[derive(Accounts)]
+#[instruction(mint_bump: u8)]
+pub struct InitReplicaNft<'info> {
+    /// The [MergePool], aka the update authority of the replica NFT.
+    pub pool: Account<'info, MergePool>,
+
+    /// The [MergeMiner] the replica NFT is issued to.
+    pub mm: Account<'info, MergeMiner>,
+
+    /// The replica [quarry_mine::Quarry] the replica NFT is staked into.
+    pub quarry: Box<Account<'info, quarry_mine::Quarry>>,
+
+    /// [Mint] of the replica NFT.
+    #[account(
+        init,
+        seeds = [
+            b"ReplicaMint",
+            mm.key().to_bytes().as_ref(),
+            quarry.key().to_bytes().as_ref()
+        ],
+        mint::decimals = 0,
+        mint::authority = pool,
+        bump = mint_bump,
+        payer = payer,
+        space = Mint::LEN
+    )]
+    pub replica_mint: Box<Account<'info, Mint>>,
+
+    /// [TokenAccount] of the [MergePool] holding the replica NFT while it is not staked.
+    #[account(
+        init,
+        associated_token::mint = replica_mint,
+        associated_token::authority = pool,
+        payer = payer
+    )]
+    pub pool_replica_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Metadata of the replica NFT, created by the token metadata program.
+    #[account(mut)]
+    pub replica_metadata: UncheckedAccount<'info>,
+
+    /// Master edition of the replica NFT, created by the token metadata program.
+    #[account(mut)]
+    pub replica_edition: UncheckedAccount<'info>,
+
+    /// Payer of the replica NFT.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The token metadata program.
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    /// [Token] program.
+    pub token_program: Program<'info, Token>,
+
+    /// [AssociatedToken] program.
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
+
+    /// [Rent] sysvar.
+    pub rent: Sysvar<'info, Rent>,
+}
+
 /// [quarry_merge_mine::withdraw_tokens] accounts
 #[derive(Accounts)]
 pub struct WithdrawTokens<'info> {
@@ -238,7 +322,6 @@ pub struct WithdrawTokens<'info> {
     /// The [Mint] being withdrawn from the [MergeMiner].
     pub withdraw_mint: Account<'info, Mint>,
     /// A [TokenAccount] owned by the [MergeMiner] to withdraw from.
-    /// Must be the [MergePool::primary_mint] or the [MergePool::replica_mint].
     #[account(mut)]
     pub mm_token_account: Account<'info, TokenAccount>,
     /// Account to send tokens to.
@@ -273,47 +356,46 @@ pub struct ClaimRewards<'info> {
     #[account(mut)]
     pub claim_fee_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// Arbitrary account holding the [Mint] of the quarry staked token.
-    /// Passed to [quarry_mine] but unused.
-    #[account(mut)]
-    pub stake_token_account: Box<Account<'info, TokenAccount>>,
-
     /// User's stake.
     pub stake: QuarryStake<'info>,
 }
 
-/// [quarry_merge_mine::stake_primary_miner] accounts
+/// [quarry_merge_mine::stake_primary_miner] and [quarry_merge_mine::unstake_primary_miner] accounts
 #[derive(Accounts)]
 pub struct QuarryStakePrimary<'info> {
-    /// The [MergeMiner::owner].
+    /// The [MergeMiner::owner]. Pays for the [quarry_mine::StakeReceipt].
+    #[account(mut)]
     pub mm_owner: Signer<'info>,
 
-    /// The [TokenAccount] holding the [MergeMiner]'s primary tokens.
-    #[account(mut)]
-    pub mm_primary_token_account: Account<'info, TokenAccount>,
+    /// The staked NFT.
+    pub nft: MergeMinerNft<'info>,
 
     /// Staking accounts for the [quarry_mine::Quarry].
     pub stake: QuarryStake<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
 }
 
-/// [quarry_merge_mine::stake_replica_miner] accounts
+/// [quarry_merge_mine::stake_replica_miner] and [quarry_merge_mine::unstake_replica_miner] accounts
 #[derive(Accounts)]
 pub struct QuarryStakeReplica<'info> {
-    /// The [MergeMiner::owner].
+    /// The [MergeMiner::owner]. Pays for the [quarry_mine::StakeReceipt].
+    #[account(mut)]
     pub mm_owner: Signer<'info>,
 
-    /// [Mint] of a token that can be staked into a farming program.
-    /// This token should not be distributed to users, as it can depeg and can cause minters to lose their funds.
-    /// The [MergePool] must be the `mint_authority` and the `freeze_authority`.
+    /// [TokenAccount] of the [MergePool] holding the replica NFT while it is not staked.
     #[account(mut)]
-    pub replica_mint: Account<'info, Mint>,
+    pub pool_replica_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// The [TokenAccount] holding the [MergeMiner]'s minted pool tokens.
-    #[account(mut)]
-    pub replica_mint_token_account: Account<'info, TokenAccount>,
+    /// The staked replica NFT.
+    pub nft: MergeMinerNft<'info>,
 
     /// Staking accounts for the [quarry_mine::Quarry].
     pub stake: QuarryStake<'info>,
+
+    /// [System] program.
+    pub system_program: Program<'info, System>,
 }
 
 // --------------------------------
@@ -342,20 +424,38 @@ pub struct QuarryStake<'info> {
     #[account(mut)]
     pub miner: Box<Account<'info, quarry_mine::Miner>>,
 
-    /// The [TokenAccount] of the [quarry_mine::Miner] that holds the staked tokens.
-    #[account(mut)]
-    pub miner_vault: Account<'info, TokenAccount>,
-
     /// [anchor_spl::token] program.
     pub token_program: Program<'info, Token>,
 
     /// [quarry_mine] program.
     pub mine_program: Program<'info, quarry_mine::program::QuarryMine>,
+}
+
+/// Accounts of an NFT staked by a [MergeMiner].
+///
+/// The NFT metadata and edition are validated by [quarry_mine].
+#[derive(Accounts)]
+pub struct MergeMinerNft<'info> {
+    /// [Mint] of the NFT.
+    pub token_mint: Box<Account<'info, Mint>>,
+
+    /// Metadata of the NFT.
+    pub token_metadata: UncheckedAccount<'info>,
+
+    /// Master edition or edition of the NFT.
+    pub token_edition: UncheckedAccount<'info>,
+
+    /// [TokenAccount] of the [MergeMiner] holding the NFT while it is not staked.
+    #[account(mut)]
+    pub mm_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// NFT vault of the [quarry_mine::Miner].
+    #[account(mut)]
+    pub miner_nft_vault: Box<Account<'info, TokenAccount>>,
 
-    /// Unused variable used as a filler for deprecated accounts. Handled by [quarry_mine].
-    /// One should pass in a randomly generated Keypair for this account.
+    /// [quarry_mine::StakeReceipt] of the NFT.
     #[account(mut)]
-    pub unused_account: UncheckedAccount<'info>,
+    pub stake_receipt: UncheckedAccount<'info>,
 }
 
 /// Error Codes
@@ -371,4 +471,12 @@ pub enum ErrorCode {
     CannotWithdrawReplicaMint,
     #[msg("User must first withdraw from all replica quarries.")]
     OutstandingReplicaTokens,
+    #[msg("Merge miner already holds an NFT.")]
+    NftAlreadyStaked,
+    #[msg("Merge miner has no NFT staked into the primary quarry.")]
+    NoPrimaryNft,
+    #[msg("Quarry is not a replica of the merge pool.")]
+    InvalidReplicaQuarry,
+    #[msg("Primary quarry must stake NFTs.")]
+    InvalidPrimaryQuarry,
 }
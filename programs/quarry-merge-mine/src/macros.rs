@@ -4,7 +4,7 @@ macro_rules! gen_pool_signer_seeds {
     ($pool:expr) => {
         &[
             b"MergePool" as &[u8],
-            &$pool.primary_mint.to_bytes(),
+            &$pool.primary_quarry.to_bytes(),
             &[$pool.bump],
         ]
     };
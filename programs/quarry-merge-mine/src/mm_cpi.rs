@@ -3,16 +3,99 @@
 
 use crate::events::WithdrawTokensEvent;
 use crate::{
-    ClaimRewards, InitMiner, MergeMiner, QuarryStakePrimary, QuarryStakeReplica, WithdrawTokens,
+    ClaimRewards, InitMiner, InitReplicaNft, MergeMiner, MergePool, QuarryStakePrimary,
+    QuarryStakeReplica, WithdrawTokens,
 };
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke_signed;
 use anchor_lang::Key;
 use anchor_spl::token;
 use vipers::unwrap_int;
 
+/// Name of replica NFTs.
+const REPLICA_NAME: &str = "Quarry Merge Mine Replica";
+/// Symbol of replica NFTs.
+const REPLICA_SYMBOL: &str = "REPLICA";
+
+impl MergePool {
+    /// Mints a replica NFT to the [MergePool] and creates its metadata and master edition.
+    pub fn create_replica_nft(&self, init: &InitReplicaNft) -> ProgramResult {
+        let seeds = gen_pool_signer_seeds!(self);
+        let signer_seeds = &[&seeds[..]];
+
+        // Mint the only replica token to the pool
+        token::mint_to(
+            CpiContext::new_with_signer(
+                init.token_program.to_account_info(),
+                token::MintTo {
+                    mint: init.replica_mint.to_account_info(),
+                    to: init.pool_replica_token_account.to_account_info(),
+                    authority: init.pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+
+        invoke_signed(
+            &metaplex_token_metadata::instruction::create_metadata_accounts(
+                metaplex_token_metadata::ID,
+                init.replica_metadata.key(),
+                init.replica_mint.key(),
+                init.pool.key(),
+                init.payer.key(),
+                init.pool.key(),
+                REPLICA_NAME.to_string(),
+                REPLICA_SYMBOL.to_string(),
+                String::new(),
+                None,
+                0,
+                true,
+                false,
+            ),
+            &[
+                init.replica_metadata.to_account_info(),
+                init.replica_mint.to_account_info(),
+                init.pool.to_account_info(),
+                init.payer.to_account_info(),
+                init.system_program.to_account_info(),
+                init.rent.to_account_info(),
+                init.token_metadata_program.to_account_info(),
+            ],
+            signer_seeds,
+        )?;
+
+        // Moves the mint authority to the edition, so no more replicas can be minted
+        invoke_signed(
+            &metaplex_token_metadata::instruction::create_master_edition(
+                metaplex_token_metadata::ID,
+                init.replica_edition.key(),
+                init.replica_mint.key(),
+                init.pool.key(),
+                init.pool.key(),
+                init.replica_metadata.key(),
+                init.payer.key(),
+                Some(0),
+            ),
+            &[
+                init.replica_edition.to_account_info(),
+                init.replica_mint.to_account_info(),
+                init.pool.to_account_info(),
+                init.payer.to_account_info(),
+                init.replica_metadata.to_account_info(),
+                init.token_program.to_account_info(),
+                init.system_program.to_account_info(),
+                init.rent.to_account_info(),
+                init.token_metadata_program.to_account_info(),
+            ],
+            signer_seeds,
+        )
+    }
+}
+
 impl MergeMiner {
     /// Initializes a [quarry_mine::Miner] for the [MergeMiner].
-    pub fn init_miner(&self, init: &InitMiner, bump: u8, metadata_bump: u8) -> ProgramResult {
+    pub fn init_miner(&self, init: &InitMiner, bump: u8) -> ProgramResult {
         let seeds = gen_merge_miner_signer_seeds!(self);
         let signer_seeds = &[&seeds[..]];
         let cpi_ctx = CpiContext::new_with_signer(
@@ -20,116 +103,133 @@ impl MergeMiner {
             init.to_create_miner_accounts(),
             signer_seeds,
         );
-        quarry_mine::cpi::create_miner(cpi_ctx, bump, metadata_bump)
+        quarry_mine::cpi::create_miner(cpi_ctx, bump)
     }
 
-    /// Stakes all available primary tokens owned by the [MergeMiner] into the primary miner.
-    /// Returns the number of tokens deposited.
-    pub fn stake_max_primary_miner(&self, stake: &QuarryStakePrimary) -> Result<u64, ProgramError> {
-        let amount = stake.mm_primary_token_account.amount;
-        // short circuit in case there is nothing to stake
-        if amount == 0 {
-            return Ok(0);
-        }
-
-        // Deposit tokens
+    /// Stakes the NFT held by the [MergeMiner] into the primary miner.
+    pub fn stake_primary_miner<'info>(
+        &self,
+        stake: &QuarryStakePrimary<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+        metadata_bump: u8,
+        edition_bump: u8,
+        receipt_bump: u8,
+        weight_proof: Option<quarry_mine::WeightProof>,
+    ) -> ProgramResult {
         let seeds = gen_merge_miner_signer_seeds!(self);
         let signer_seeds = &[&seeds[..]];
         let cpi_ctx = CpiContext::new_with_signer(
             stake.stake.mine_program.to_account_info(),
-            stake.to_user_stake_accounts(),
+            stake.to_stake_nft_accounts(),
             signer_seeds,
-        );
-        quarry_mine::cpi::stake_tokens(cpi_ctx, amount)?;
-        Ok(amount)
+        )
+        .with_remaining_accounts(remaining_accounts.to_vec());
+        quarry_mine::cpi::stake_nft(
+            cpi_ctx,
+            1,
+            metadata_bump,
+            edition_bump,
+            receipt_bump,
+            weight_proof,
+            None,
+        )
     }
 
-    /// Mints the maximum number of replica tokens for the [crate::MergeMiner],
-    /// staking them into a [quarry_mine::Miner].
-    pub fn stake_max_replica_miner(&self, stake: &QuarryStakeReplica) -> Result<u64, ProgramError> {
-        // amount of tokens to mint
-        // this is the current balance of primary tokens minus the miner's balance
-        let amount = unwrap_int!(self.primary_balance.checked_sub(stake.stake.miner.balance));
-        // short circuit in case there is nothing to stake
-        if amount == 0 {
-            return Ok(0);
-        }
-
-        // Mint replica tokens to stake in the pool
+    /// Moves the replica NFT of the [MergeMiner] out of the [MergePool],
+    /// staking it into a replica [quarry_mine::Miner].
+    pub fn stake_replica_miner<'info>(
+        &self,
+        stake: &QuarryStakeReplica<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+        metadata_bump: u8,
+        edition_bump: u8,
+        receipt_bump: u8,
+    ) -> ProgramResult {
+        // Check out the replica NFT from the pool
         let seeds = gen_pool_signer_seeds!(stake.stake.pool);
         let signer_seeds = &[&seeds[..]];
-        let cpi_ctx = CpiContext::new_with_signer(
-            stake.stake.mine_program.to_account_info(),
-            stake.to_mint_accounts(),
-            signer_seeds,
-        );
-        token::mint_to(cpi_ctx, amount)?;
+        token::transfer(
+            CpiContext::new_with_signer(
+                stake.stake.token_program.to_account_info(),
+                stake.to_checkout_accounts(),
+                signer_seeds,
+            ),
+            1,
+        )?;
 
-        // Stake tokens
+        // Stake the replica NFT
         let seeds = gen_merge_miner_signer_seeds!(self);
         let signer_seeds = &[&seeds[..]];
         let cpi_ctx = CpiContext::new_with_signer(
             stake.stake.mine_program.to_account_info(),
-            stake.to_user_stake_accounts(),
+            stake.to_stake_nft_accounts(),
             signer_seeds,
-        );
-        quarry_mine::cpi::stake_tokens(cpi_ctx, amount)?;
-
-        Ok(amount)
+        )
+        .with_remaining_accounts(remaining_accounts.to_vec());
+        quarry_mine::cpi::stake_nft(
+            cpi_ctx,
+            1,
+            metadata_bump,
+            edition_bump,
+            receipt_bump,
+            None,
+            None,
+        )
     }
 
-    /// Unstakes from the primary miner.
-    pub fn unstake_primary_miner(&self, stake: &QuarryStakePrimary, amount: u64) -> ProgramResult {
-        require!(amount <= stake.stake.miner.balance, InsufficientBalance);
-
-        // noop if there is nothing to usntake
-        if amount == 0 {
-            return Ok(());
-        }
-
-        // Withdraw tokens
+    /// Unstakes the NFT from the primary miner into the [MergeMiner]'s ATA.
+    pub fn unstake_primary_miner(
+        &self,
+        stake: &QuarryStakePrimary,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
         let seeds = gen_merge_miner_signer_seeds!(self);
         let signer_seeds = &[&seeds[..]];
+        let mm_info = stake.stake.mm.to_account_info();
+        let pre_lamports = mm_info.lamports();
+
         let cpi_ctx = CpiContext::new_with_signer(
             stake.stake.mine_program.to_account_info(),
-            stake.to_user_stake_accounts(),
+            stake.to_withdraw_nft_accounts(),
             signer_seeds,
         );
-        quarry_mine::cpi::withdraw_tokens(cpi_ctx, amount)
+        quarry_mine::cpi::withdraw_nft(cpi_ctx, 1, metadata_bump, edition_bump)?;
+
+        refund_receipt_rent(&mm_info, &stake.mm_owner.to_account_info(), pre_lamports)
     }
 
-    /// Unstake tokens from a replica [quarry_mine::Miner] and burns the replica tokens.
-    pub fn unstake_all_and_burn_replica_miner(
+    /// Unstakes the replica NFT from a replica [quarry_mine::Miner],
+    /// returning it to the [MergePool].
+    pub fn unstake_replica_miner(
         &self,
         stake: &QuarryStakeReplica,
-    ) -> Result<u64, ProgramError> {
-        let amount = stake.stake.miner.balance;
-        // noop if there is nothing to unstake
-        if amount == 0 {
-            return Ok(0);
-        }
-
-        // Unstake tokens
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
         let seeds = gen_merge_miner_signer_seeds!(self);
         let signer_seeds = &[&seeds[..]];
-        let cpi_ctx = CpiContext::new_with_signer(
-            stake.stake.mine_program.to_account_info(),
-            stake.to_user_stake_accounts(),
-            signer_seeds,
-        );
-        quarry_mine::cpi::withdraw_tokens(cpi_ctx, amount)?;
+        let mm_info = stake.stake.mm.to_account_info();
+        let pre_lamports = mm_info.lamports();
 
-        // Burn replica tokens
-        let seeds = gen_merge_miner_signer_seeds!(stake.stake.mm);
-        let signer_seeds = &[&seeds[..]];
+        // Unstake the replica NFT
         let cpi_ctx = CpiContext::new_with_signer(
-            stake.stake.token_program.to_account_info(),
-            stake.to_burn_accounts(),
+            stake.stake.mine_program.to_account_info(),
+            stake.to_withdraw_nft_accounts(),
             signer_seeds,
         );
-        token::burn(cpi_ctx, amount)?;
+        quarry_mine::cpi::withdraw_nft(cpi_ctx, 1, metadata_bump, edition_bump)?;
+        refund_receipt_rent(&mm_info, &stake.mm_owner.to_account_info(), pre_lamports)?;
 
-        Ok(amount)
+        // Return the replica NFT to the pool
+        token::transfer(
+            CpiContext::new_with_signer(
+                stake.stake.token_program.to_account_info(),
+                stake.to_return_accounts(),
+                signer_seeds,
+            ),
+            1,
+        )
     }
 
     /// Withdraws tokens from the [MergeMiner].
@@ -173,3 +273,16 @@ impl MergeMiner {
         quarry_mine::cpi::claim_rewards(cpi_ctx)
     }
 }
+
+/// Forwards the [quarry_mine::StakeReceipt] rent that [quarry_mine] refunds to the
+/// [MergeMiner] on withdrawal to the owner who paid for it.
+fn refund_receipt_rent(
+    mm: &AccountInfo,
+    mm_owner: &AccountInfo,
+    pre_lamports: u64,
+) -> ProgramResult {
+    let refund = unwrap_int!(mm.lamports().checked_sub(pre_lamports));
+    **mm.try_borrow_mut_lamports()? = pre_lamports;
+    **mm_owner.try_borrow_mut_lamports()? = unwrap_int!(mm_owner.lamports().checked_add(refund));
+    Ok(())
+}
@@ -4,23 +4,40 @@ use crate::{events::*, QuarryStakePrimary, QuarryStakeReplica};
 use anchor_lang::prelude::*;
 use vipers::*;
 
-/// Deposits tokens into the [MergeMiner].
-/// Before calling this, the owner should call the [token::transfer] instruction
-/// to transfer to the [MergeMiner]'s [MergeMiner]::primary_token_account.
-pub fn stake_primary_miner(ctx: Context<QuarryStakePrimary>) -> ProgramResult {
+/// Stakes the NFT held by the [MergeMiner] into the primary [quarry_mine::Quarry].
+/// Before calling this, the owner should call the [anchor_spl::token::transfer] instruction
+/// to transfer the NFT to the [MergeMiner]'s ATA.
+pub fn stake_primary_miner<'info>(
+    ctx: Context<'_, '_, '_, 'info, QuarryStakePrimary<'info>>,
+    metadata_bump: u8,
+    edition_bump: u8,
+    receipt_bump: u8,
+    weight_proof: Option<quarry_mine::WeightProof>,
+) -> ProgramResult {
+    // A [MergeMiner] holds a single NFT, so that each replica NFT stands for exactly one NFT.
     let mm = &ctx.accounts.stake.mm;
-    let amount = mm.stake_max_primary_miner(ctx.accounts)?;
+    require!(mm.primary_balance == 0, NftAlreadyStaked);
+
+    mm.stake_primary_miner(
+        ctx.accounts,
+        ctx.remaining_accounts,
+        metadata_bump,
+        edition_bump,
+        receipt_bump,
+        weight_proof,
+    )?;
 
     // Update [MergeMiner]/[MergePool]
     let mm = &mut ctx.accounts.stake.mm;
-    mm.primary_balance = unwrap_int!(mm.primary_balance.checked_add(amount));
+    mm.primary_balance = 1;
+    mm.primary_mint = ctx.accounts.nft.token_mint.key();
     let pool = &mut ctx.accounts.stake.pool;
-    pool.total_primary_balance = unwrap_int!(pool.total_primary_balance.checked_add(amount));
+    pool.total_primary_balance = unwrap_int!(pool.total_primary_balance.checked_add(1));
 
-    ctx.accounts.stake.miner.reload()?;
+    ctx.accounts.nft.miner_nft_vault.reload()?;
     invariant!(
-        mm.primary_balance == ctx.accounts.stake.miner.balance,
-        "after ix, mm balance must be miner balance"
+        ctx.accounts.nft.miner_nft_vault.amount == 1,
+        "after ix, the NFT must be staked"
     );
 
     emit!(StakePrimaryEvent {
@@ -28,84 +45,77 @@ pub fn stake_primary_miner(ctx: Context<QuarryStakePrimary>) -> ProgramResult {
         mm: mm.key(),
         miner: ctx.accounts.stake.miner.key(),
         owner: mm.owner.key(),
-        amount,
+        mint: mm.primary_mint,
     });
 
     Ok(())
 }
 
-/// Stakes all possible replica tokens into a [quarry_mine::Quarry].
-/// Before calling this, the owner should call the [anchor_spl::token::transfer] instruction
-/// to transfer to the [MergeMiner]'s primary token ATA.
-pub fn stake_replica_miner(ctx: Context<QuarryStakeReplica>) -> ProgramResult {
+/// Stakes the [MergeMiner]'s replica NFT into a replica [quarry_mine::Quarry].
+pub fn stake_replica_miner<'info>(
+    ctx: Context<'_, '_, '_, 'info, QuarryStakeReplica<'info>>,
+    metadata_bump: u8,
+    edition_bump: u8,
+    receipt_bump: u8,
+) -> ProgramResult {
     // ! IMPORTANT NOTE !
-    // The replica mint issued to this pool could greatly exceed that of the balance.
-    // However, withdrawals are only possible if all of the replica miners are unstaked,
-    // since the total outstanding replica tokens issued to a given [MergeMiner] must be zero.
+    // Replica NFTs may only be staked while the primary NFT is staked.
+    // The primary NFT may only be unstaked once every replica NFT has been returned
+    // to the pool, so replica miners can never outlive the primary stake.
 
-    // This only works because there can only be one Quarry per mint per rewarder.
-    // If one desires multiple Quarries per mint per rewarder, one should incentivize a wrapper token.
+    // This only works because each replica NFT is bound to one [MergeMiner] and one quarry.
+    let mm = &ctx.accounts.stake.mm;
+    require!(mm.primary_balance == 1, NoPrimaryNft);
 
-    // pre-instruction checks
-    let pre_replica_mint_supply = ctx.accounts.replica_mint.supply;
-    invariant!(
-        ctx.accounts.stake.miner.balance == ctx.accounts.stake.miner_vault.amount,
-        "replica miner balance should equal miner_vault balance"
-    );
+    // noop if the replica NFT is already staked
+    if ctx.accounts.nft.miner_nft_vault.amount == 1 {
+        return Ok(());
+    }
 
-    let mm = &ctx.accounts.stake.mm;
-    let stake_amount = mm.stake_max_replica_miner(ctx.accounts)?;
+    mm.stake_replica_miner(
+        ctx.accounts,
+        ctx.remaining_accounts,
+        metadata_bump,
+        edition_bump,
+        receipt_bump,
+    )?;
 
     // Update replica balance
     let mm = &mut ctx.accounts.stake.mm;
-    mm.replica_balance = unwrap_int!(mm.replica_balance.checked_add(stake_amount));
+    mm.replica_balance = unwrap_int!(mm.replica_balance.checked_add(1));
     let pool = &mut ctx.accounts.stake.pool;
-    pool.total_replica_balance = unwrap_int!(pool.total_replica_balance.checked_add(stake_amount));
+    pool.total_replica_balance = unwrap_int!(pool.total_replica_balance.checked_add(1));
 
     emit!(StakeReplicaEvent {
         pool: pool.key(),
         mm: mm.key(),
         miner: ctx.accounts.stake.miner.key(),
         owner: mm.owner.key(),
-        amount: stake_amount,
+        mint: ctx.accounts.nft.token_mint.key(),
     });
 
     // post-instruction checks
-    post_stake_replica_miner(ctx, pre_replica_mint_supply, stake_amount)?;
+    post_stake_replica_miner(ctx)?;
 
     Ok(())
 }
 
 /// Checks run after [crate::quarry_merge_mine::stake_replica_miner].
-fn post_stake_replica_miner(
-    ctx: Context<QuarryStakeReplica>,
-    pre_replica_mint_supply: u64,
-    stake_amount: u64,
-) -> ProgramResult {
-    ctx.accounts.stake.miner.reload()?;
-    ctx.accounts.stake.miner_vault.reload()?;
-    ctx.accounts.replica_mint.reload()?;
-    ctx.accounts.replica_mint_token_account.reload()?;
-    invariant!(
-        unwrap_int!(ctx
-            .accounts
-            .replica_mint
-            .supply
-            .checked_sub(pre_replica_mint_supply))
-            == stake_amount,
-        "supply increase should equal the stake amount"
-    );
+fn post_stake_replica_miner(ctx: Context<QuarryStakeReplica>) -> ProgramResult {
+    ctx.accounts.nft.miner_nft_vault.reload()?;
+    ctx.accounts.nft.mm_token_account.reload()?;
+    ctx.accounts.pool_replica_token_account.reload()?;
     invariant!(
-        ctx.accounts.stake.mm.primary_balance == ctx.accounts.stake.miner.balance,
-        "replica miner balance should equal primary_balance"
+        ctx.accounts.nft.miner_nft_vault.amount == 1,
+        "replica NFT should be staked"
     );
     invariant!(
-        ctx.accounts.stake.miner.balance == ctx.accounts.stake.miner_vault.amount,
-        "replica miner balance should equal miner_vault balance"
+        ctx.accounts.nft.mm_token_account.amount == 0,
+        "mm replica NFT balance should be zero"
     );
     invariant!(
-        ctx.accounts.replica_mint_token_account.amount == 0,
-        "mm replica mint balance should be zero"
+        ctx.accounts.pool_replica_token_account.amount == 0,
+        "pool replica NFT balance should be zero"
     );
 
     Ok(())
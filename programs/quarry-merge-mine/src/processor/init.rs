@@ -1,6 +1,6 @@
 //! Account initialization-related instructions.
 
-use crate::{events::*, InitMergeMiner, InitMiner, NewPool};
+use crate::{events::*, InitMergeMiner, InitMiner, InitReplicaNft, NewPool};
 use anchor_lang::prelude::*;
 use vipers::*;
 
@@ -8,11 +8,9 @@ use vipers::*;
 /// Anyone can call this.
 pub fn new_pool(ctx: Context<NewPool>, bump: u8) -> ProgramResult {
     let pool = &mut ctx.accounts.pool;
-    pool.primary_mint = ctx.accounts.primary_mint.key();
+    pool.primary_quarry = ctx.accounts.primary_quarry.key();
     pool.bump = bump;
 
-    pool.replica_mint = ctx.accounts.replica_mint.key();
-
     pool.mm_count = 0;
 
     pool.total_primary_balance = 0;
@@ -20,7 +18,7 @@ pub fn new_pool(ctx: Context<NewPool>, bump: u8) -> ProgramResult {
 
     emit!(NewMergePoolEvent {
         pool: pool.key(),
-        primary_mint: pool.primary_mint,
+        primary_quarry: pool.primary_quarry,
     });
 
     Ok(())
@@ -41,13 +39,15 @@ pub fn init_merge_miner(ctx: Context<InitMergeMiner>, bump: u8) -> ProgramResult
     pool.mm_count = unwrap_int!(pool.mm_count.checked_add(1));
 
     mm.primary_balance = 0;
+    mm.replica_balance = 0;
+    mm.primary_mint = Pubkey::default();
 
-    let primary_mint = ctx.accounts.pool.primary_mint;
+    let primary_quarry = ctx.accounts.pool.primary_quarry;
 
     emit!(InitMergeMinerEvent {
         mm: mm.key(),
         pool: mm.pool,
-        primary_mint,
+        primary_quarry,
         owner: mm.owner,
     });
 
@@ -67,3 +67,18 @@ pub fn init_miner(ctx: Context<InitMiner>, bump: u8) -> ProgramResult {
 
     Ok(())
 }
+
+/// Issues the replica NFT of a [MergeMiner] for a replica [quarry_mine::Quarry].
+pub fn init_replica_nft(ctx: Context<InitReplicaNft>) -> ProgramResult {
+    let pool = &ctx.accounts.pool;
+    pool.create_replica_nft(ctx.accounts)?;
+
+    emit!(InitReplicaNftEvent {
+        pool: pool.key(),
+        mm: ctx.accounts.mm.key(),
+        quarry: ctx.accounts.quarry.key(),
+        replica_mint: ctx.accounts.replica_mint.key(),
+    });
+
+    Ok(())
+}
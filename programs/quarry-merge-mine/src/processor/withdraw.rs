@@ -4,31 +4,39 @@ use crate::{events::*, QuarryStakePrimary, QuarryStakeReplica, WithdrawTokens};
 use anchor_lang::prelude::*;
 use vipers::*;
 
-/// Withdraws tokens from the [MergeMiner].
-pub fn unstake_primary_miner(ctx: Context<QuarryStakePrimary>, amount: u64) -> ProgramResult {
-    // Check to see if the [MergeMiner] is fully collateralized after the withdraw
+/// Unstakes the NFT from the primary [quarry_mine::Quarry] into the [MergeMiner]'s ATA.
+pub fn unstake_primary_miner(
+    ctx: Context<QuarryStakePrimary>,
+    metadata_bump: u8,
+    edition_bump: u8,
+) -> ProgramResult {
     let mm = &ctx.accounts.stake.mm;
-    require!(amount <= mm.primary_balance, InsufficientBalance);
+    require!(mm.primary_balance == 1, NoPrimaryNft);
+    assert_keys!(
+        *ctx.accounts.nft.token_mint,
+        mm.primary_mint,
+        "primary_mint"
+    );
 
-    // There must be zero replica tokens if there is an unstaking of the primary miner.
+    // There must be no replica NFTs staked if the primary NFT is unstaked.
     // This is to ensure that the user cannot mine after withdrawing their stake.
-    // To perform a partial unstake, one should do a full unstake of each replica miner,
-    // unstake the desired amount, then stake back into each replica miner.
     require!(mm.replica_balance == 0, OutstandingReplicaTokens);
 
-    // Withdraw tokens to the [MergeMiner]'s account
-    mm.unstake_primary_miner(ctx.accounts, amount)?;
+    // Withdraw the NFT to the [MergeMiner]'s account
+    mm.unstake_primary_miner(ctx.accounts, metadata_bump, edition_bump)?;
 
     // Update [MergeMiner]/[MergePool]
     let mm = &mut ctx.accounts.stake.mm;
-    mm.primary_balance = unwrap_int!(mm.primary_balance.checked_sub(amount));
+    let mint = mm.primary_mint;
+    mm.primary_balance = 0;
+    mm.primary_mint = Pubkey::default();
     let pool = &mut ctx.accounts.stake.pool;
-    pool.total_primary_balance = unwrap_int!(pool.total_primary_balance.checked_sub(amount));
+    pool.total_primary_balance = unwrap_int!(pool.total_primary_balance.checked_sub(1));
 
     ctx.accounts.stake.miner.reload()?;
     invariant!(
-        mm.primary_balance == ctx.accounts.stake.miner.balance,
-        "after ix, mm balance must be miner balance"
+        ctx.accounts.stake.miner.balance == 0,
+        "after ix, primary miner balance must be zero"
     );
 
     emit!(UnstakePrimaryEvent {
@@ -36,43 +44,42 @@ pub fn unstake_primary_miner(ctx: Context<QuarryStakePrimary>, amount: u64) -> P
         mm: mm.key(),
         miner: ctx.accounts.stake.miner.key(),
         owner: mm.owner.key(),
-        amount,
+        mint,
     });
 
     Ok(())
 }
 
-/// Unstakes all of a [crate::MergeMiner]'s replica tokens for a [quarry_mine::Miner].
-pub fn unstake_all_replica_miner(ctx: Context<QuarryStakeReplica>) -> ProgramResult {
-    // pre-instruction checks
-    let pre_replica_mint_supply = ctx.accounts.replica_mint.supply;
-    invariant!(
-        ctx.accounts.stake.miner.balance == ctx.accounts.stake.miner_vault.amount,
-        "replica miner balance should equal miner_vault balance"
-    );
+/// Unstakes the [crate::MergeMiner]'s replica NFT from a replica [quarry_mine::Quarry].
+pub fn unstake_replica_miner(
+    ctx: Context<QuarryStakeReplica>,
+    metadata_bump: u8,
+    edition_bump: u8,
+) -> ProgramResult {
+    // noop if there is nothing to unstake
+    if ctx.accounts.nft.miner_nft_vault.amount == 0 {
+        return Ok(());
+    }
 
-    // Check to see if the merge miner is fully collateralized after the withdraw
     let mm = &ctx.accounts.stake.mm;
-
-    // Unstake and burn all replica tokens
-    let amount = mm.unstake_all_and_burn_replica_miner(ctx.accounts)?;
+    mm.unstake_replica_miner(ctx.accounts, metadata_bump, edition_bump)?;
 
     // Update replica balance
     let mm = &mut ctx.accounts.stake.mm;
-    mm.replica_balance = unwrap_int!(mm.replica_balance.checked_sub(amount));
+    mm.replica_balance = unwrap_int!(mm.replica_balance.checked_sub(1));
     let pool = &mut ctx.accounts.stake.pool;
-    pool.total_replica_balance = unwrap_int!(pool.total_replica_balance.checked_sub(amount));
+    pool.total_replica_balance = unwrap_int!(pool.total_replica_balance.checked_sub(1));
 
     emit!(UnstakeReplicaEvent {
         pool: pool.key(),
         mm: mm.key(),
         miner: ctx.accounts.stake.miner.key(),
         owner: mm.owner.key(),
-        amount,
+        mint: ctx.accounts.nft.token_mint.key(),
     });
 
     // post-instruction checks
-    post_unstake_replica_miner(ctx, pre_replica_mint_supply, amount)?;
+    post_unstake_replica_miner(ctx)?;
 
     Ok(())
 }
@@ -106,31 +113,26 @@ pub fn withdraw_tokens(ctx: Context<WithdrawTokens>) -> ProgramResult {
 }
 
 /// Checks run after [crate::quarry_merge_mine::unstake_replica_miner].
-fn post_unstake_replica_miner(
-    ctx: Context<QuarryStakeReplica>,
-    pre_replica_mint_supply: u64,
-    burn_amount: u64,
-) -> ProgramResult {
+fn post_unstake_replica_miner(ctx: Context<QuarryStakeReplica>) -> ProgramResult {
     ctx.accounts.stake.miner.reload()?;
-    ctx.accounts.stake.miner_vault.reload()?;
-    ctx.accounts.replica_mint.reload()?;
-    ctx.accounts.replica_mint_token_account.reload()?;
-    invariant!(
-        unwrap_int!(pre_replica_mint_supply.checked_sub(ctx.accounts.replica_mint.supply))
-            == burn_amount,
-        "supply increase should equal the stake amount"
-    );
+    ctx.accounts.nft.miner_nft_vault.reload()?;
+    ctx.accounts.nft.mm_token_account.reload()?;
+    ctx.accounts.pool_replica_token_account.reload()?;
     invariant!(
         ctx.accounts.stake.miner.balance == 0,
         "replica miner balance should be zero"
     );
     invariant!(
-        ctx.accounts.stake.miner.balance == ctx.accounts.stake.miner_vault.amount,
-        "replica miner balance should equal miner_vault balance"
+        ctx.accounts.nft.miner_nft_vault.amount == 0,
+        "replica NFT should be unstaked"
+    );
+    invariant!(
+        ctx.accounts.nft.mm_token_account.amount == 0,
+        "mm replica NFT balance should be zero"
     );
     invariant!(
-        ctx.accounts.replica_mint_token_account.amount == 0,
-        "mm replica mint balance should be zero"
+        ctx.accounts.pool_replica_token_account.amount == 1,
+        "replica NFT should be returned to the pool"
     );
 
     Ok(())
@@ -2,33 +2,34 @@
 
 use anchor_lang::prelude::*;
 
-/// A token that represents a locked other token.
+/// A pool of [MergeMiner]s staking NFTs into a primary [quarry_mine::Quarry].
+///
+/// The [MergePool] is the update authority of the replica NFTs it issues, so replica
+/// [quarry_mine::Quarry]s gate on [quarry_mine::NftGate::UpdateAuthority] with the
+/// [MergePool] as their `nft_update_authority`.
 ///
 /// To derive the address, use the following code:
 /// ```
 /// &[
 ///     b"MergePool" as &[u8],
-///     &$pool.primary_mint.to_bytes(),
+///     &$pool.primary_quarry.to_bytes(),
 ///     &[$pool.bump],
 /// ]
 /// ```
 #[account]
 #[derive(Copy, Debug, Default)]
 pub struct MergePool {
-    /// Mint of the underlying staked token, i.e. the [quarry_mine::Quarry::token_mint_key].
-    pub primary_mint: Pubkey,
+    /// The [quarry_mine::Quarry] NFTs are staked into.
+    pub primary_quarry: Pubkey,
     /// Bump seed.
     pub bump: u8,
 
-    /// Mint of the replica staked token, i.e. the [quarry_mine::Quarry::token_mint_key] of replicas.
-    pub replica_mint: Pubkey,
     /// Number of [MergeMiner]s tracked by the [MergePool].
     pub mm_count: u64,
 
-    /// Total number of primary tokens deposited.
-    /// Used for TVL calculation.
+    /// Total number of NFTs staked into the primary [quarry_mine::Quarry].
     pub total_primary_balance: u64,
-    /// Total number of replica tokens deposited.
+    /// Total number of replica NFTs staked into replica [quarry_mine::Quarry]s.
     pub total_replica_balance: u64,
 
     /// Reserved for future program upgrades.
@@ -59,10 +60,12 @@ pub struct MergeMiner {
     /// The index of the [MergeMiner] within the [MergePool].
     pub index: u64,
 
-    /// Amount of tokens staked into the primary quarry.
+    /// Number of NFTs staked into the primary quarry. A [MergeMiner] holds at most one NFT.
     pub primary_balance: u64,
-    /// Amount of replica tokens that have been issued to this [MergeMiner].
-    /// Primary tokens may only be withdrawn if [MergeMiner::primary_balance] == 0 and
-    /// [MergeMiner::replica_balance] == 0.
+    /// Number of replica NFTs staked into replica quarries.
+    /// The primary NFT may only be unstaked if [MergeMiner::replica_balance] == 0.
     pub replica_balance: u64,
+
+    /// Mint of the NFT staked into the primary quarry, or the default [Pubkey] if none.
+    pub primary_mint: Pubkey,
 }
@@ -7,12 +7,57 @@ use vipers::validate::Validate;
 use vipers::{assert_ata, assert_keys_eq};
 
 use crate::addresses;
+use crate::metadata::Metadata;
+use crate::nft;
 use crate::{
-    AcceptAuthority, ClaimRewards, CreateMiner, CreateQuarry, ExtractFees,
-    MutableRewarderWithAuthority, MutableRewarderWithPauseAuthority, NewRewarder,
-    ReadOnlyRewarderWithAuthority, SetAnnualRewards, SetFamine, SetPauseAuthority, SetRewardsShare,
-    TransferAuthority, UpdateQuarryRewards, UserClaim, UserStake,
+    AcceptAuthority, AcceptConfigAuthority, AcceptMinerAuthority, AddRewardStream, CancelChange,
+    ClaimForfeitedRewards, ClaimForfeitedRewardsFromVault, ClaimRewards, ClaimRewardsFromVault,
+    CloseMiner, CloseMinerVault, CloseQuarry, CloseRewarder, CreateFungibleQuarry, CreateMiner,
+    CreateQuarry, CreateWeightTable, ExecuteChange, ExtractFees, FundRewardsVault, Harvest,
+    HarvestFromVault, HarvestRewards, HarvestRewardsFromVault, InitConfig,
+    MutableConfigWithAuthority, MutableRewarderWithAuthority, MutableRewarderWithPauseAuthority,
+    NewRewarder, NewVaultRewarder, ProposeChange, ReadOnlyRewarderWithAuthority, SetAllowHarvest,
+    SetAnnualRewards, SetClaimFee, SetEmissionSchedule, SetFamine, SetFeeSplit, SetLockupTiers,
+    SetNftWeights, SetPauseAuthority, SetRewardStreamRate, SetRewardsShare, SetStartTs,
+    SetTimelockDelay, SetUnstakePenalties, SetWeightsMerkleRoot, StakeNft, StakeNftFrozen,
+    TransferAuthority, TransferMinerAuthority, UpdateQuarryRewards, UserClaim, UserStake,
+    UserStakeBatch, UserStakeFrozen, UserStakeTokens, WithdrawNft, WithdrawNftFrozen,
 };
+use crate::{Miner, Quarry, QuarryKind, RewarderFunding, StakeCustody};
+use anchor_spl::token::{Mint, TokenAccount};
+
+// --------------------------------
+// Protocol Functions
+// --------------------------------
+
+impl<'info> Validate<'info> for InitConfig<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.admin, addresses::FEE_SETTER, "admin");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for MutableConfigWithAuthority<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.authority, self.config.authority, "config authority");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for AcceptConfigAuthority<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(
+            self.config.pending_authority != Pubkey::default(),
+            PendingAuthorityNotSet
+        );
+        assert_keys_eq!(
+            self.authority,
+            self.config.pending_authority,
+            "pending authority"
+        );
+        Ok(())
+    }
+}
 
 // --------------------------------
 // Rewarder Functions
@@ -38,6 +83,49 @@ impl<'info> Validate<'info> for NewRewarder<'info> {
     }
 }
 
+impl<'info> Validate<'info> for NewVaultRewarder<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(self.base.is_signer, Unauthorized);
+
+        assert_ata!(
+            self.claim_fee_token_account,
+            self.rewarder,
+            self.rewards_token_mint
+        );
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for FundRewardsVault<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(
+            self.rewarder.funding == RewarderFunding::Vault,
+            InvalidRewarderFunding
+        );
+        assert_keys_eq!(
+            self.rewards_vault,
+            self.rewarder.rewards_vault,
+            "rewards_vault"
+        );
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SetClaimFee<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.fee_setter, self.config.fee_setter, "fee_setter");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SetFeeSplit<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.fee_setter, self.config.fee_setter, "fee_setter");
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for SetPauseAuthority<'info> {
     fn validate(&self) -> ProgramResult {
         self.auth.validate()?;
@@ -83,7 +171,15 @@ impl<'info> Validate<'info> for SetAnnualRewards<'info> {
     fn validate(&self) -> ProgramResult {
         self.auth.validate()?;
         require!(!self.auth.rewarder.is_paused, Paused);
-        Ok(())
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetEmissionSchedule<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        self.auth.rewarder.assert_not_timelocked()
     }
 }
 
@@ -99,12 +195,46 @@ impl<'info> Validate<'info> for CreateQuarry<'info> {
     }
 }
 
+impl<'info> Validate<'info> for SetTimelockDelay<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for ProposeChange<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for ExecuteChange<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.queued_change.rewarder, self.rewarder, "rewarder");
+        assert_keys_eq!(self.payer, self.queued_change.payer, "payer");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CancelChange<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        assert_keys_eq!(self.queued_change.rewarder, self.auth.rewarder, "rewarder");
+        assert_keys_eq!(self.payer, self.queued_change.payer, "payer");
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for SetRewardsShare<'info> {
     fn validate(&self) -> ProgramResult {
         self.auth.validate()?;
         require!(!self.auth.rewarder.is_paused, Paused);
         assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
-        Ok(())
+        self.auth.rewarder.assert_not_timelocked()
     }
 }
 
@@ -113,10 +243,152 @@ impl<'info> Validate<'info> for SetFamine<'info> {
         self.auth.validate()?;
         require!(!self.auth.rewarder.is_paused, Paused);
         assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for AddRewardStream<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetRewardStreamRate<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetStartTs<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetLockupTiers<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(self.quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetUnstakePenalties<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(self.quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for ClaimForfeitedRewards<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        require!(
+            self.rewarder.funding == RewarderFunding::MintWrapper,
+            InvalidRewarderFunding
+        );
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+
+        assert_keys_eq!(
+            self.mint_wrapper.token_mint,
+            self.rewards_token_mint,
+            "mint_wrapper.token_mint",
+        );
+        assert_keys_eq!(
+            self.minter.minter_authority,
+            self.rewarder,
+            "minter.minter_authority"
+        );
+        assert_keys_eq!(
+            self.rewards_token_mint,
+            self.rewarder.rewards_token_mint,
+            "rewards token mint",
+        );
+
+        assert_keys_eq!(*self.treasury, self.quarry.penalty_treasury, "treasury");
+        assert_keys_eq!(self.treasury.mint, self.rewards_token_mint, "treasury.mint");
+
         Ok(())
     }
 }
 
+impl<'info> Validate<'info> for ClaimForfeitedRewardsFromVault<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        require!(
+            self.rewarder.funding == RewarderFunding::Vault,
+            InvalidRewarderFunding
+        );
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+
+        assert_keys_eq!(
+            *self.rewards_vault,
+            self.rewarder.rewards_vault,
+            "rewards_vault"
+        );
+        assert_keys_eq!(*self.treasury, self.quarry.penalty_treasury, "treasury");
+        assert_keys_eq!(
+            self.treasury.mint,
+            self.rewarder.rewards_token_mint,
+            "treasury.mint"
+        );
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CreateWeightTable<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(self.quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        require!(
+            self.quarry.weights_merkle_root == [0; 32],
+            WeightSourceConflict
+        );
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetNftWeights<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        assert_keys_eq!(self.weight_table.quarry, self.quarry, "weight table quarry");
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
+impl<'info> Validate<'info> for SetWeightsMerkleRoot<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(self.quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+        require!(!self.auth.rewarder.is_paused, Paused);
+        assert_keys_eq!(self.quarry.rewarder_key, self.auth.rewarder, "rewarder");
+        require!(
+            self.quarry.weight_table == Pubkey::default(),
+            WeightSourceConflict
+        );
+        self.auth.rewarder.assert_not_timelocked()
+    }
+}
+
 impl<'info> Validate<'info> for UpdateQuarryRewards<'info> {
     fn validate(&self) -> ProgramResult {
         require!(!self.rewarder.is_paused, Paused);
@@ -129,6 +401,14 @@ impl<'info> Validate<'info> for UpdateQuarryRewards<'info> {
 /// Miner functions
 /// --------------------------------
 
+impl<'info> Validate<'info> for CreateFungibleQuarry<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.auth.validate()?;
+        require!(!self.auth.rewarder.is_paused, Paused);
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for CreateMiner<'info> {
     fn validate(&self) -> ProgramResult {
         require!(!self.rewarder.is_paused, Paused);
@@ -147,49 +427,209 @@ impl<'info> Validate<'info> for CreateMiner<'info> {
 
 impl<'info> UserStake<'info> {
     /// Validates the UserStake.
-    pub fn validate(&self, metadata_bump: u8) -> ProgramResult {
-        // metadata check
-        msg!(
-            "Received Metadata Pubkey {}",
-            self.token_metadata.key().to_string()
-        );
-
-        let expected_metadata = Pubkey::create_program_address(
-            &[
-                b"metadata",
-                &metadataProgramID.to_bytes(),
-                &self.token_mint.key().to_bytes(),
-                &[metadata_bump],
-            ],
-            &metadataProgramID,
+    pub fn validate(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        // rewarder is_paused
+        require!(!self.rewarder.is_paused, Paused);
+        self.validate_accounts(metadata_bump, edition_bump)
+    }
+
+    /// Validates the UserStake, whether or not the [crate::Rewarder] is paused.
+    pub fn validate_accounts(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        // authority
+        require!(self.authority.is_signer, Unauthorized);
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+
+        // quarry
+        assert_keys_eq!(self.miner.quarry_key, self.quarry.key(), "quarry");
+
+        validate_nft_accounts(
+            &self.authority.key(),
+            &self.miner,
+            &self.quarry,
+            &self.token_mint,
+            &self.token_metadata,
+            &self.token_edition,
+            &self.token_account,
+            metadata_bump,
+            edition_bump,
         )?;
+        validate_miner_nft_vault(&self.miner, &self.token_mint, &self.miner_nft_vault)?;
 
-        msg!("Expected Metadata Pubkey {}", expected_metadata.to_string());
+        // rewarder
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
 
+        Ok(())
+    }
+}
+
+/// Validates the accounts of an NFT staked into or withdrawn from a [Miner].
+#[allow(clippy::too_many_arguments)]
+pub fn validate_nft_accounts(
+    authority: &Pubkey,
+    miner: &Account<Miner>,
+    quarry: &Quarry,
+    token_mint: &Account<Mint>,
+    token_metadata: &Account<Metadata>,
+    token_edition: &AccountInfo,
+    token_account: &Account<TokenAccount>,
+    metadata_bump: u8,
+    edition_bump: u8,
+) -> ProgramResult {
+    require!(quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+
+    // metadata check
+    msg!(
+        "Received Metadata Pubkey {}",
+        token_metadata.key().to_string()
+    );
+
+    let expected_metadata = Pubkey::create_program_address(
+        &[
+            b"metadata",
+            &metadataProgramID.to_bytes(),
+            &token_mint.key().to_bytes(),
+            &[metadata_bump],
+        ],
+        &metadataProgramID,
+    )?;
+
+    msg!("Expected Metadata Pubkey {}", expected_metadata.to_string());
+
+    assert_keys_eq!(
+        token_metadata.to_account_info().key(),
+        expected_metadata,
+        "miner nft metadata"
+    );
+
+    // user's staked token_account
+    assert_ata!(
+        *token_account,
+        *authority,
+        *token_mint,
+        "authority staked token"
+    );
+
+    // token account's state owner check
+    assert_keys_eq!(token_account.owner, *authority, "token account");
+
+    // nft gate
+    quarry.assert_nft_gate(miner, token_metadata)?;
+
+    // the staked token must be an NFT
+    nft::assert_nft_mint(&token_mint.key(), token_mint, token_edition, edition_bump)?;
+
+    Ok(())
+}
+
+/// Validates the vault holding an NFT staked into a [Miner].
+pub fn validate_miner_nft_vault(
+    miner: &Account<Miner>,
+    token_mint: &Account<Mint>,
+    miner_nft_vault: &Account<TokenAccount>,
+) -> ProgramResult {
+    assert_ata!(*miner_nft_vault, *miner, *token_mint, "miner vault");
+    assert_keys_eq!(miner_nft_vault.owner, *miner, "nft vault owner");
+    Ok(())
+}
+
+impl<'info> StakeNft<'info> {
+    /// Validates the [StakeNft] accounts.
+    pub fn validate(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        self.stake.validate(metadata_bump, edition_bump)
+    }
+}
+
+impl<'info> WithdrawNft<'info> {
+    /// Validates the [WithdrawNft] accounts.
+    pub fn validate(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        self.stake.validate(metadata_bump, edition_bump)?;
+        self.validate_receipt()
+    }
+
+    /// Validates the [WithdrawNft] accounts of an emergency withdrawal.
+    pub fn validate_emergency(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        require!(self.stake.rewarder.is_paused, NotPaused);
+        self.stake.validate_accounts(metadata_bump, edition_bump)?;
+        self.validate_receipt()
+    }
+
+    fn validate_receipt(&self) -> ProgramResult {
+        require!(
+            self.stake_receipt.custody == StakeCustody::Vault,
+            StakeCustodyMismatch
+        );
+
+        assert_keys_eq!(self.stake_receipt.miner, self.stake.miner, "receipt miner");
         assert_keys_eq!(
-            self.token_metadata.to_account_info().key(),
-            expected_metadata,
-            "miner nft metadata"
+            self.stake_receipt.mint,
+            *self.stake.token_mint,
+            "receipt mint"
         );
+        assert_keys_eq!(self.rent_receiver, self.stake.authority, "rent receiver");
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for UserStakeTokens<'info> {
+    fn validate(&self) -> ProgramResult {
+        // rewarder is_paused
+        require!(!self.rewarder.is_paused, Paused);
+        self.validate_accounts()
+    }
+}
+
+impl<'info> UserStakeTokens<'info> {
+    /// Validates the [UserStakeTokens] accounts of an emergency withdrawal.
+    pub fn validate_emergency(&self) -> ProgramResult {
+        require!(self.rewarder.is_paused, NotPaused);
+        self.validate_accounts()
+    }
 
-        // miner_nft_vault
+    /// Validates the UserStakeTokens accounts, whether or not the [crate::Rewarder] is paused.
+    fn validate_accounts(&self) -> ProgramResult {
+        // authority
+        require!(self.authority.is_signer, Unauthorized);
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+
+        // quarry
+        assert_keys_eq!(self.miner.quarry_key, self.quarry, "quarry");
+        require!(self.quarry.kind == QuarryKind::Fungible, QuarryKindMismatch);
+
+        // miner_vault
         assert_ata!(
-            *self.miner_nft_vault,
+            self.miner_vault,
             self.miner,
-            *self.token_mint,
+            self.quarry.token_mint_key,
             "miner vault"
         );
+        assert_keys_eq!(self.miner_vault.owner, self.miner, "miner vault owner");
 
-        // user's staked token_account
-        assert_ata!(
-            self.token_account,
-            self.authority,
-            *self.token_mint,
-            "authority staked token"
+        // user's token_account
+        assert_keys_eq!(
+            self.token_account.mint,
+            self.quarry.token_mint_key,
+            "token account mint"
         );
+        assert_keys_eq!(self.token_account.owner, self.authority, "token account");
 
+        // rewarder
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+
+        Ok(())
+    }
+}
+
+impl<'info> UserStakeFrozen<'info> {
+    /// Validates the UserStakeFrozen.
+    pub fn validate(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
         // rewarder is_paused
         require!(!self.rewarder.is_paused, Paused);
+        self.validate_accounts(metadata_bump, edition_bump)
+    }
+
+    /// Validates the UserStakeFrozen, whether or not the [crate::Rewarder] is paused.
+    pub fn validate_accounts(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
         // authority
         require!(self.authority.is_signer, Unauthorized);
         assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
@@ -197,26 +637,78 @@ impl<'info> UserStake<'info> {
         // quarry
         assert_keys_eq!(self.miner.quarry_key, self.quarry.key(), "quarry");
 
-        // token account's state owner check
-        assert_keys_eq!(self.miner_nft_vault.owner, self.miner, "nft vault owner");
-        assert_keys_eq!(self.token_account.owner, self.authority, "token account");
-
-        // nft update authority
+        validate_nft_accounts(
+            &self.authority.key(),
+            &self.miner,
+            &self.quarry,
+            &self.token_mint,
+            &self.token_metadata,
+            &self.token_edition,
+            &self.token_account,
+            metadata_bump,
+            edition_bump,
+        )?;
         assert_keys_eq!(
-            self.token_metadata.update_authority,
-            self.miner.nft_update_authority,
-            "nft update authority",
+            self.token_metadata_program,
+            metadataProgramID,
+            "token metadata program"
         );
 
+        // rewarder
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+
+        Ok(())
+    }
+}
+
+impl<'info> StakeNftFrozen<'info> {
+    /// Validates the [StakeNftFrozen] accounts.
+    pub fn validate(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        self.stake.validate(metadata_bump, edition_bump)
+    }
+}
+
+impl<'info> WithdrawNftFrozen<'info> {
+    /// Validates the [WithdrawNftFrozen] accounts.
+    pub fn validate(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        self.stake.validate(metadata_bump, edition_bump)?;
+        self.validate_receipt()
+    }
+
+    /// Validates the [WithdrawNftFrozen] accounts of an emergency withdrawal.
+    pub fn validate_emergency(&self, metadata_bump: u8, edition_bump: u8) -> ProgramResult {
+        require!(self.stake.rewarder.is_paused, NotPaused);
+        self.stake.validate_accounts(metadata_bump, edition_bump)?;
+        self.validate_receipt()
+    }
+
+    fn validate_receipt(&self) -> ProgramResult {
+        require!(
+            self.stake_receipt.custody == StakeCustody::Frozen,
+            StakeCustodyMismatch
+        );
+
+        assert_keys_eq!(self.stake_receipt.miner, self.stake.miner, "receipt miner");
         assert_keys_eq!(
-            self.token_metadata.update_authority,
-            self.quarry.nft_update_authority,
-            "nft update authority"
+            self.stake_receipt.mint,
+            *self.stake.token_mint,
+            "receipt mint"
         );
+        assert_keys_eq!(self.rent_receiver, self.stake.authority, "rent receiver");
 
-        // rewarder
-        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+        Ok(())
+    }
+}
 
+impl<'info> Validate<'info> for UserStakeBatch<'info> {
+    /// Validates the accounts shared by all NFTs of a batch.
+    /// Each NFT is validated with [validate_nft_accounts] as it is processed.
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        require!(self.authority.is_signer, Unauthorized);
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+        assert_keys_eq!(self.miner.quarry_key, self.quarry, "quarry");
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
         Ok(())
     }
 }
@@ -226,6 +718,10 @@ impl<'info> Validate<'info> for ClaimRewards<'info> {
     fn validate(&self) -> ProgramResult {
         self.stake.validate()?;
         require!(!self.stake.rewarder.is_paused, Paused);
+        require!(
+            self.stake.rewarder.funding == RewarderFunding::MintWrapper,
+            InvalidRewarderFunding
+        );
 
         assert_keys_eq!(
             self.mint_wrapper.token_mint,
@@ -273,6 +769,186 @@ impl<'info> Validate<'info> for ClaimRewards<'info> {
     }
 }
 
+impl<'info> Validate<'info> for ClaimRewardsFromVault<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.stake.validate()?;
+        require!(
+            self.stake.rewarder.funding == RewarderFunding::Vault,
+            InvalidRewarderFunding
+        );
+
+        assert_keys_eq!(
+            *self.rewards_vault,
+            self.stake.rewarder.rewards_vault,
+            "rewards_vault"
+        );
+        assert_keys_eq!(
+            self.rewards_token_account.mint,
+            self.stake.rewarder.rewards_token_mint,
+            "rewards_token_account.mint",
+        );
+        assert_keys_eq!(
+            *self.claim_fee_token_account,
+            self.stake.rewarder.claim_fee_token_account,
+            "claim_fee_token_account"
+        );
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CloseMiner<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+        assert_keys_eq!(self.miner.quarry_key, self.quarry, "quarry");
+        require!(self.miner.is_closable(), MinerNotEmpty);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CloseMinerVault<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+        assert_keys_eq!(self.miner_vault.owner, self.miner, "miner vault owner");
+        require!(self.miner_vault.amount == 0, VaultNotEmpty);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CloseQuarry<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(
+            self.authority,
+            self.rewarder.authority,
+            "rewarder authority"
+        );
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for CloseRewarder<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(
+            self.authority,
+            self.rewarder.authority,
+            "rewarder authority"
+        );
+        require!(self.rewarder.num_quarries == 0, RewarderHasQuarries);
+        assert_keys_eq!(
+            self.claim_fee_token_account,
+            self.rewarder.claim_fee_token_account,
+            "claim_fee_token_account"
+        );
+        require!(self.claim_fee_token_account.amount == 0, FeesNotExtracted);
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for TransferMinerAuthority<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for AcceptMinerAuthority<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        require!(
+            self.miner.is_pending_authority(&self.new_authority.key()),
+            PendingAuthorityNotSet
+        );
+        assert_keys_eq!(
+            self.previous_authority,
+            self.miner.authority,
+            "previous authority"
+        );
+        assert_keys_eq!(self.miner.quarry_key, self.quarry, "quarry");
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for SetAllowHarvest<'info> {
+    fn validate(&self) -> ProgramResult {
+        assert_keys_eq!(self.authority, self.miner.authority, "miner authority");
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for Harvest<'info> {
+    fn validate(&self) -> ProgramResult {
+        // the miner is validated when harvesting
+        self.rewards.validate()
+    }
+}
+
+impl<'info> Validate<'info> for HarvestRewards<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        require!(
+            self.rewarder.funding == RewarderFunding::MintWrapper,
+            InvalidRewarderFunding
+        );
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+
+        assert_keys_eq!(
+            self.mint_wrapper.token_mint,
+            self.rewards_token_mint,
+            "mint_wrapper.token_mint",
+        );
+        assert_keys_eq!(
+            self.minter.minter_authority,
+            self.rewarder,
+            "minter.minter_authority"
+        );
+        assert_keys_eq!(
+            self.rewards_token_mint,
+            self.rewarder.rewards_token_mint,
+            "rewards token mint",
+        );
+        assert_keys_eq!(
+            *self.claim_fee_token_account,
+            self.rewarder.claim_fee_token_account,
+            "claim_fee_token_account"
+        );
+
+        Ok(())
+    }
+}
+
+impl<'info> Validate<'info> for HarvestFromVault<'info> {
+    fn validate(&self) -> ProgramResult {
+        // the miner is validated when harvesting
+        self.rewards.validate()
+    }
+}
+
+impl<'info> Validate<'info> for HarvestRewardsFromVault<'info> {
+    fn validate(&self) -> ProgramResult {
+        require!(!self.rewarder.is_paused, Paused);
+        require!(
+            self.rewarder.funding == RewarderFunding::Vault,
+            InvalidRewarderFunding
+        );
+        assert_keys_eq!(self.quarry.rewarder_key, self.rewarder, "rewarder");
+
+        assert_keys_eq!(
+            *self.rewards_vault,
+            self.rewarder.rewards_vault,
+            "rewards_vault"
+        );
+        assert_keys_eq!(
+            *self.claim_fee_token_account,
+            self.rewarder.claim_fee_token_account,
+            "claim_fee_token_account"
+        );
+
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for UserClaim<'info> {
     fn validate(&self) -> ProgramResult {
         require!(!self.rewarder.is_paused, Paused);
@@ -311,12 +987,12 @@ impl<'info> Validate<'info> for ExtractFees<'info> {
         );
         assert_keys_eq!(
             self.fee_to_token_account.owner,
-            addresses::FEE_TO,
+            self.config.fee_to,
             "fee_to_token_account.owner"
         );
         assert_ata!(
             self.fee_to_token_account,
-            addresses::FEE_TO,
+            self.config.fee_to,
             self.rewarder.rewards_token_mint,
             "fee ata"
         );
@@ -1,5 +1,6 @@
 //! Contains addresses used for the Quarry program.
-//! These addresses are updated via program upgrades.
+//! These are the initial addresses of the [crate::Config], which governance may update
+//! without a program upgrade.
 
 use anchor_lang::prelude::*;
 
@@ -17,8 +18,9 @@ pub mod fee_setter {
     declare_id!("4MMZH3ih1aSty2nx4MC3kSR94Zb55XsXnqb5jfEcyHWQ");
 }
 
-/// Account authorized to take fees.
+/// Initial account authorized to take fees.
 pub static FEE_TO: Pubkey = fee_to::ID;
 
-/// Account authorized to set fees of a rewarder. Currently unused.
+/// Initial account authorized to set fees of a rewarder, with [crate::quarry_mine::set_claim_fee].
+/// This account also initializes the [crate::Config].
 pub static FEE_SETTER: Pubkey = fee_setter::ID;
@@ -0,0 +1,310 @@
+//! Staking and withdrawing many NFTs in a single instruction.
+//!
+//! The accounts of each NFT are passed as remaining accounts, [BatchNft::NUM_ACCOUNTS]
+//! per NFT, in the order of the fields of [BatchNft]. When staking into a [crate::Quarry]
+//! with a [crate::WeightTable], the table must precede the accounts of the NFTs.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::system_instruction;
+use anchor_lang::{AccountsClose, Key};
+use anchor_spl::token::{self, Mint, TokenAccount};
+use std::io::Cursor;
+use vipers::{assert_keys_eq, unwrap_int};
+
+use crate::account_validators::{validate_miner_nft_vault, validate_nft_accounts};
+use crate::metadata::Metadata;
+use crate::penalty::{self, WithdrawnNfts};
+use crate::quarry::StakeAction;
+use crate::weight_table;
+use crate::{
+    BatchStakeArgs, BatchWithdrawArgs, StakeCustody, StakeEvent, StakeReceipt, UserStakeBatch,
+    WithdrawEvent,
+};
+
+/// Accounts of a single NFT of a batch.
+pub struct BatchNft<'info> {
+    /// NFT mint.
+    pub token_mint: Account<'info, Mint>,
+    /// NFT metadata.
+    pub token_metadata: Account<'info, Metadata>,
+    /// NFT master edition or edition.
+    pub token_edition: AccountInfo<'info>,
+    /// Token account of the [crate::Miner] authority holding the NFT.
+    pub token_account: Account<'info, TokenAccount>,
+    /// NFT vault of the [crate::Miner].
+    pub miner_nft_vault: Account<'info, TokenAccount>,
+    /// [StakeReceipt] of the NFT.
+    pub stake_receipt: AccountInfo<'info>,
+}
+
+impl<'info> BatchNft<'info> {
+    /// Number of remaining accounts passed per NFT.
+    pub const NUM_ACCOUNTS: usize = 6;
+
+    /// Loads the accounts of `num_nfts` NFTs.
+    pub fn load_all(
+        accounts: &[AccountInfo<'info>],
+        num_nfts: usize,
+    ) -> Result<Vec<Self>, ProgramError> {
+        require!(
+            accounts.len() == unwrap_int!(num_nfts.checked_mul(Self::NUM_ACCOUNTS)),
+            InvalidBatchAccounts
+        );
+        accounts
+            .chunks_exact(Self::NUM_ACCOUNTS)
+            .map(|accounts| {
+                Ok(BatchNft {
+                    token_mint: Account::try_from(&accounts[0])?,
+                    token_metadata: Account::try_from(&accounts[1])?,
+                    token_edition: accounts[2].clone(),
+                    token_account: Account::try_from(&accounts[3])?,
+                    miner_nft_vault: Account::try_from(&accounts[4])?,
+                    stake_receipt: accounts[5].clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl<'info> UserStakeBatch<'info> {
+    /// Stakes a batch of NFTs.
+    pub fn stake_nfts(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        args: &[BatchStakeArgs],
+    ) -> ProgramResult {
+        if args.is_empty() {
+            // noop
+            return Ok(());
+        }
+
+        let (weight_table, nft_accounts) = if self.quarry.weight_table == Pubkey::default() {
+            (None, remaining_accounts)
+        } else {
+            let (weight_table, nft_accounts) = remaining_accounts
+                .split_first()
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            (
+                weight_table::load_weight_table(&self.quarry, Some(weight_table))?,
+                nft_accounts,
+            )
+        };
+        let nfts = BatchNft::load_all(nft_accounts, args.len())?;
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let mut total_weight: u64 = 0;
+        for (nft, args) in nfts.iter().zip(args) {
+            self.validate_nft(nft, args.metadata_bump, args.edition_bump)?;
+
+            let weight = weight_table::nft_weight(
+                &self.quarry,
+                &nft.token_mint.key(),
+                args.weight_proof.as_ref(),
+                weight_table.as_deref(),
+            )?;
+            let (weight, unlock_ts) =
+                self.quarry
+                    .apply_lockup(args.lockup_tier, weight, timestamp)?;
+            total_weight = unwrap_int!(total_weight.checked_add(weight));
+
+            self.create_stake_receipt(nft, args.receipt_bump, weight, timestamp, unlock_ts)?;
+
+            // Transfer NFT to miner vault
+            token::transfer(
+                CpiContext::new(
+                    self.token_program.to_account_info(),
+                    token::Transfer {
+                        from: nft.token_account.to_account_info(),
+                        to: nft.miner_nft_vault.to_account_info(),
+                        authority: self.authority.to_account_info(),
+                    },
+                ),
+                1,
+            )?;
+
+            emit!(StakeEvent {
+                timestamp,
+                authority: self.authority.key(),
+                amount: 1,
+                weight,
+                unlock_ts,
+                token: nft.token_mint.key(),
+            });
+        }
+
+        self.quarry.process_stake_action_internal(
+            StakeAction::Stake,
+            timestamp,
+            &self.rewarder,
+            &mut self.miner,
+            total_weight,
+        )
+    }
+
+    /// Withdraws a batch of NFTs.
+    pub fn withdraw_nfts(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        args: &[BatchWithdrawArgs],
+    ) -> ProgramResult {
+        if args.is_empty() {
+            // noop
+            return Ok(());
+        }
+
+        let nfts = BatchNft::load_all(remaining_accounts, args.len())?;
+
+        let seeds = gen_miner_signer_seeds!(self.miner);
+        let signer_seeds = &[&seeds[..]];
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        let mut total_weight: u64 = 0;
+        let mut stakes: Vec<(u64, i64)> = Vec::with_capacity(nfts.len());
+        for (nft, args) in nfts.iter().zip(args) {
+            self.validate_nft(nft, args.metadata_bump, args.edition_bump)?;
+            require!(nft.miner_nft_vault.amount == 1, InsufficientBalance);
+
+            let stake_receipt: Account<StakeReceipt> = Account::try_from(&nft.stake_receipt)?;
+            assert_keys_eq!(stake_receipt.miner, self.miner, "receipt miner");
+            assert_keys_eq!(stake_receipt.mint, nft.token_mint, "receipt mint");
+            require!(
+                stake_receipt.custody == StakeCustody::Vault,
+                StakeCustodyMismatch
+            );
+            stake_receipt.assert_unlocked(timestamp)?;
+
+            // Withdraw the weight the NFT was staked with, even if weights changed since.
+            let weight = stake_receipt.weight;
+            total_weight = unwrap_int!(total_weight.checked_add(weight));
+            stakes.push((weight, stake_receipt.stake_ts));
+
+            // Transfer out NFT from quarry vault
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    token::Transfer {
+                        from: nft.miner_nft_vault.to_account_info(),
+                        to: nft.token_account.to_account_info(),
+                        authority: self.miner.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                1,
+            )?;
+
+            stake_receipt.close(self.authority.to_account_info())?;
+
+            emit!(WithdrawEvent {
+                timestamp,
+                authority: self.authority.key(),
+                amount: 1,
+                weight,
+                token: nft.token_mint.key(),
+            });
+        }
+
+        self.quarry.process_stake_action_internal(
+            StakeAction::Withdraw,
+            timestamp,
+            &self.rewarder,
+            &mut self.miner,
+            total_weight,
+        )?;
+
+        // penalties depend on the checkpointed miner, so they are added up after the withdrawal
+        let mut withdrawn = WithdrawnNfts::default();
+        for (weight, stake_ts) in stakes {
+            withdrawn.add(&self.quarry, &self.miner, weight, stake_ts, timestamp)?;
+        }
+        penalty::apply_unstake_penalty(
+            &mut self.quarry,
+            &mut self.miner,
+            self.authority.key(),
+            &withdrawn,
+            timestamp,
+        )
+    }
+
+    /// Validates the accounts of an NFT of the batch.
+    fn validate_nft(
+        &self,
+        nft: &BatchNft<'info>,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        validate_nft_accounts(
+            &self.authority.key(),
+            &self.miner,
+            &self.quarry,
+            &nft.token_mint,
+            &nft.token_metadata,
+            &nft.token_edition,
+            &nft.token_account,
+            metadata_bump,
+            edition_bump,
+        )?;
+        validate_miner_nft_vault(&self.miner, &nft.token_mint, &nft.miner_nft_vault)
+    }
+
+    /// Creates the [StakeReceipt] of an NFT, paid for by the [crate::Miner] authority.
+    fn create_stake_receipt(
+        &self,
+        nft: &BatchNft<'info>,
+        receipt_bump: u8,
+        weight: u64,
+        stake_ts: i64,
+        unlock_ts: i64,
+    ) -> ProgramResult {
+        let stake_receipt = StakeReceipt {
+            miner: self.miner.key(),
+            quarry: self.quarry.key(),
+            mint: nft.token_mint.key(),
+            bump: receipt_bump,
+            weight,
+            stake_ts,
+            unlock_ts,
+            custody: StakeCustody::Vault,
+        };
+        init_stake_receipt(
+            &self.authority.to_account_info(),
+            &nft.stake_receipt,
+            &self.system_program.to_account_info(),
+            &stake_receipt,
+        )
+    }
+}
+
+/// Creates the account of a [StakeReceipt] at its program address, paid for by `payer`.
+pub fn init_stake_receipt<'info>(
+    payer: &AccountInfo<'info>,
+    receipt_info: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    stake_receipt: &StakeReceipt,
+) -> ProgramResult {
+    let seeds: &[&[u8]] = &[
+        b"StakeReceipt".as_ref(),
+        stake_receipt.miner.as_ref(),
+        stake_receipt.mint.as_ref(),
+        &[stake_receipt.bump],
+    ];
+    let expected_receipt = Pubkey::create_program_address(seeds, &crate::ID)?;
+    assert_keys_eq!(*receipt_info.key, expected_receipt, "stake receipt");
+
+    let space = 8 + stake_receipt.try_to_vec()?.len();
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            &expected_receipt,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            &crate::ID,
+        ),
+        &[payer.clone(), receipt_info.clone(), system_program.clone()],
+        &[seeds],
+    )?;
+
+    let mut data = receipt_info.try_borrow_mut_data()?;
+    let dst: &mut [u8] = &mut data;
+    stake_receipt.try_serialize(&mut Cursor::new(dst))
+}
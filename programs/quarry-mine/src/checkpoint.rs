@@ -0,0 +1,295 @@
+//! Settling every [Quarry] of a [Rewarder] before its rates change.
+//!
+//! The rate of a [Quarry] depends on the [Rewarder]::annual_rewards_rate, its
+//! [crate::EmissionSchedule] and the [Rewarder]::total_rewards_shares, so changing any of
+//! them changes the rate of every [Quarry].
+//! Each [Quarry] is checkpointed at its previous rate first, so the new rate only applies
+//! from the time of the change.
+//!
+//! The [Quarry]s are passed as remaining accounts, sorted by key, so that each appears once.
+
+use anchor_lang::prelude::*;
+use vipers::{assert_keys_eq, unwrap_int};
+
+use crate::{EmissionSchedule, EmissionScheduleUpdateEvent, Quarry, Rewarder};
+
+/// Loads the [Quarry]s of `rewarder` passed as remaining accounts.
+///
+/// Every [Quarry] of the [Rewarder] other than `exclude` must be passed, writable and sorted by key.
+pub fn load_quarries<'info>(
+    rewarder: &Account<'info, Rewarder>,
+    remaining_accounts: &[AccountInfo<'info>],
+    exclude: Option<Pubkey>,
+) -> Result<Vec<Account<'info, Quarry>>, ProgramError> {
+    let num_quarries = usize::from(rewarder.num_quarries);
+    let expected_len = if exclude.is_some() {
+        unwrap_int!(num_quarries.checked_sub(1))
+    } else {
+        num_quarries
+    };
+    require!(
+        remaining_accounts.len() == expected_len,
+        InvalidQuarryCheckpointAccounts
+    );
+
+    let mut quarries: Vec<Account<'info, Quarry>> = Vec::with_capacity(expected_len);
+    for quarry_info in remaining_accounts {
+        require!(quarry_info.is_writable, InvalidQuarryCheckpointAccounts);
+        if let Some(previous) = quarries.last() {
+            require!(
+                previous.key() < *quarry_info.key,
+                InvalidQuarryCheckpointAccounts
+            );
+        }
+        require!(
+            Some(*quarry_info.key) != exclude,
+            InvalidQuarryCheckpointAccounts
+        );
+        let quarry: Account<Quarry> = Account::try_from(quarry_info)?;
+        assert_keys_eq!(quarry.rewarder_key, rewarder, "rewarder");
+        quarries.push(quarry);
+    }
+    Ok(quarries)
+}
+
+/// Persists the [Quarry]s loaded with [load_quarries].
+pub fn exit_quarries(quarries: &[Account<Quarry>]) -> ProgramResult {
+    for quarry in quarries {
+        quarry.exit(&crate::ID)?;
+    }
+    Ok(())
+}
+
+impl Rewarder {
+    /// Sets the annual rewards rate, settling `quarries`, every [Quarry] of the [Rewarder],
+    /// at the previous rate first.
+    ///
+    /// The new rate replaces any [EmissionSchedule], whose steps would otherwise override it.
+    pub fn update_annual_rewards_rate(
+        &mut self,
+        quarries: &mut [&mut Quarry],
+        new_rate: u64,
+        current_ts: i64,
+    ) -> ProgramResult {
+        self.checkpoint_quarries(quarries, current_ts)?;
+        if self.emission_schedule.steps().next().is_some() {
+            self.emission_schedule = EmissionSchedule::default();
+            emit!(EmissionScheduleUpdateEvent {
+                steps: vec![],
+                timestamp: current_ts,
+            });
+        }
+        self.set_annual_rewards_rate(new_rate, current_ts)?;
+        self.sync_quarry_rates(quarries, current_ts)
+    }
+
+    /// Replaces the [EmissionSchedule], settling `quarries`, every [Quarry] of the [Rewarder],
+    /// at the previous schedule first.
+    ///
+    /// The rate of the previous schedule at `current_ts` applies until the first new step.
+    pub fn update_emission_schedule(
+        &mut self,
+        quarries: &mut [&mut Quarry],
+        schedule: EmissionSchedule,
+        current_ts: i64,
+    ) -> ProgramResult {
+        self.checkpoint_quarries(quarries, current_ts)?;
+        self.annual_rewards_rate = self.annual_rewards_rate_at(current_ts);
+        self.emission_schedule = schedule;
+        self.sync_quarry_rates(quarries, current_ts)
+    }
+
+    /// Sets the rewards share of `quarry`, settling it and `other_quarries`, every other
+    /// [Quarry] of the [Rewarder], at their previous rates first.
+    pub fn update_rewards_share(
+        &mut self,
+        quarry: &mut Quarry,
+        other_quarries: &mut [&mut Quarry],
+        new_share: u64,
+        current_ts: i64,
+    ) -> ProgramResult {
+        self.checkpoint_quarries(&mut [&mut *quarry], current_ts)?;
+        self.checkpoint_quarries(other_quarries, current_ts)?;
+        self.set_quarry_rewards_share(quarry, new_share, current_ts)?;
+        self.sync_quarry_rates(other_quarries, current_ts)
+    }
+
+    /// Checkpoints the rewards of `quarries` at the current rates.
+    fn checkpoint_quarries(&self, quarries: &mut [&mut Quarry], current_ts: i64) -> ProgramResult {
+        for quarry in quarries.iter_mut() {
+            let payroll = quarry.payroll(self)?;
+            quarry.update_rewards_internal(current_ts, self, &payroll)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the annual rewards rate of `quarries` from the current parameters of the [Rewarder].
+    fn sync_quarry_rates(&self, quarries: &mut [&mut Quarry], current_ts: i64) -> ProgramResult {
+        for quarry in quarries.iter_mut() {
+            quarry.annual_rewards_rate =
+                self.compute_quarry_annual_rewards_rate_at(quarry.rewards_share, current_ts)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::payroll::SECONDS_PER_YEAR;
+    use crate::quarry::StakeAction;
+    use crate::{EmissionStep, Miner};
+
+    const SECONDS_PER_YEAR_U64: u64 = SECONDS_PER_YEAR as u64;
+
+    /// Creates a [Rewarder] with two [Quarry]s of one share each, and a [Miner]
+    /// staked into each [Quarry] from time 0.
+    fn setup(annual_rewards_rate: u64) -> (Rewarder, [Quarry; 2], [Miner; 2]) {
+        let mut rewarder = Rewarder {
+            num_quarries: 2,
+            ..Default::default()
+        };
+        let mut quarries = [Quarry::default(), Quarry::default()];
+        for quarry in quarries.iter_mut() {
+            quarry.famine_ts = i64::MAX;
+            rewarder.set_quarry_rewards_share(quarry, 1, 0).unwrap();
+        }
+        rewarder
+            .set_annual_rewards_rate(annual_rewards_rate, 0)
+            .unwrap();
+        let [quarry_a, quarry_b] = &mut quarries;
+        rewarder
+            .sync_quarry_rates(&mut [quarry_a, quarry_b], 0)
+            .unwrap();
+
+        let mut miners = [Miner::default(), Miner::default()];
+        for (quarry, miner) in quarries.iter_mut().zip(miners.iter_mut()) {
+            quarry
+                .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, miner, 1_000)
+                .unwrap();
+        }
+        (rewarder, quarries, miners)
+    }
+
+    #[test]
+    fn test_update_annual_rewards_rate_settles_every_quarry() {
+        // each quarry earns 1 token per second
+        let (mut rewarder, mut quarries, mut miners) = setup(2 * SECONDS_PER_YEAR_U64);
+
+        // from 100, each quarry earns 2 tokens per second
+        let [quarry_a, quarry_b] = &mut quarries;
+        rewarder
+            .update_annual_rewards_rate(&mut [quarry_a, quarry_b], 4 * SECONDS_PER_YEAR_U64, 100)
+            .unwrap();
+        for quarry in quarries.iter() {
+            assert_eq!(quarry.last_update_ts, 100);
+            assert_eq!(quarry.annual_rewards_rate, 2 * SECONDS_PER_YEAR_U64);
+        }
+
+        for (quarry, miner) in quarries.iter_mut().zip(miners.iter_mut()) {
+            quarry
+                .update_rewards_and_miner(miner, &rewarder, 200)
+                .unwrap();
+            // 100 seconds at 1 token per second, then 100 seconds at 2 tokens per second;
+            // rewards may be off by precision errors
+            assert_eq!(miner.rewards_earned, 299);
+        }
+    }
+
+    #[test]
+    fn test_update_rewards_share_settles_every_quarry() {
+        // each quarry earns 2 tokens per second
+        let (mut rewarder, mut quarries, mut miners) = setup(4 * SECONDS_PER_YEAR_U64);
+
+        // from 100, quarry A earns 3 tokens per second and quarry B earns 1
+        let [quarry_a, quarry_b] = &mut quarries;
+        rewarder
+            .update_rewards_share(quarry_a, &mut [quarry_b], 3, 100)
+            .unwrap();
+        assert_eq!(rewarder.total_rewards_shares, 4);
+        for quarry in quarries.iter() {
+            assert_eq!(quarry.last_update_ts, 100);
+        }
+        assert_eq!(quarries[0].annual_rewards_rate, 3 * SECONDS_PER_YEAR_U64);
+        assert_eq!(quarries[1].annual_rewards_rate, SECONDS_PER_YEAR_U64);
+
+        for (quarry, miner) in quarries.iter_mut().zip(miners.iter_mut()) {
+            quarry
+                .update_rewards_and_miner(miner, &rewarder, 200)
+                .unwrap();
+        }
+        // rewards may be off by precision errors
+        assert_eq!(miners[0].rewards_earned, 499);
+        assert_eq!(miners[1].rewards_earned, 299);
+    }
+
+    #[test]
+    fn test_update_emission_schedule_settles_every_quarry() {
+        // each quarry earns 1 token per second, then 2 from 50
+        let (mut rewarder, mut quarries, mut miners) = setup(2 * SECONDS_PER_YEAR_U64);
+        rewarder.emission_schedule = EmissionSchedule::new(
+            &[EmissionStep {
+                start_ts: 50,
+                annual_rate: 4 * SECONDS_PER_YEAR_U64,
+            }],
+            0,
+        )
+        .unwrap();
+
+        // from 100, the previous schedule's rate applies until each quarry earns 3 from 150
+        let schedule = EmissionSchedule::new(
+            &[EmissionStep {
+                start_ts: 150,
+                annual_rate: 6 * SECONDS_PER_YEAR_U64,
+            }],
+            100,
+        )
+        .unwrap();
+        let [quarry_a, quarry_b] = &mut quarries;
+        rewarder
+            .update_emission_schedule(&mut [quarry_a, quarry_b], schedule, 100)
+            .unwrap();
+        assert_eq!(rewarder.annual_rewards_rate, 4 * SECONDS_PER_YEAR_U64);
+        assert_eq!(rewarder.emission_schedule, schedule);
+        for quarry in quarries.iter() {
+            assert_eq!(quarry.last_update_ts, 100);
+            assert_eq!(quarry.annual_rewards_rate, 2 * SECONDS_PER_YEAR_U64);
+        }
+
+        for (quarry, miner) in quarries.iter_mut().zip(miners.iter_mut()) {
+            quarry
+                .update_rewards_and_miner(miner, &rewarder, 200)
+                .unwrap();
+            // 50 seconds at 1 token per second, 100 at 2 and 50 at 3;
+            // rewards may be off by precision errors
+            assert!(miner.rewards_earned >= 399 && miner.rewards_earned <= 400);
+        }
+    }
+
+    #[test]
+    fn test_update_annual_rewards_rate_replaces_emission_schedule() {
+        let (mut rewarder, mut quarries, _) = setup(2 * SECONDS_PER_YEAR_U64);
+        rewarder.emission_schedule = EmissionSchedule::new(
+            &[EmissionStep {
+                start_ts: 50,
+                annual_rate: 4 * SECONDS_PER_YEAR_U64,
+            }],
+            0,
+        )
+        .unwrap();
+
+        let [quarry_a, quarry_b] = &mut quarries;
+        rewarder
+            .update_annual_rewards_rate(&mut [quarry_a, quarry_b], 6 * SECONDS_PER_YEAR_U64, 100)
+            .unwrap();
+        assert_eq!(rewarder.emission_schedule, EmissionSchedule::default());
+        assert_eq!(
+            rewarder.annual_rewards_rate_at(100),
+            6 * SECONDS_PER_YEAR_U64
+        );
+        for quarry in quarries.iter() {
+            assert_eq!(quarry.annual_rewards_rate, 3 * SECONDS_PER_YEAR_U64);
+        }
+    }
+}
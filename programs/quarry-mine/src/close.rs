@@ -0,0 +1,249 @@
+//! Closing finished accounts to reclaim their rent.
+//!
+//! Payers are not recorded, so rent goes to the authority of each account:
+//! the [Miner]::authority for [Miner]s and their vaults, and the [Rewarder]::authority
+//! for [Quarry]s and [Rewarder]s and the token accounts they own.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount};
+use vipers::{assert_keys_eq, unwrap_int};
+
+use crate::{
+    CloseMinerVault, CloseQuarry, CloseRewarder, Miner, Quarry, RewardStream, Rewarder,
+    RewarderFunding,
+};
+
+impl Miner {
+    /// Returns true if the [Miner] holds no stake and no unclaimed rewards,
+    /// including those of its [crate::RewardStream]s.
+    pub fn is_closable(&self) -> bool {
+        self.balance == 0
+            && self.rewards_earned == 0
+            && self
+                .reward_streams
+                .iter()
+                .all(|stream| stream.rewards_earned == 0)
+    }
+}
+
+impl Quarry {
+    /// Checks that the [Quarry] may be closed at `current_ts`.
+    ///
+    /// Its rewards must have ceased, every [Miner] must have been closed, and its
+    /// rewards share must have been released with [crate::quarry_mine::set_rewards_share],
+    /// so that closing it does not change the rates of the other [Quarry]s.
+    pub fn assert_closable(&self, current_ts: i64) -> ProgramResult {
+        require!(self.famine_ts <= current_ts, QuarryNotFinished);
+        require!(
+            self.num_miners == 0 && self.total_tokens_deposited == 0,
+            QuarryHasMiners
+        );
+        require!(self.rewards_share == 0, InvalidRewardsShare);
+        require!(self.forfeited_rewards == 0, QuarryNotFinished);
+        Ok(())
+    }
+}
+
+impl Rewarder {
+    /// Removes a closed [Quarry] from the [Rewarder]'s counters.
+    pub fn remove_quarry(&mut self, quarry: &Quarry) -> ProgramResult {
+        self.num_quarries = unwrap_int!(self.num_quarries.checked_sub(1));
+        self.total_rewards_shares =
+            unwrap_int!(self.total_rewards_shares.checked_sub(quarry.rewards_share));
+        Ok(())
+    }
+}
+
+impl<'info> CloseMinerVault<'info> {
+    /// Closes the empty vault, returning its rent to the [Miner]::authority.
+    pub fn close_vault(&self) -> ProgramResult {
+        let seeds = gen_miner_signer_seeds!(self.miner);
+        let signer_seeds = &[&seeds[..]];
+
+        token::close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            token::CloseAccount {
+                account: self.miner_vault.to_account_info(),
+                destination: self.authority.to_account_info(),
+                authority: self.miner.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+}
+
+impl<'info> CloseQuarry<'info> {
+    /// Empties and closes the vaults of the [Quarry]'s [RewardStream]s.
+    ///
+    /// The remaining accounts are the vault of each active stream, then the [TokenAccount]
+    /// receiving the tokens left in it, in the order of the [Quarry]::reward_streams.
+    pub fn close_reward_stream_vaults(
+        &self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> ProgramResult {
+        let streams: Vec<&RewardStream> = self
+            .quarry
+            .reward_streams
+            .iter()
+            .filter(|stream| stream.is_active())
+            .collect();
+        require!(
+            remaining_accounts.len()
+                == unwrap_int!(streams.len().checked_mul(RewardStream::NUM_CLAIM_ACCOUNTS)),
+            InvalidRewardStreamAccounts
+        );
+
+        for (stream, accounts) in streams
+            .into_iter()
+            .zip(remaining_accounts.chunks_exact(RewardStream::NUM_CLAIM_ACCOUNTS))
+        {
+            let vault: Account<TokenAccount> = Account::try_from(&accounts[0])?;
+            let destination: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+            assert_keys_eq!(vault, stream.vault, "reward stream vault");
+            assert_keys_eq!(destination.mint, stream.mint, "destination.mint");
+            drain_and_close(
+                &self.token_program,
+                &self.rewarder,
+                &vault,
+                &destination,
+                self.authority.to_account_info(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'info> CloseRewarder<'info> {
+    /// Empties and closes the [Rewarder]::rewards_vault of a [RewarderFunding::Vault] [Rewarder].
+    ///
+    /// The remaining accounts are the rewards vault, then the [TokenAccount]
+    /// receiving the tokens left in it.
+    pub fn close_rewards_vault(&self, remaining_accounts: &[AccountInfo<'info>]) -> ProgramResult {
+        let rewarder = &self.rewarder;
+        if rewarder.funding != RewarderFunding::Vault {
+            require!(remaining_accounts.is_empty(), InvalidRewarderFunding);
+            return Ok(());
+        }
+        require!(remaining_accounts.len() == 2, InvalidRewarderFunding);
+        let rewards_vault: Account<TokenAccount> = Account::try_from(&remaining_accounts[0])?;
+        let destination: Account<TokenAccount> = Account::try_from(&remaining_accounts[1])?;
+        assert_keys_eq!(rewards_vault, rewarder.rewards_vault, "rewards vault");
+        assert_keys_eq!(destination.mint, rewards_vault.mint, "destination mint");
+
+        drain_and_close(
+            &self.token_program,
+            rewarder,
+            &rewards_vault,
+            &destination,
+            self.authority.to_account_info(),
+        )
+    }
+
+    /// Closes the [Rewarder]::claim_fee_token_account, whose fees must have been extracted.
+    pub fn close_claim_fee_token_account(&self) -> ProgramResult {
+        let seeds = gen_rewarder_signer_seeds!(self.rewarder);
+        let signer_seeds = &[&seeds[..]];
+        token::close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            token::CloseAccount {
+                account: self.claim_fee_token_account.to_account_info(),
+                destination: self.authority.to_account_info(),
+                authority: self.rewarder.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+}
+
+/// Transfers all tokens of a [TokenAccount] owned by the [Rewarder] to `destination`,
+/// then closes it, sending its rent to `rent_destination`.
+fn drain_and_close<'info>(
+    token_program: &Program<'info, Token>,
+    rewarder: &Account<'info, Rewarder>,
+    account: &Account<'info, TokenAccount>,
+    destination: &Account<'info, TokenAccount>,
+    rent_destination: AccountInfo<'info>,
+) -> ProgramResult {
+    let seeds = gen_rewarder_signer_seeds!(rewarder);
+    let signer_seeds = &[&seeds[..]];
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: account.to_account_info(),
+                to: destination.to_account_info(),
+                authority: rewarder.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        account.amount,
+    )?;
+    token::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token::CloseAccount {
+            account: account.to_account_info(),
+            destination: rent_destination,
+            authority: rewarder.to_account_info(),
+        },
+        signer_seeds,
+    ))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::MinerRewardStream;
+    use vipers::program_err;
+
+    #[test]
+    fn test_miner_is_closable() {
+        let mut miner = Miner::default();
+        assert!(miner.is_closable());
+
+        miner.reward_streams[2] = MinerRewardStream {
+            rewards_per_token_paid: 1,
+            rewards_earned: 1,
+        };
+        assert!(!miner.is_closable());
+        miner.reward_streams[2].rewards_earned = 0;
+        assert!(miner.is_closable());
+
+        miner.rewards_earned = 1;
+        assert!(!miner.is_closable());
+        miner.rewards_earned = 0;
+        miner.balance = 1;
+        assert!(!miner.is_closable());
+    }
+
+    #[test]
+    fn test_close_quarry() {
+        let mut quarry = Quarry {
+            famine_ts: 100,
+            num_miners: 1,
+            rewards_share: 10,
+            ..Default::default()
+        };
+        assert_eq!(quarry.assert_closable(99), program_err!(QuarryNotFinished));
+        assert_eq!(quarry.assert_closable(100), program_err!(QuarryHasMiners));
+        quarry.num_miners = 0;
+        assert_eq!(
+            quarry.assert_closable(100),
+            program_err!(InvalidRewardsShare)
+        );
+        quarry.rewards_share = 0;
+        quarry.forfeited_rewards = 1;
+        assert_eq!(quarry.assert_closable(100), program_err!(QuarryNotFinished));
+        quarry.forfeited_rewards = 0;
+        assert_eq!(quarry.assert_closable(100), Ok(()));
+
+        let mut rewarder = Rewarder {
+            num_quarries: 2,
+            total_rewards_shares: 10,
+            ..Default::default()
+        };
+        rewarder.remove_quarry(&quarry).unwrap();
+        assert_eq!(rewarder.num_quarries, 1);
+        assert_eq!(rewarder.total_rewards_shares, 10);
+    }
+}
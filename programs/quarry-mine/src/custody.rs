@@ -0,0 +1,132 @@
+//! Non-custodial staking, which freezes NFTs in their owner's token account.
+//!
+//! The edition of an NFT is the freeze authority of its mint, so freezing goes through the
+//! token metadata program's `FreezeDelegatedAccount` and `ThawDelegatedAccount` instructions,
+//! signed by the [crate::Miner] as the delegate of the token account.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::{invoke, invoke_signed};
+use anchor_lang::Key;
+use anchor_spl::token;
+
+use crate::metadata;
+use crate::UserStakeFrozen;
+
+/// Index of the `FreezeDelegatedAccount` token metadata instruction.
+const FREEZE_DELEGATED_ACCOUNT: u8 = 26;
+/// Index of the `ThawDelegatedAccount` token metadata instruction.
+const THAW_DELEGATED_ACCOUNT: u8 = 27;
+
+/// Builds a `FreezeDelegatedAccount` or `ThawDelegatedAccount` instruction.
+fn delegated_account_instruction(
+    instruction: u8,
+    delegate: Pubkey,
+    token_account: Pubkey,
+    edition: Pubkey,
+    mint: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: metadata::ID,
+        accounts: vec![
+            AccountMeta::new(delegate, true),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(edition, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(token::ID, false),
+        ],
+        data: vec![instruction],
+    }
+}
+
+impl<'info> UserStakeFrozen<'info> {
+    /// Delegates the NFT to the [crate::Miner] and freezes it in the user's token account.
+    pub fn freeze_nft(&self) -> ProgramResult {
+        token::approve(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token::Approve {
+                    to: self.token_account.to_account_info(),
+                    delegate: self.miner.to_account_info(),
+                    authority: self.authority.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        self.invoke_delegated_account_instruction(FREEZE_DELEGATED_ACCOUNT)
+    }
+
+    /// Thaws the NFT in the user's token account and revokes the [crate::Miner]'s delegation.
+    pub fn thaw_nft(&self) -> ProgramResult {
+        self.invoke_delegated_account_instruction(THAW_DELEGATED_ACCOUNT)?;
+        invoke(
+            &spl_token::instruction::revoke(
+                &token::ID,
+                &self.token_account.key(),
+                &self.authority.key(),
+                &[],
+            )?,
+            &[
+                self.token_account.to_account_info(),
+                self.authority.to_account_info(),
+                self.token_program.to_account_info(),
+            ],
+        )
+    }
+
+    fn invoke_delegated_account_instruction(&self, instruction: u8) -> ProgramResult {
+        let seeds = gen_miner_signer_seeds!(self.miner);
+        let signer_seeds = &[&seeds[..]];
+
+        invoke_signed(
+            &delegated_account_instruction(
+                instruction,
+                self.miner.key(),
+                self.token_account.key(),
+                self.token_edition.key(),
+                self.token_mint.key(),
+            ),
+            &[
+                self.miner.to_account_info(),
+                self.token_account.to_account_info(),
+                self.token_edition.to_account_info(),
+                self.token_mint.to_account_info(),
+                self.token_program.to_account_info(),
+                self.token_metadata_program.to_account_info(),
+            ],
+            signer_seeds,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delegated_account_instruction() {
+        let delegate = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let ix = delegated_account_instruction(
+            FREEZE_DELEGATED_ACCOUNT,
+            delegate,
+            token_account,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        assert_eq!(ix.program_id, metadata::ID);
+        assert_eq!(ix.data, vec![FREEZE_DELEGATED_ACCOUNT]);
+        // only the delegate signs
+        let signers: Vec<Pubkey> = ix
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_signer)
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(signers, vec![delegate]);
+        assert!(ix.accounts[1].is_writable);
+        assert_eq!(ix.accounts[1].pubkey, token_account);
+        assert_eq!(ix.accounts[4].pubkey, token::ID);
+    }
+}
@@ -0,0 +1,105 @@
+//! Emergency withdrawals, which return staked NFTs and tokens while the [crate::Rewarder]
+//! is paused.
+//!
+//! No reward math runs, so holders get their stake back even if [crate::payroll::Payroll]
+//! is broken. Rewards the [Miner] earned up to its last checkpoint stay on the [Miner]
+//! and may be claimed once the [crate::Rewarder] is unpaused, including those held back
+//! for the unstake penalties of withdrawn NFTs.
+//!
+//! Since neither is checkpointed, the rewards of the withdrawn weight are split in two:
+//! - those accrued between the [Miner]'s last checkpoint and the [Quarry]::last_update_ts
+//!   are already counted in the [Quarry]::rewards_per_token_stored, but are never paid
+//!   to anyone;
+//! - those accrued since the [Quarry]::last_update_ts are shared by the remaining stakers
+//!   at the next update of the [Quarry], as the withdrawn weight no longer counts towards
+//!   the [Quarry]::total_tokens_deposited.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token;
+use vipers::unwrap_int;
+
+use crate::{Miner, Quarry, UserStake, UserStakeTokens};
+
+impl Quarry {
+    /// Removes `weight` from the [Miner] and the [Quarry] without checkpointing either.
+    pub fn emergency_withdraw(&mut self, miner: &mut Miner, weight: u64) -> ProgramResult {
+        miner.balance = unwrap_int!(miner.balance.checked_sub(weight));
+        self.total_tokens_deposited = unwrap_int!(self.total_tokens_deposited.checked_sub(weight));
+        Ok(())
+    }
+}
+
+impl<'info> UserStake<'info> {
+    /// Transfers the NFT out of the [Miner]'s vault back to the user.
+    pub fn return_nft(&self) -> ProgramResult {
+        let seeds = gen_miner_signer_seeds!(self.miner);
+        let signer_seeds = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.miner_nft_vault.to_account_info(),
+                    to: self.token_account.to_account_info(),
+                    authority: self.miner.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )
+    }
+}
+
+impl<'info> UserStakeTokens<'info> {
+    /// Transfers `amount` tokens out of the [Miner]'s vault back to the user.
+    pub fn return_tokens(&self, amount: u64) -> ProgramResult {
+        let seeds = gen_miner_signer_seeds!(self.miner);
+        let signer_seeds = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.miner_vault.to_account_info(),
+                    to: self.token_account.to_account_info(),
+                    authority: self.miner.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emergency_withdraw() {
+        let mut quarry = Quarry {
+            total_tokens_deposited: 30,
+            rewards_per_token_stored: 100,
+            last_update_ts: 50,
+            ..Default::default()
+        };
+        let mut miner = Miner {
+            balance: 20,
+            rewards_earned: 7,
+            rewards_per_token_paid: 40,
+            ..Default::default()
+        };
+
+        quarry.emergency_withdraw(&mut miner, 15).unwrap();
+        assert_eq!(miner.balance, 5);
+        assert_eq!(quarry.total_tokens_deposited, 15);
+        // checkpoints are left untouched
+        assert_eq!(miner.rewards_earned, 7);
+        assert_eq!(miner.rewards_per_token_paid, 40);
+        assert_eq!(quarry.rewards_per_token_stored, 100);
+        assert_eq!(quarry.last_update_ts, 50);
+
+        assert!(quarry.emergency_withdraw(&mut miner, 6).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Emission schedules, which change the annual rewards rate of a [Rewarder] over time.
+//!
+//! The schedule is stored on the [Rewarder], which every checkpoint already loads, so
+//! [crate::payroll::Payroll] can integrate rewards across step boundaries without a keeper.
+
+use anchor_lang::prelude::*;
+use num_traits::ToPrimitive;
+use vipers::unwrap_int;
+
+use crate::{EmissionPreset, EmissionSchedule, EmissionStep, Rewarder, MAX_ANNUAL_REWARDS_RATE};
+
+/// Maximum number of [EmissionStep]s of an [EmissionSchedule].
+pub const MAX_EMISSION_STEPS: usize = 16;
+
+impl EmissionSchedule {
+    /// Creates an [EmissionSchedule] whose steps all start after `current_ts`.
+    pub fn new(steps: &[EmissionStep], current_ts: i64) -> Result<Self, ProgramError> {
+        require!(steps.len() <= MAX_EMISSION_STEPS, InvalidEmissionSchedule);
+
+        let mut previous_ts = current_ts;
+        for step in steps {
+            require!(step.start_ts > previous_ts, InvalidEmissionSchedule);
+            require!(
+                step.annual_rate <= MAX_ANNUAL_REWARDS_RATE,
+                MaxAnnualRewardsRateExceeded
+            );
+            previous_ts = step.start_ts;
+        }
+
+        let mut schedule = EmissionSchedule::default();
+        schedule.steps[..steps.len()].copy_from_slice(steps);
+        Ok(schedule)
+    }
+
+    /// Steps of the schedule, in order of `start_ts`.
+    pub fn steps(&self) -> impl Iterator<Item = &EmissionStep> {
+        // unused steps are zeroed, and steps may never start at the epoch
+        self.steps.iter().take_while(|step| step.start_ts != 0)
+    }
+}
+
+impl EmissionPreset {
+    /// Computes the [EmissionStep]s of the preset.
+    pub fn to_steps(&self) -> Result<Vec<EmissionStep>, ProgramError> {
+        match *self {
+            EmissionPreset::Custom { ref steps } => Ok(steps.clone()),
+            EmissionPreset::LinearDecay {
+                start_ts,
+                period,
+                initial_rate,
+                final_rate,
+                num_steps,
+            } => {
+                require!(final_rate <= initial_rate, InvalidEmissionSchedule);
+                let total_decay = unwrap_int!(initial_rate.checked_sub(final_rate));
+                let last_index = u64::from(num_steps).saturating_sub(1).max(1);
+                (0..num_steps)
+                    .map(|index| {
+                        let decay = unwrap_int!((total_decay as u128)
+                            .checked_mul(index.into())
+                            .and_then(|decay| decay.checked_div(last_index.into()))
+                            .and_then(|decay| decay.to_u64()));
+                        Ok(EmissionStep {
+                            start_ts: preset_step_ts(start_ts, period, index)?,
+                            annual_rate: unwrap_int!(initial_rate.checked_sub(decay)),
+                        })
+                    })
+                    .collect()
+            }
+            EmissionPreset::Halving {
+                start_ts,
+                period,
+                initial_rate,
+                num_steps,
+            } => (0..num_steps)
+                .map(|index| {
+                    Ok(EmissionStep {
+                        start_ts: preset_step_ts(start_ts, period, index)?,
+                        annual_rate: initial_rate.checked_shr(index.into()).unwrap_or(0),
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Start of the step at `index` of a preset with a step every `period` seconds.
+fn preset_step_ts(start_ts: i64, period: i64, index: u8) -> Result<i64, ProgramError> {
+    require!(period > 0, InvalidEmissionSchedule);
+    Ok(unwrap_int!(period
+        .checked_mul(index.into())
+        .and_then(|offset| start_ts.checked_add(offset))))
+}
+
+impl Rewarder {
+    /// Annual rewards rate of the [Rewarder] at `current_ts`.
+    ///
+    /// Before the first [EmissionStep], this is the [Rewarder]::annual_rewards_rate.
+    pub fn annual_rewards_rate_at(&self, current_ts: i64) -> u64 {
+        self.emission_schedule
+            .steps()
+            .take_while(|step| step.start_ts <= current_ts)
+            .last()
+            .map_or(self.annual_rewards_rate, |step| step.annual_rate)
+    }
+
+    /// Computes the annual rewards rate of a [crate::Quarry] at `current_ts`.
+    pub fn compute_quarry_annual_rewards_rate_at(
+        &self,
+        quarry_rewards_share: u64,
+        current_ts: i64,
+    ) -> Result<u64, ProgramError> {
+        self.compute_quarry_rewards_rate(
+            self.annual_rewards_rate_at(current_ts),
+            quarry_rewards_share,
+        )
+    }
+
+    /// Computes the [EmissionStep]s starting after `after_ts`, at the rates of a [crate::Quarry].
+    pub fn compute_quarry_emission_steps(
+        &self,
+        quarry_rewards_share: u64,
+        after_ts: i64,
+    ) -> Result<Vec<EmissionStep>, ProgramError> {
+        self.emission_schedule
+            .steps()
+            .filter(|step| step.start_ts > after_ts)
+            .map(|step| {
+                Ok(EmissionStep {
+                    start_ts: step.start_ts,
+                    annual_rate: self
+                        .compute_quarry_rewards_rate(step.annual_rate, quarry_rewards_share)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use vipers::program_err;
+
+    fn step(start_ts: i64, annual_rate: u64) -> EmissionStep {
+        EmissionStep {
+            start_ts,
+            annual_rate,
+        }
+    }
+
+    #[test]
+    fn test_new_emission_schedule() {
+        let steps = [step(100, 50), step(200, 0)];
+        let schedule = EmissionSchedule::new(&steps, 10).unwrap();
+        assert_eq!(schedule.steps().copied().collect::<Vec<_>>(), steps);
+        assert_eq!(EmissionSchedule::new(&[], 10).unwrap().steps().count(), 0);
+
+        // steps must start in the future, in order
+        assert_eq!(
+            EmissionSchedule::new(&steps, 100),
+            program_err!(InvalidEmissionSchedule)
+        );
+        assert_eq!(
+            EmissionSchedule::new(&[step(200, 50), step(200, 0)], 10),
+            program_err!(InvalidEmissionSchedule)
+        );
+        assert_eq!(
+            EmissionSchedule::new(&[step(100, 1); MAX_EMISSION_STEPS + 1], 10),
+            program_err!(InvalidEmissionSchedule)
+        );
+        assert_eq!(
+            EmissionSchedule::new(&[step(100, MAX_ANNUAL_REWARDS_RATE + 1)], 10),
+            program_err!(MaxAnnualRewardsRateExceeded)
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        let halving = EmissionPreset::Halving {
+            start_ts: 1_000,
+            period: 100,
+            initial_rate: 1_000,
+            num_steps: 4,
+        };
+        assert_eq!(
+            halving.to_steps().unwrap(),
+            vec![
+                step(1_000, 1_000),
+                step(1_100, 500),
+                step(1_200, 250),
+                step(1_300, 125)
+            ]
+        );
+
+        let linear_decay = EmissionPreset::LinearDecay {
+            start_ts: 1_000,
+            period: 100,
+            initial_rate: 1_000,
+            final_rate: 100,
+            num_steps: 4,
+        };
+        assert_eq!(
+            linear_decay.to_steps().unwrap(),
+            vec![
+                step(1_000, 1_000),
+                step(1_100, 700),
+                step(1_200, 400),
+                step(1_300, 100)
+            ]
+        );
+
+        assert_eq!(
+            EmissionPreset::LinearDecay {
+                start_ts: 1_000,
+                period: 100,
+                initial_rate: 100,
+                final_rate: 1_000,
+                num_steps: 4,
+            }
+            .to_steps(),
+            program_err!(InvalidEmissionSchedule)
+        );
+        assert_eq!(
+            EmissionPreset::Halving {
+                start_ts: 1_000,
+                period: 0,
+                initial_rate: 1_000,
+                num_steps: 4,
+            }
+            .to_steps(),
+            program_err!(InvalidEmissionSchedule)
+        );
+    }
+
+    #[test]
+    fn test_annual_rewards_rate_at() {
+        let rewarder = Rewarder {
+            annual_rewards_rate: 1_000,
+            total_rewards_shares: 4,
+            emission_schedule: EmissionSchedule::new(&[step(100, 500), step(200, 0)], 0).unwrap(),
+            ..Default::default()
+        };
+
+        assert_eq!(rewarder.annual_rewards_rate_at(99), 1_000);
+        assert_eq!(rewarder.annual_rewards_rate_at(100), 500);
+        assert_eq!(rewarder.annual_rewards_rate_at(i64::MAX), 0);
+
+        assert_eq!(
+            rewarder.compute_quarry_annual_rewards_rate_at(1, 150),
+            Ok(125)
+        );
+        assert_eq!(
+            rewarder.compute_quarry_emission_steps(1, 100).unwrap(),
+            vec![step(200, 0)]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Splitting claim fees across multiple recipients.
+//!
+//! Each [FeeRecipient] of a [Rewarder] receives its share of the fees, in basis points,
+//! with [crate::quarry_mine::extract_fees]. The [crate::Config]::fee_to receives the rest,
+//! including any rounding dust, so the claim fee token account is always emptied.
+//!
+//! The token accounts of the recipients are passed as remaining accounts, in the order
+//! of the [Rewarder]::fee_split.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, TokenAccount};
+use num_traits::cast::ToPrimitive;
+use vipers::{assert_ata, unwrap_int};
+
+use crate::{ExtractFees, FeeRecipient, FeesExtractEvent, Rewarder};
+
+/// Maximum number of [FeeRecipient]s of a [Rewarder].
+pub const MAX_FEE_RECIPIENTS: usize = 4;
+
+/// Basis points of the whole claim fee.
+pub const TOTAL_FEE_BPS: u64 = 10_000;
+
+impl Rewarder {
+    /// Replaces the [FeeRecipient]s of the [Rewarder].
+    pub fn set_fee_split(&mut self, recipients: &[FeeRecipient]) -> ProgramResult {
+        require!(recipients.len() <= MAX_FEE_RECIPIENTS, InvalidFeeSplit);
+        let mut total_bps: u64 = 0;
+        for recipient in recipients {
+            require!(recipient.recipient != Pubkey::default(), InvalidFeeSplit);
+            require!(recipient.bps > 0, InvalidFeeSplit);
+            total_bps = unwrap_int!(total_bps.checked_add(recipient.bps));
+        }
+        require!(total_bps <= TOTAL_FEE_BPS, InvalidFeeSplit);
+
+        let mut fee_split = [FeeRecipient::default(); MAX_FEE_RECIPIENTS];
+        fee_split[..recipients.len()].copy_from_slice(recipients);
+        self.fee_split = fee_split;
+        Ok(())
+    }
+
+    /// [FeeRecipient]s of the [Rewarder], in order.
+    pub fn fee_recipients(&self) -> impl Iterator<Item = &FeeRecipient> {
+        // unused recipients are zeroed
+        self.fee_split
+            .iter()
+            .take_while(|recipient| recipient.recipient != Pubkey::default())
+    }
+
+    /// Splits `amount` of fees, returning the share of each [FeeRecipient] and the rest.
+    pub fn compute_fee_split(&self, amount: u64) -> Result<(Vec<u64>, u64), ProgramError> {
+        let mut rest = amount;
+        let shares = self
+            .fee_recipients()
+            .map(|recipient| {
+                let share = unwrap_int!((amount as u128)
+                    .checked_mul(recipient.bps.into())
+                    .and_then(|share| share.checked_div(TOTAL_FEE_BPS.into()))
+                    .and_then(|share| share.to_u64()));
+                rest = unwrap_int!(rest.checked_sub(share));
+                Ok(share)
+            })
+            .collect::<Result<Vec<u64>, ProgramError>>()?;
+        Ok((shares, rest))
+    }
+}
+
+impl<'info> ExtractFees<'info> {
+    /// Distributes the claim fees of the [Rewarder] to its [FeeRecipient]s
+    /// and the [crate::Config]::fee_to.
+    pub fn extract_fees(&self, remaining_accounts: &[AccountInfo<'info>]) -> ProgramResult {
+        let recipients: Vec<FeeRecipient> = self.rewarder.fee_recipients().copied().collect();
+        require!(
+            remaining_accounts.len() == recipients.len(),
+            InvalidFeeSplitAccounts
+        );
+
+        let amount = self.claim_fee_token_account.amount;
+        let (shares, rest) = self.rewarder.compute_fee_split(amount)?;
+
+        for ((recipient, share), account) in recipients.iter().zip(shares).zip(remaining_accounts) {
+            let recipient_token_account: Account<TokenAccount> = Account::try_from(account)?;
+            assert_ata!(
+                recipient_token_account,
+                recipient.recipient,
+                self.rewarder.rewards_token_mint,
+                "fee recipient ata"
+            );
+            self.transfer_fees(recipient_token_account.to_account_info(), share)?;
+        }
+        self.transfer_fees(self.fee_to_token_account.to_account_info(), rest)?;
+
+        emit!(FeesExtractEvent {
+            rewarder: self.rewarder.key(),
+            amount,
+            fee_to_amount: rest,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    fn transfer_fees(&self, destination: AccountInfo<'info>, amount: u64) -> ProgramResult {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let seeds = gen_rewarder_signer_seeds!(self.rewarder);
+        let signer_seeds = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.claim_fee_token_account.to_account_info(),
+                    to: destination,
+                    authority: self.rewarder.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use vipers::program_err;
+
+    fn recipient(bps: u64) -> FeeRecipient {
+        FeeRecipient {
+            recipient: Pubkey::new_unique(),
+            bps,
+        }
+    }
+
+    #[test]
+    fn test_set_fee_split() {
+        let mut rewarder = Rewarder::default();
+        let recipients = [recipient(5_000), recipient(2_500)];
+        rewarder.set_fee_split(&recipients).unwrap();
+        assert_eq!(
+            rewarder.fee_recipients().copied().collect::<Vec<_>>(),
+            recipients
+        );
+
+        assert_eq!(
+            rewarder.set_fee_split(&[recipient(5_000), recipient(5_001)]),
+            program_err!(InvalidFeeSplit)
+        );
+        assert_eq!(
+            rewarder.set_fee_split(&[recipient(0)]),
+            program_err!(InvalidFeeSplit)
+        );
+        assert_eq!(
+            rewarder.set_fee_split(&[recipient(1); MAX_FEE_RECIPIENTS + 1]),
+            program_err!(InvalidFeeSplit)
+        );
+
+        rewarder.set_fee_split(&[]).unwrap();
+        assert_eq!(rewarder.fee_recipients().count(), 0);
+    }
+
+    #[test]
+    fn test_compute_fee_split() {
+        let mut rewarder = Rewarder::default();
+        assert_eq!(rewarder.compute_fee_split(1_000), Ok((vec![], 1_000)));
+
+        rewarder
+            .set_fee_split(&[recipient(3_333), recipient(3_333), recipient(3_334)])
+            .unwrap();
+        // rounding dust goes to the rest
+        assert_eq!(rewarder.compute_fee_split(100), Ok((vec![33, 33, 33], 1)));
+        assert_eq!(rewarder.compute_fee_split(0), Ok((vec![0, 0, 0], 0)));
+
+        rewarder
+            .set_fee_split(&[recipient(5_000), recipient(2_500)])
+            .unwrap();
+        assert_eq!(
+            rewarder.compute_fee_split(u64::MAX),
+            Ok((vec![u64::MAX / 2, u64::MAX / 4], u64::MAX / 4 + 2))
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Permissionless harvesting of the rewards of [Miner]s which opted in.
+//!
+//! Anyone may harvest, but rewards are only ever paid into the associated token account
+//! of the [Miner] authority. To harvest many [Miner]s at once, each [Miner] and the
+//! rewards token account of its authority are passed as remaining accounts, in pairs.
+//!
+//! Rewards are minted with [HarvestRewards], or transferred out of the rewards vault of
+//! [crate::RewarderFunding::Vault] [crate::Rewarder]s with [HarvestRewardsFromVault].
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use vipers::{assert_ata, assert_keys_eq, unwrap_int};
+
+use crate::vault::pay_from_vault;
+use crate::{ClaimEvent, HarvestRewards, HarvestRewardsFromVault, Miner, Quarry};
+
+/// Number of remaining accounts passed per [Miner] when harvesting many.
+pub const NUM_HARVEST_ACCOUNTS: usize = 2;
+
+impl<'info> HarvestRewards<'info> {
+    /// Harvests the rewards of a [Miner] into `rewards_token_account`.
+    pub fn harvest(
+        &mut self,
+        miner: &mut Account<'info, Miner>,
+        rewards_token_account: &Account<'info, TokenAccount>,
+        current_ts: i64,
+    ) -> ProgramResult {
+        validate_miner(
+            &self.quarry,
+            &self.rewards_token_mint.key(),
+            miner,
+            rewards_token_account,
+        )?;
+
+        self.quarry
+            .update_rewards_and_miner(miner, &self.rewarder, current_ts)?;
+
+        let amount_claimable = miner.claimable_rewards()?;
+        if amount_claimable == 0 {
+            // 0 claimable -- skip all logic
+            return Ok(());
+        }
+        let claim_fee = self.rewarder.compute_claim_fee(amount_claimable)?;
+        let amount_claimable_minus_fees = unwrap_int!(amount_claimable.checked_sub(claim_fee));
+        miner.rewards_earned = unwrap_int!(miner.rewards_earned.checked_sub(amount_claimable));
+
+        self.perform_mint(
+            rewards_token_account.to_account_info(),
+            amount_claimable_minus_fees,
+        )?;
+        self.perform_mint(self.claim_fee_token_account.to_account_info(), claim_fee)?;
+
+        emit!(ClaimEvent {
+            authority: miner.authority,
+            staked_token: self.quarry.nft_update_authority,
+            timestamp: current_ts,
+            rewards_token: self.rewards_token_mint.key(),
+            amount: amount_claimable_minus_fees,
+            fees: claim_fee,
+        });
+
+        Ok(())
+    }
+
+    /// Harvests the rewards of every [Miner] passed as remaining accounts.
+    pub fn harvest_many(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> ProgramResult {
+        for_each_miner(
+            remaining_accounts,
+            |miner, rewards_token_account, current_ts| {
+                self.harvest(miner, rewards_token_account, current_ts)
+            },
+        )
+    }
+
+    fn perform_mint(&self, destination: AccountInfo<'info>, amount: u64) -> ProgramResult {
+        let seeds = gen_rewarder_signer_seeds!(self.rewarder);
+        let signer_seeds = &[&seeds[..]];
+
+        quarry_mint_wrapper::cpi::perform_mint(
+            CpiContext::new_with_signer(
+                self.mint_wrapper_program.to_account_info(),
+                quarry_mint_wrapper::cpi::accounts::PerformMint {
+                    mint_wrapper: self.mint_wrapper.to_account_info(),
+                    minter_authority: self.rewarder.to_account_info(),
+                    token_mint: self.rewards_token_mint.to_account_info(),
+                    destination,
+                    minter: self.minter.to_account_info(),
+                    token_program: self.token_program.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )
+    }
+}
+
+impl<'info> HarvestRewardsFromVault<'info> {
+    /// Harvests the rewards of a [Miner] from the rewards vault into `rewards_token_account`.
+    ///
+    /// Once the vault is empty, rewards stay on the [Miner] until the vault is topped up.
+    pub fn harvest(
+        &mut self,
+        miner: &mut Account<'info, Miner>,
+        rewards_token_account: &Account<'info, TokenAccount>,
+        current_ts: i64,
+    ) -> ProgramResult {
+        validate_miner(
+            &self.quarry,
+            &self.rewarder.rewards_token_mint,
+            miner,
+            rewards_token_account,
+        )?;
+
+        self.quarry
+            .update_rewards_and_miner(miner, &self.rewarder, current_ts)?;
+
+        let rewards_claimable = miner.claimable_rewards()?;
+        if rewards_claimable == 0 || self.rewards_vault.amount == 0 {
+            // nothing to pay out -- skip all logic
+            return Ok(());
+        }
+        let (amount_claimable_minus_fees, claim_fee) = pay_from_vault(
+            &self.rewarder,
+            &mut self.rewards_vault,
+            miner,
+            rewards_claimable,
+            rewards_token_account.to_account_info(),
+            self.claim_fee_token_account.to_account_info(),
+            self.token_program.to_account_info(),
+        )?;
+
+        emit!(ClaimEvent {
+            authority: miner.authority,
+            staked_token: self.quarry.nft_update_authority,
+            timestamp: current_ts,
+            rewards_token: self.rewards_vault.mint,
+            amount: amount_claimable_minus_fees,
+            fees: claim_fee,
+        });
+
+        Ok(())
+    }
+
+    /// Harvests the rewards of every [Miner] passed as remaining accounts.
+    pub fn harvest_many(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> ProgramResult {
+        for_each_miner(
+            remaining_accounts,
+            |miner, rewards_token_account, current_ts| {
+                self.harvest(miner, rewards_token_account, current_ts)
+            },
+        )
+    }
+}
+
+/// Calls `harvest` on each [Miner] passed as remaining accounts, then saves the [Miner].
+fn for_each_miner<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    mut harvest: impl FnMut(
+        &mut Account<'info, Miner>,
+        &Account<'info, TokenAccount>,
+        i64,
+    ) -> ProgramResult,
+) -> ProgramResult {
+    let miners = remaining_accounts.chunks_exact(NUM_HARVEST_ACCOUNTS);
+    require!(
+        !remaining_accounts.is_empty() && miners.remainder().is_empty(),
+        InvalidHarvestAccounts
+    );
+
+    let current_ts = Clock::get()?.unix_timestamp;
+    for accounts in miners {
+        require!(accounts[0].is_writable, InvalidHarvestAccounts);
+        let mut miner: Account<Miner> = Account::try_from(&accounts[0])?;
+        let rewards_token_account: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+        harvest(&mut miner, &rewards_token_account, current_ts)?;
+        miner.exit(&crate::ID)?;
+    }
+
+    Ok(())
+}
+
+/// Checks that the [Miner] may be harvested into `rewards_token_account`.
+fn validate_miner(
+    quarry: &Account<Quarry>,
+    rewards_token_mint: &Pubkey,
+    miner: &Account<Miner>,
+    rewards_token_account: &Account<TokenAccount>,
+) -> ProgramResult {
+    require!(miner.allow_harvest, HarvestNotAllowed);
+    assert_keys_eq!(miner.quarry_key, *quarry, "quarry");
+    assert_ata!(
+        *rewards_token_account,
+        miner.authority,
+        *rewards_token_mint,
+        "rewards token account"
+    );
+    Ok(())
+}
@@ -17,19 +17,35 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::Token;
 use anchor_spl::token::{self, Mint, TokenAccount, Transfer};
 use metadata::Metadata;
-use payroll::Payroll;
-use std::cmp;
 use vipers::assert_keys_eq;
 use vipers::unwrap_int;
 use vipers::validate::Validate;
 
 pub mod account_validators;
 pub mod addresses;
+pub mod batch;
+pub mod checkpoint;
+pub mod close;
+pub mod custody;
+pub mod emergency;
+pub mod emission;
+pub mod fee_split;
+pub mod harvest;
+pub mod lockup;
+pub mod merkle_proof;
 pub mod metadata;
+pub mod nft;
 pub mod payroll;
+pub mod penalty;
 pub mod quarry;
 pub mod rewarder;
+pub mod stream;
+pub mod timelock;
+pub mod transfer;
+pub mod vault;
+pub mod weight_table;
 
+use crate::penalty::WithdrawnNfts;
 use crate::quarry::StakeAction;
 
 declare_id!("6RRreJu7qYTnp2rWs6n74hKhGHh4D58CaGG9tPm9ZMqk");
@@ -39,9 +55,12 @@ declare_id!("6RRreJu7qYTnp2rWs6n74hKhGHh4D58CaGG9tPm9ZMqk");
 pub const MAX_ANNUAL_REWARDS_RATE: u64 = u64::MAX >> 3;
 
 /// The fees of new [Rewarder]s: 1,000 milliBPS = 1 BP or 0.01%.
-/// This may be changed by governance in the future via program upgrade.
+/// The fee of each [Rewarder] may be changed by the [Config]::fee_setter.
 pub const DEFAULT_CLAIM_FEE_MILLIBPS: u64 = 1_000;
 
+/// Maximum fee the [Config]::fee_setter may set: 100,000 milliBPS = 100 BPS or 1%.
+pub const MAX_CLAIM_FEE_MILLIBPS: u64 = 100_000;
+
 /// Program for [quarry_mine].
 #[program]
 pub mod quarry_mine {
@@ -75,6 +94,8 @@ pub mod quarry_mine {
         rewarder.pause_authority = Pubkey::default();
         rewarder.is_paused = false;
 
+        rewarder.funding = RewarderFunding::MintWrapper;
+
         let current_ts = Clock::get()?.unix_timestamp;
         msg!("CURRENT TIMESTAMP {}", current_ts);
         emit!(NewRewarderEvent {
@@ -86,6 +107,53 @@ pub mod quarry_mine {
         Ok(())
     }
 
+    /// Creates a new [Rewarder] which pays rewards out of a pre-funded vault
+    /// instead of minting them.
+    #[access_control(ctx.accounts.validate())]
+    pub fn new_vault_rewarder(
+        ctx: Context<NewVaultRewarder>,
+        bump: u8,
+        _vault_bump: u8,
+    ) -> ProgramResult {
+        let rewarder = &mut ctx.accounts.rewarder;
+
+        rewarder.base = ctx.accounts.base.key();
+        rewarder.bump = bump;
+
+        rewarder.authority = ctx.accounts.authority.key();
+        rewarder.pending_authority = Pubkey::default();
+
+        rewarder.annual_rewards_rate = 0;
+        rewarder.num_quarries = 0;
+        rewarder.total_rewards_shares = 0;
+        rewarder.mint_wrapper = Pubkey::default();
+
+        rewarder.rewards_token_mint = ctx.accounts.rewards_token_mint.key();
+
+        rewarder.claim_fee_token_account = ctx.accounts.claim_fee_token_account.key();
+        rewarder.max_claim_fee_millibps = DEFAULT_CLAIM_FEE_MILLIBPS;
+
+        rewarder.pause_authority = Pubkey::default();
+        rewarder.is_paused = false;
+
+        rewarder.funding = RewarderFunding::Vault;
+        rewarder.rewards_vault = ctx.accounts.rewards_vault.key();
+
+        emit!(NewRewarderEvent {
+            authority: rewarder.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Tops up the rewards vault of a [RewarderFunding::Vault] [Rewarder].
+    /// Anyone can call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn fund_rewards_vault(ctx: Context<FundRewardsVault>, amount: u64) -> ProgramResult {
+        ctx.accounts.fund_rewards_vault(amount)
+    }
+
     /// Sets the pause authority.
     #[access_control(ctx.accounts.validate())]
     pub fn set_pause_authority(ctx: Context<SetPauseAuthority>) -> ProgramResult {
@@ -94,6 +162,43 @@ pub mod quarry_mine {
         Ok(())
     }
 
+    /// Sets the claim fee of the [Rewarder].
+    /// This may only be called by the [Config]::fee_setter.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_claim_fee(ctx: Context<SetClaimFee>, max_claim_fee_millibps: u64) -> ProgramResult {
+        let rewarder = &mut ctx.accounts.rewarder;
+        let previous_claim_fee_millibps = rewarder.max_claim_fee_millibps;
+        rewarder.set_claim_fee(max_claim_fee_millibps)?;
+
+        emit!(ClaimFeeUpdateEvent {
+            rewarder: rewarder.key(),
+            previous_claim_fee_millibps,
+            max_claim_fee_millibps,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the [FeeRecipient]s sharing the claim fees of the [Rewarder].
+    /// This may only be called by the [Config]::fee_setter.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_fee_split(
+        ctx: Context<SetFeeSplit>,
+        recipients: Vec<FeeRecipient>,
+    ) -> ProgramResult {
+        let rewarder = &mut ctx.accounts.rewarder;
+        rewarder.set_fee_split(&recipients)?;
+
+        emit!(FeeSplitUpdateEvent {
+            rewarder: rewarder.key(),
+            recipients,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
     /// Pauses the [Rewarder].
     #[access_control(ctx.accounts.validate())]
     pub fn pause(ctx: Context<MutableRewarderWithPauseAuthority>) -> ProgramResult {
@@ -132,35 +237,189 @@ pub mod quarry_mine {
         Ok(())
     }
 
+    /// Closes a [Rewarder] without [Quarry]s, returning its rent to the [Rewarder]::authority.
+    ///
+    /// Its fees must have been extracted with [quarry_mine::extract_fees]; the
+    /// [Rewarder]::claim_fee_token_account is closed too.
+    /// The rewards vault of a [RewarderFunding::Vault] [Rewarder] is emptied and closed too;
+    /// see [CloseRewarder::close_rewards_vault] for its remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn close_rewarder<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseRewarder<'info>>,
+    ) -> ProgramResult {
+        ctx.accounts.close_claim_fee_token_account()?;
+        ctx.accounts.close_rewards_vault(ctx.remaining_accounts)?;
+
+        emit!(RewarderCloseEvent {
+            rewarder: ctx.accounts.rewarder.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
     /// Sets the amount of reward tokens distributed to all [Quarry]s per day.
+    /// This replaces any [EmissionSchedule] of the [Rewarder].
+    ///
+    /// Every [Quarry] of the [Rewarder] is passed as a remaining account and settled at the
+    /// previous rate first; see [checkpoint].
     #[access_control(ctx.accounts.validate())]
-    pub fn set_annual_rewards(ctx: Context<SetAnnualRewards>, new_rate: u64) -> ProgramResult {
-        require!(
-            new_rate <= MAX_ANNUAL_REWARDS_RATE,
-            MaxAnnualRewardsRateExceeded
-        );
+    pub fn set_annual_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetAnnualRewards<'info>>,
+        new_rate: u64,
+    ) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        let rewarder = &mut ctx.accounts.auth.rewarder;
+        let mut quarries = checkpoint::load_quarries(rewarder, ctx.remaining_accounts, None)?;
+        let mut quarry_refs: Vec<&mut Quarry> =
+            quarries.iter_mut().map(|quarry| &mut **quarry).collect();
+        rewarder.update_annual_rewards_rate(&mut quarry_refs, new_rate, current_ts)?;
+        checkpoint::exit_quarries(&quarries)
+    }
+
+    /// Sets the [EmissionSchedule] of the [Rewarder], replacing any previous schedule.
+    ///
+    /// Each [EmissionStep] changes the annual rewards rate at its `start_ts`, without
+    /// requiring [quarry_mine::update_quarry_rewards] to be called.
+    ///
+    /// Every [Quarry] of the [Rewarder] is passed as a remaining account and settled at the
+    /// previous schedule first; see [checkpoint].
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_emission_schedule<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetEmissionSchedule<'info>>,
+        preset: EmissionPreset,
+    ) -> ProgramResult {
+        let steps = preset.to_steps()?;
+        let current_ts = Clock::get()?.unix_timestamp;
+        let schedule = EmissionSchedule::new(&steps, current_ts)?;
+        let rewarder = &mut ctx.accounts.auth.rewarder;
+        let mut quarries = checkpoint::load_quarries(rewarder, ctx.remaining_accounts, None)?;
+        let mut quarry_refs: Vec<&mut Quarry> =
+            quarries.iter_mut().map(|quarry| &mut **quarry).collect();
+        rewarder.update_emission_schedule(&mut quarry_refs, schedule, current_ts)?;
+        checkpoint::exit_quarries(&quarries)?;
+
+        emit!(EmissionScheduleUpdateEvent {
+            steps,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the delay of changes to the [Rewarder]'s parameters. The delay may only increase.
+    ///
+    /// Once set, changes must be proposed with [quarry_mine::propose_change].
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_timelock_delay(ctx: Context<SetTimelockDelay>, delay: i64) -> ProgramResult {
+        let rewarder = &mut ctx.accounts.auth.rewarder;
+        let previous_delay = rewarder.timelock_delay;
+        rewarder.set_timelock_delay(delay)?;
+
+        emit!(TimelockDelayUpdateEvent {
+            rewarder: rewarder.key(),
+            previous_delay,
+            delay,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Proposes a change to the [Rewarder]'s parameters, which may be executed from `eta`.
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn propose_change(
+        ctx: Context<ProposeChange>,
+        bump: u8,
+        change: ParameterChange,
+        eta: i64,
+    ) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
         let rewarder = &mut ctx.accounts.auth.rewarder;
-        let previous_rate = rewarder.annual_rewards_rate;
-        rewarder.annual_rewards_rate = new_rate;
+        rewarder.assert_valid_eta(eta, current_ts)?;
+        if let ParameterChange::AnnualRewards { new_rate } = change {
+            require!(
+                new_rate <= MAX_ANNUAL_REWARDS_RATE,
+                MaxAnnualRewardsRateExceeded
+            );
+        }
+        let index = rewarder.num_queued_changes;
+        rewarder.num_queued_changes = unwrap_int!(rewarder.num_queued_changes.checked_add(1));
+
+        let queued_change = &mut ctx.accounts.queued_change;
+        queued_change.rewarder = rewarder.key();
+        queued_change.index = index;
+        queued_change.bump = bump;
+        queued_change.payer = ctx.accounts.payer.key();
+        queued_change.change = change;
+        queued_change.eta = eta;
+
+        emit!(ChangeProposeEvent {
+            rewarder: queued_change.rewarder,
+            queued_change: queued_change.key(),
+            change,
+            eta,
+            timestamp: current_ts,
+        });
 
+        Ok(())
+    }
+
+    /// Executes a [QueuedChange] whose `eta` has passed. Anyone can call this.
+    ///
+    /// See [ExecuteChange::execute] for the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn execute_change<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteChange<'info>>,
+    ) -> ProgramResult {
         let current_ts = Clock::get()?.unix_timestamp;
-        emit!(RewarderAnnualRewardsUpdateEvent {
-            previous_rate,
-            new_rate,
+        ctx.accounts.execute(ctx.remaining_accounts, current_ts)?;
+
+        let queued_change = &ctx.accounts.queued_change;
+        emit!(ChangeExecuteEvent {
+            rewarder: queued_change.rewarder,
+            queued_change: queued_change.key(),
+            change: queued_change.change,
             timestamp: current_ts,
         });
 
         Ok(())
     }
 
+    /// Cancels a [QueuedChange].
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn cancel_change(ctx: Context<CancelChange>) -> ProgramResult {
+        let queued_change = &ctx.accounts.queued_change;
+        emit!(ChangeCancelEvent {
+            rewarder: queued_change.rewarder,
+            queued_change: queued_change.key(),
+            change: queued_change.change,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
     /// --------------------------------
     /// Quarry functions
     /// --------------------------------
 
     /// Creates a new [Quarry].
     /// This may only be called by the [Rewarder]::authority.
+    ///
+    /// The `nft_gate` determines which NFTs may be staked. `nft_gate_key` is the
+    /// collection mint for [NftGate::VerifiedCollection], the creator address for
+    /// [NftGate::VerifiedCreator], and is ignored otherwise. Gated [Quarry]s are addressed
+    /// by their `nft_gate_key`, so each collection or creator may have its own [Quarry].
     #[access_control(ctx.accounts.validate())]
-    pub fn create_quarry(ctx: Context<CreateQuarry>, bump: u8) -> ProgramResult {
+    pub fn create_quarry(
+        ctx: Context<CreateQuarry>,
+        bump: u8,
+        nft_gate: NftGate,
+        nft_gate_key: Pubkey,
+    ) -> ProgramResult {
         let rewarder = &mut ctx.accounts.auth.rewarder;
         // Update rewarder's quarry stats
         let index = rewarder.num_quarries;
@@ -177,54 +436,170 @@ pub mod quarry_mine {
         quarry.token_mint_decimals = 0;
         quarry.rewards_share = 0;
         quarry.nft_update_authority = *ctx.accounts.nft_update_authority.to_account_info().key;
+        quarry.nft_gate = nft_gate;
+        quarry.nft_gate_key = match nft_gate {
+            NftGate::UpdateAuthority => Pubkey::default(),
+            NftGate::VerifiedCollection | NftGate::VerifiedCreator => {
+                require!(nft_gate_key != Pubkey::default(), InvalidNftGateKey);
+                nft_gate_key
+            }
+        };
+        quarry.kind = QuarryKind::Nft;
 
         let current_ts = Clock::get()?.unix_timestamp;
         emit!(QuarryCreateEvent {
+            kind: quarry.kind,
+            token_mint: quarry.token_mint_key,
             nft_update_authority: quarry.nft_update_authority,
+            nft_gate: quarry.nft_gate,
+            nft_gate_key: quarry.nft_gate_key,
             timestamp: current_ts,
         });
 
         Ok(())
     }
 
-    /// Sets the rewards share of a quarry.
+    /// Creates a new [Quarry] staking a fungible token, such as an LP token.
+    /// This may only be called by the [Rewarder]::authority.
+    ///
+    /// The [Quarry] has no NFT update authority, so [Miner]s are created with the
+    /// default [Pubkey] as their `nft_update_authority`.
     #[access_control(ctx.accounts.validate())]
-    pub fn set_rewards_share(ctx: Context<SetRewardsShare>, new_share: u64) -> ProgramResult {
-        // Rewarders annual rate -> 10,950,000
-        // Rewarders total reward share -> 0
-        // Quarry's reward share -> 0
-
-        // New share -> 10
-
-        // New value of rewarders total share -> 20
-
+    pub fn create_fungible_quarry(ctx: Context<CreateFungibleQuarry>, bump: u8) -> ProgramResult {
         let rewarder = &mut ctx.accounts.auth.rewarder;
+        // Update rewarder's quarry stats
+        let index = rewarder.num_quarries;
+        rewarder.num_quarries = unwrap_int!(rewarder.num_quarries.checked_add(1));
+
         let quarry = &mut ctx.accounts.quarry;
-        rewarder.total_rewards_shares = unwrap_int!(rewarder
-            .total_rewards_shares
-            .checked_add(new_share)
-            .and_then(|v| v.checked_sub(quarry.rewards_share)));
+        quarry.bump = bump;
 
-        let now = Clock::get()?.unix_timestamp;
-        quarry.last_update_ts = cmp::min(now, quarry.famine_ts);
-        quarry.annual_rewards_rate = rewarder.compute_quarry_annual_rewards_rate(new_share)?;
-        quarry.rewards_share = new_share;
+        // Set quarry params
+        quarry.index = index;
+        quarry.famine_ts = i64::MAX;
+        quarry.rewarder_key = rewarder.key();
+        quarry.annual_rewards_rate = 0;
+        quarry.rewards_share = 0;
+        quarry.kind = QuarryKind::Fungible;
+        quarry.token_mint_key = ctx.accounts.token_mint.key();
+        quarry.token_mint_decimals = ctx.accounts.token_mint.decimals;
 
-        emit!(QuarryRewardsUpdateEvent {
+        let current_ts = Clock::get()?.unix_timestamp;
+        emit!(QuarryCreateEvent {
+            kind: quarry.kind,
+            token_mint: quarry.token_mint_key,
             nft_update_authority: quarry.nft_update_authority,
-            annual_rewards_rate: quarry.annual_rewards_rate,
-            rewards_share: quarry.rewards_share,
-            timestamp: now,
+            nft_gate: quarry.nft_gate,
+            nft_gate_key: quarry.nft_gate_key,
+            timestamp: current_ts,
         });
 
         Ok(())
     }
 
+    /// Sets the rewards share of a quarry.
+    ///
+    /// Every other [Quarry] of the [Rewarder] is passed as a remaining account, and all
+    /// [Quarry]s are settled at their previous rates first; see [checkpoint].
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_rewards_share<'info>(
+        ctx: Context<'_, '_, '_, 'info, SetRewardsShare<'info>>,
+        new_share: u64,
+    ) -> ProgramResult {
+        let now = Clock::get()?.unix_timestamp;
+        let rewarder = &mut ctx.accounts.auth.rewarder;
+        let quarry = &mut ctx.accounts.quarry;
+        let mut other_quarries =
+            checkpoint::load_quarries(rewarder, ctx.remaining_accounts, Some(quarry.key()))?;
+        let mut other_quarry_refs: Vec<&mut Quarry> = other_quarries
+            .iter_mut()
+            .map(|quarry| &mut **quarry)
+            .collect();
+        rewarder.update_rewards_share(quarry, &mut other_quarry_refs, new_share, now)?;
+        checkpoint::exit_quarries(&other_quarries)
+    }
+
     /// Sets the famine, which stops rewards.
     #[access_control(ctx.accounts.validate())]
     pub fn set_famine(ctx: Context<SetFamine>, famine_ts: i64) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        ctx.accounts
+            .quarry
+            .update_famine_ts(&ctx.accounts.auth.rewarder, famine_ts, current_ts)
+    }
+
+    /// Sets when the [Quarry] starts distributing rewards, and whether
+    /// [Miner]s may stake before then.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_start_ts(
+        ctx: Context<SetStartTs>,
+        start_ts: i64,
+        allow_early_staking: bool,
+    ) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        ctx.accounts.quarry.update_start_ts(
+            &ctx.accounts.auth.rewarder,
+            start_ts,
+            allow_early_staking,
+            current_ts,
+        )
+    }
+
+    /// Adds a [RewardStream] paying out `mint` to the [Quarry]'s [Miner]s.
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn add_reward_stream(
+        ctx: Context<AddRewardStream>,
+        _vault_bump: u8,
+        annual_rewards_rate: u64,
+    ) -> ProgramResult {
+        // checkpoint the quarry, so the stream only accrues from now on
+        let current_ts = Clock::get()?.unix_timestamp;
+        let rewarder = &ctx.accounts.auth.rewarder;
+        let payroll = ctx.accounts.quarry.payroll(rewarder)?;
         let quarry = &mut ctx.accounts.quarry;
-        quarry.famine_ts = famine_ts;
+        quarry.update_rewards_internal(current_ts, rewarder, &payroll)?;
+
+        let index = quarry.add_reward_stream(
+            ctx.accounts.mint.key(),
+            ctx.accounts.vault.key(),
+            annual_rewards_rate,
+        )?;
+
+        emit!(RewardStreamUpdateEvent {
+            quarry: quarry.key(),
+            index: index as u8,
+            rewards_token: ctx.accounts.mint.key(),
+            annual_rewards_rate,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the annual rewards rate of a [RewardStream].
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_reward_stream_rate(
+        ctx: Context<SetRewardStreamRate>,
+        index: u8,
+        annual_rewards_rate: u64,
+    ) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        let rewards_token = ctx.accounts.quarry.update_reward_stream_rate(
+            &ctx.accounts.auth.rewarder,
+            index,
+            annual_rewards_rate,
+            current_ts,
+        )?;
+
+        emit!(RewardStreamUpdateEvent {
+            quarry: ctx.accounts.quarry.key(),
+            index,
+            rewards_token,
+            annual_rewards_rate,
+            timestamp: current_ts,
+        });
 
         Ok(())
     }
@@ -235,7 +610,7 @@ pub mod quarry_mine {
     pub fn update_quarry_rewards(ctx: Context<UpdateQuarryRewards>) -> ProgramResult {
         let current_ts = Clock::get()?.unix_timestamp;
         let rewarder = &ctx.accounts.rewarder;
-        let payroll: Payroll = (*ctx.accounts.quarry).into();
+        let payroll = ctx.accounts.quarry.payroll(rewarder)?;
         let quarry = &mut ctx.accounts.quarry;
         quarry.update_rewards_internal(current_ts, rewarder, &payroll)?;
 
@@ -249,6 +624,152 @@ pub mod quarry_mine {
         Ok(())
     }
 
+    /// Closes a finished [Quarry], returning its rent to the [Rewarder]::authority.
+    /// This may only be called by the [Rewarder]::authority.
+    ///
+    /// The [Quarry] must be past its famine, have no [Miner]s left, and have no rewards share.
+    /// The vaults of its [RewardStream]s are emptied and closed too;
+    /// see [CloseQuarry::close_reward_stream_vaults] for their remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn close_quarry<'info>(
+        ctx: Context<'_, '_, '_, 'info, CloseQuarry<'info>>,
+    ) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        ctx.accounts.quarry.assert_closable(current_ts)?;
+        ctx.accounts
+            .close_reward_stream_vaults(ctx.remaining_accounts)?;
+        let quarry = &ctx.accounts.quarry;
+        ctx.accounts.rewarder.remove_quarry(quarry)?;
+
+        emit!(QuarryCloseEvent {
+            rewarder: quarry.rewarder_key,
+            quarry: quarry.key(),
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a [WeightTable] for a [Quarry], which assigns per-NFT staking weights.
+    /// This may only be called by the [Rewarder]::authority, before the [Rewarder] is timelocked.
+    #[access_control(ctx.accounts.validate())]
+    pub fn create_weight_table(
+        ctx: Context<CreateWeightTable>,
+        bump: u8,
+        capacity: u32,
+    ) -> ProgramResult {
+        let weight_table = &mut ctx.accounts.weight_table;
+        weight_table.quarry = ctx.accounts.quarry.key();
+        weight_table.bump = bump;
+        weight_table.capacity = capacity;
+
+        let quarry = &mut ctx.accounts.quarry;
+        quarry.weight_table = weight_table.key();
+
+        Ok(())
+    }
+
+    /// Sets the weights of NFT mints in a [WeightTable].
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_nft_weights(ctx: Context<SetNftWeights>, weights: Vec<NftWeight>) -> ProgramResult {
+        let weight_table = &mut ctx.accounts.weight_table;
+        for weight in weights {
+            weight_table.insert(weight)?;
+        }
+
+        let current_ts = Clock::get()?.unix_timestamp;
+        emit!(NftWeightsUpdateEvent {
+            quarry: weight_table.quarry,
+            num_entries: weight_table.entries.len() as u32,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the merkle root of `(mint, weight)` pairs used to weigh NFTs staked into a [Quarry].
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_weights_merkle_root(
+        ctx: Context<SetWeightsMerkleRoot>,
+        root: [u8; 32],
+    ) -> ProgramResult {
+        let quarry = &mut ctx.accounts.quarry;
+        quarry.weights_merkle_root = root;
+
+        let current_ts = Clock::get()?.unix_timestamp;
+        emit!(WeightsMerkleRootUpdateEvent {
+            quarry: quarry.key(),
+            root,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the [LockupTier]s stakers of a [Quarry] may choose from.
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_lockup_tiers(
+        ctx: Context<SetLockupTiers>,
+        lockup_tiers: Vec<LockupTier>,
+    ) -> ProgramResult {
+        let quarry = &mut ctx.accounts.quarry;
+        quarry.set_lockup_tiers(&lockup_tiers)?;
+
+        let current_ts = Clock::get()?.unix_timestamp;
+        emit!(LockupTiersUpdateEvent {
+            quarry: quarry.key(),
+            lockup_tiers,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the early unstake penalty schedule of a [Quarry].
+    /// This may only be called by the [Rewarder]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_unstake_penalties(
+        ctx: Context<SetUnstakePenalties>,
+        penalties: Vec<UnstakePenalty>,
+        destination: PenaltyDestination,
+        treasury: Pubkey,
+    ) -> ProgramResult {
+        let quarry = &mut ctx.accounts.quarry;
+        quarry.set_unstake_penalties(&penalties, destination, treasury)?;
+
+        let current_ts = Clock::get()?.unix_timestamp;
+        emit!(UnstakePenaltiesUpdateEvent {
+            quarry: quarry.key(),
+            penalties,
+            destination,
+            treasury,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Mints the rewards forfeited by early unstakers to the [Quarry]::penalty_treasury,
+    /// for a [RewarderFunding::MintWrapper] [Rewarder].
+    /// Anyone can call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn claim_forfeited_rewards(ctx: Context<ClaimForfeitedRewards>) -> ProgramResult {
+        ctx.accounts.claim_forfeited_rewards()
+    }
+
+    /// Transfers the rewards forfeited by early unstakers to the [Quarry]::penalty_treasury
+    /// from the rewards vault of a [RewarderFunding::Vault] [Rewarder].
+    /// Anyone can call this.
+    #[access_control(ctx.accounts.validate())]
+    pub fn claim_forfeited_rewards_from_vault(
+        ctx: Context<ClaimForfeitedRewardsFromVault>,
+    ) -> ProgramResult {
+        ctx.accounts.claim_forfeited_rewards()
+    }
+
     /// --------------------------------
     /// Miner functions
     /// --------------------------------
@@ -280,106 +801,585 @@ pub mod quarry_mine {
         Ok(())
     }
 
-    /// Stakes tokens into the [Miner].
-    #[access_control(ctx.accounts.validate(metadata_bump))]
-    pub fn stake_nft(ctx: Context<UserStake>, amount: u8, metadata_bump: u8) -> ProgramResult {
-        if amount == 0 {
-            // noop
-            return Ok(());
-        }
-
-        if amount > 1 {
-            // Error
-            return Err(ProgramError::InvalidAccountData);
-        }
-
+    /// Transfers the authority of a [Miner], which must be accepted
+    /// with [quarry_mine::accept_miner_authority].
+    #[access_control(ctx.accounts.validate())]
+    pub fn transfer_miner_authority(
+        ctx: Context<TransferMinerAuthority>,
+        new_authority: Pubkey,
+    ) -> ProgramResult {
+        let miner = &mut ctx.accounts.miner;
+        miner.pending_authority = new_authority;
+        Ok(())
+    }
+
+    /// Accepts the authority of a [Miner], moving its position into a new [Miner]
+    /// of the new authority and closing the old one.
+    ///
+    /// See [transfer] for the layout of the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn accept_miner_authority<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptMinerAuthority<'info>>,
+        bump: u8,
+        receipt_bumps: Vec<u8>,
+    ) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        ctx.accounts
+            .transfer_position(ctx.remaining_accounts, &receipt_bumps, current_ts)?;
+
+        let new_miner = &mut ctx.accounts.new_miner;
+        new_miner.bump = bump;
+        new_miner.authority = ctx.accounts.new_authority.key();
+
+        emit!(MinerAuthorityTransferEvent {
+            quarry: new_miner.quarry_key,
+            previous_authority: ctx.accounts.previous_authority.key(),
+            authority: new_miner.authority,
+            miner: new_miner.key(),
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Closes an empty [Miner], returning its rent to the [Miner]::authority.
+    ///
+    /// The [Miner] must have no stake and no unclaimed rewards.
+    #[access_control(ctx.accounts.validate())]
+    pub fn close_miner(ctx: Context<CloseMiner>) -> ProgramResult {
         let quarry = &mut ctx.accounts.quarry;
+        quarry.num_miners = unwrap_int!(quarry.num_miners.checked_sub(1));
+
+        emit!(MinerCloseEvent {
+            authority: ctx.accounts.authority.key(),
+            quarry: quarry.key(),
+            miner: ctx.accounts.miner.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Closes an empty token account of a [Miner], such as an emptied `miner_nft_vault`,
+    /// returning its rent to the [Miner]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn close_miner_vault(ctx: Context<CloseMinerVault>) -> ProgramResult {
+        ctx.accounts.close_vault()
+    }
+
+    /// Stakes an NFT into the [Miner].
+    ///
+    /// If the [Quarry] has a weights merkle root, `weight_proof` must prove the weight of the NFT.
+    /// If a `lockup_tier` is chosen, the NFT cannot be withdrawn until the lockup ends,
+    /// and its weight is boosted by the tier's multiplier.
+    #[access_control(ctx.accounts.validate(metadata_bump, edition_bump))]
+    pub fn stake_nft(
+        ctx: Context<StakeNft>,
+        amount: u8,
+        metadata_bump: u8,
+        edition_bump: u8,
+        receipt_bump: u8,
+        weight_proof: Option<WeightProof>,
+        lockup_tier: Option<u8>,
+    ) -> ProgramResult {
+        require!(amount == 1, InvalidNftAmount);
+
+        let weight = ctx
+            .accounts
+            .stake
+            .nft_weight(weight_proof.as_ref(), ctx.remaining_accounts)?;
+
+        let stake = &mut ctx.accounts.stake;
         let clock = Clock::get()?;
-        quarry.process_stake_action_internal(
+        let (weight, unlock_ts) =
+            stake
+                .quarry
+                .apply_lockup(lockup_tier, weight, clock.unix_timestamp)?;
+        stake.quarry.process_stake_action_internal(
             StakeAction::Stake,
             clock.unix_timestamp,
-            &ctx.accounts.rewarder,
-            &mut ctx.accounts.miner,
-            1,
+            &stake.rewarder,
+            &mut stake.miner,
+            weight,
         )?;
 
+        let stake_receipt = &mut ctx.accounts.stake_receipt;
+        stake_receipt.miner = stake.miner.key();
+        stake_receipt.quarry = stake.quarry.key();
+        stake_receipt.mint = stake.token_mint.key();
+        stake_receipt.bump = receipt_bump;
+        stake_receipt.weight = weight;
+        stake_receipt.stake_ts = clock.unix_timestamp;
+        stake_receipt.unlock_ts = unlock_ts;
+
         let cpi_accounts = Transfer {
-            from: ctx.accounts.token_account.to_account_info(),
-            to: ctx.accounts.miner_nft_vault.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+            from: stake.token_account.to_account_info(),
+            to: stake.miner_nft_vault.to_account_info(),
+            authority: stake.authority.to_account_info(),
         };
-        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_program = stake.token_program.to_account_info();
         let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
         // Transfer NFT to miner vault
         token::transfer(cpi_context, 1)?;
 
         emit!(StakeEvent {
             timestamp: clock.unix_timestamp,
-            authority: ctx.accounts.authority.key(),
-            amount,
-            token: ctx.accounts.token_account.mint,
+            authority: stake.authority.key(),
+            amount: amount.into(),
+            weight,
+            unlock_ts,
+            token: stake.token_account.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws an NFT from the [Miner].
+    #[access_control(ctx.accounts.validate(metadata_bump, edition_bump))]
+    pub fn withdraw_nft(
+        ctx: Context<WithdrawNft>,
+        amount: u8,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        require!(amount == 1, InvalidNftAmount);
+
+        let stake = &mut ctx.accounts.stake;
+        require!(stake.miner_nft_vault.amount == 1, InsufficientBalance);
+
+        let clock = Clock::get()?;
+        ctx.accounts
+            .stake_receipt
+            .assert_unlocked(clock.unix_timestamp)?;
+
+        // Withdraw the weight the NFT was staked with, even if weights changed since.
+        let weight = ctx.accounts.stake_receipt.weight;
+        stake.quarry.process_stake_action_internal(
+            StakeAction::Withdraw,
+            clock.unix_timestamp,
+            &stake.rewarder,
+            &mut stake.miner,
+            weight,
+        )?;
+
+        let mut withdrawn = WithdrawnNfts::default();
+        withdrawn.add(
+            &stake.quarry,
+            &stake.miner,
+            weight,
+            ctx.accounts.stake_receipt.stake_ts,
+            clock.unix_timestamp,
+        )?;
+        penalty::apply_unstake_penalty(
+            &mut stake.quarry,
+            &mut stake.miner,
+            stake.authority.key(),
+            &withdrawn,
+            clock.unix_timestamp,
+        )?;
+
+        // Transfer out NFT from quarry vault
+        stake.return_nft()?;
+
+        emit!(WithdrawEvent {
+            timestamp: clock.unix_timestamp,
+            authority: stake.authority.key(),
+            amount: amount.into(),
+            weight,
+            token: stake.token_account.mint,
+        });
+        Ok(())
+    }
+
+    /// Withdraws an NFT from the [Miner] while the [Rewarder] is paused.
+    ///
+    /// Lockups and unstake penalties are ignored and no rewards are computed;
+    /// see [emergency] for what happens to unclaimed rewards.
+    #[access_control(ctx.accounts.validate_emergency(metadata_bump, edition_bump))]
+    pub fn emergency_withdraw_nft(
+        ctx: Context<WithdrawNft>,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        let stake = &mut ctx.accounts.stake;
+        let weight = ctx.accounts.stake_receipt.weight;
+        stake.quarry.emergency_withdraw(&mut stake.miner, weight)?;
+        stake
+            .miner
+            .release_penalized_weight(weight, ctx.accounts.stake_receipt.stake_ts)?;
+        stake.return_nft()?;
+
+        emit!(EmergencyWithdrawEvent {
+            timestamp: Clock::get()?.unix_timestamp,
+            authority: stake.authority.key(),
+            weight,
+            token: stake.token_account.mint,
+        });
+        Ok(())
+    }
+
+    /// Stakes an NFT into the [Miner] without taking custody of it.
+    ///
+    /// The NFT stays in the user's token account, which is delegated to the [Miner] and frozen.
+    /// Rewards accrue exactly as for [quarry_mine::stake_nft].
+    #[access_control(ctx.accounts.validate(metadata_bump, edition_bump))]
+    pub fn stake_nft_frozen(
+        ctx: Context<StakeNftFrozen>,
+        metadata_bump: u8,
+        edition_bump: u8,
+        receipt_bump: u8,
+        weight_proof: Option<WeightProof>,
+        lockup_tier: Option<u8>,
+    ) -> ProgramResult {
+        let stake = &mut ctx.accounts.stake;
+        require!(stake.token_account.amount == 1, InsufficientBalance);
+
+        let weight_table =
+            weight_table::load_weight_table(&stake.quarry, ctx.remaining_accounts.first())?;
+        let weight = weight_table::nft_weight(
+            &stake.quarry,
+            &stake.token_mint.key(),
+            weight_proof.as_ref(),
+            weight_table.as_deref(),
+        )?;
+
+        let clock = Clock::get()?;
+        let (weight, unlock_ts) =
+            stake
+                .quarry
+                .apply_lockup(lockup_tier, weight, clock.unix_timestamp)?;
+        stake.quarry.process_stake_action_internal(
+            StakeAction::Stake,
+            clock.unix_timestamp,
+            &stake.rewarder,
+            &mut stake.miner,
+            weight,
+        )?;
+
+        let stake_receipt = &mut ctx.accounts.stake_receipt;
+        stake_receipt.miner = stake.miner.key();
+        stake_receipt.quarry = stake.quarry.key();
+        stake_receipt.mint = stake.token_mint.key();
+        stake_receipt.bump = receipt_bump;
+        stake_receipt.weight = weight;
+        stake_receipt.stake_ts = clock.unix_timestamp;
+        stake_receipt.unlock_ts = unlock_ts;
+        stake_receipt.custody = StakeCustody::Frozen;
+
+        stake.freeze_nft()?;
+
+        emit!(StakeEvent {
+            timestamp: clock.unix_timestamp,
+            authority: stake.authority.key(),
+            amount: 1,
+            weight,
+            unlock_ts,
+            token: stake.token_account.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws an NFT staked with [quarry_mine::stake_nft_frozen], thawing it.
+    #[access_control(ctx.accounts.validate(metadata_bump, edition_bump))]
+    pub fn withdraw_nft_frozen(
+        ctx: Context<WithdrawNftFrozen>,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        let stake = &mut ctx.accounts.stake;
+        let clock = Clock::get()?;
+        ctx.accounts
+            .stake_receipt
+            .assert_unlocked(clock.unix_timestamp)?;
+
+        // Withdraw the weight the NFT was staked with, even if weights changed since.
+        let weight = ctx.accounts.stake_receipt.weight;
+        stake.quarry.process_stake_action_internal(
+            StakeAction::Withdraw,
+            clock.unix_timestamp,
+            &stake.rewarder,
+            &mut stake.miner,
+            weight,
+        )?;
+
+        let mut withdrawn = WithdrawnNfts::default();
+        withdrawn.add(
+            &stake.quarry,
+            &stake.miner,
+            weight,
+            ctx.accounts.stake_receipt.stake_ts,
+            clock.unix_timestamp,
+        )?;
+        penalty::apply_unstake_penalty(
+            &mut stake.quarry,
+            &mut stake.miner,
+            stake.authority.key(),
+            &withdrawn,
+            clock.unix_timestamp,
+        )?;
+
+        stake.thaw_nft()?;
+
+        emit!(WithdrawEvent {
+            timestamp: clock.unix_timestamp,
+            authority: stake.authority.key(),
+            amount: 1,
+            weight,
+            token: stake.token_account.mint,
         });
+        Ok(())
+    }
 
+    /// Thaws an NFT staked with [quarry_mine::stake_nft_frozen] while the [Rewarder] is paused.
+    ///
+    /// Lockups and unstake penalties are ignored and no rewards are computed;
+    /// see [emergency] for what happens to unclaimed rewards.
+    #[access_control(ctx.accounts.validate_emergency(metadata_bump, edition_bump))]
+    pub fn emergency_withdraw_nft_frozen(
+        ctx: Context<WithdrawNftFrozen>,
+        metadata_bump: u8,
+        edition_bump: u8,
+    ) -> ProgramResult {
+        let stake = &mut ctx.accounts.stake;
+        let weight = ctx.accounts.stake_receipt.weight;
+        stake.quarry.emergency_withdraw(&mut stake.miner, weight)?;
+        stake
+            .miner
+            .release_penalized_weight(weight, ctx.accounts.stake_receipt.stake_ts)?;
+        stake.thaw_nft()?;
+
+        emit!(EmergencyWithdrawEvent {
+            timestamp: Clock::get()?.unix_timestamp,
+            authority: stake.authority.key(),
+            weight,
+            token: stake.token_account.mint,
+        });
         Ok(())
     }
 
-    /// Withdraws tokens from the [Miner].
-    #[access_control(ctx.accounts.validate(metadata_bump))]
-    pub fn withdraw_nft(ctx: Context<UserStake>, amount: u8, metadata_bump: u8) -> ProgramResult {
+    /// Stakes fungible tokens into the [Miner].
+    #[access_control(ctx.accounts.validate())]
+    pub fn stake_tokens(ctx: Context<UserStakeTokens>, amount: u64) -> ProgramResult {
         if amount == 0 {
             // noop
             return Ok(());
         }
 
+        let clock = Clock::get()?;
+        let stake = &mut *ctx.accounts;
+        stake.quarry.process_stake_action_internal(
+            StakeAction::Stake,
+            clock.unix_timestamp,
+            &stake.rewarder,
+            &mut stake.miner,
+            amount,
+        )?;
+
+        // Transfer tokens to miner vault
+        token::transfer(
+            CpiContext::new(
+                stake.token_program.to_account_info(),
+                token::Transfer {
+                    from: stake.token_account.to_account_info(),
+                    to: stake.miner_vault.to_account_info(),
+                    authority: stake.authority.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        emit!(StakeEvent {
+            timestamp: clock.unix_timestamp,
+            authority: stake.authority.key(),
+            amount,
+            weight: amount,
+            unlock_ts: clock.unix_timestamp,
+            token: stake.token_account.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws fungible tokens from the [Miner].
+    #[access_control(ctx.accounts.validate())]
+    pub fn withdraw_tokens(ctx: Context<UserStakeTokens>, amount: u64) -> ProgramResult {
+        if amount == 0 {
+            // noop
+            return Ok(());
+        }
         require!(
-            amount == ctx.accounts.miner_nft_vault.amount as u8,
+            amount <= ctx.accounts.miner_vault.amount,
             InsufficientBalance
         );
 
         let clock = Clock::get()?;
-        let quarry = &mut ctx.accounts.quarry;
-        quarry.process_stake_action_internal(
+        let stake = &mut *ctx.accounts;
+        stake.quarry.process_stake_action_internal(
             StakeAction::Withdraw,
             clock.unix_timestamp,
-            &ctx.accounts.rewarder,
-            &mut ctx.accounts.miner,
-            1,
+            &stake.rewarder,
+            &mut stake.miner,
+            amount,
         )?;
-
-        // Sign a transfer instruction as the [Miner]
-        let miner_seeds = &[
-            b"Miner".as_ref(),
-            ctx.accounts.miner.quarry_key.as_ref(),
-            ctx.accounts.miner.authority.as_ref(),
-            &[ctx.accounts.miner.bump],
-        ];
-        let signer_seeds = &[&miner_seeds[..]];
-        let cpi_accounts = token::Transfer {
-            from: ctx.accounts.miner_nft_vault.to_account_info(),
-            to: ctx.accounts.token_account.to_account_info(),
-            authority: ctx.accounts.miner.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer_seeds,
-        );
-
-        // Transfer out NFT from quarry vault
-        token::transfer(cpi_ctx, 1)?;
+        stake.return_tokens(amount)?;
 
         emit!(WithdrawEvent {
             timestamp: clock.unix_timestamp,
-            authority: ctx.accounts.authority.key(),
+            authority: stake.authority.key(),
             amount,
-            token: ctx.accounts.token_account.mint,
+            weight: amount,
+            token: stake.token_account.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraws fungible tokens from the [Miner] while the [Rewarder] is paused.
+    ///
+    /// No rewards are computed; see [emergency] for what happens to unclaimed rewards.
+    #[access_control(ctx.accounts.validate_emergency())]
+    pub fn emergency_withdraw_tokens(ctx: Context<UserStakeTokens>, amount: u64) -> ProgramResult {
+        if amount == 0 {
+            // noop
+            return Ok(());
+        }
+        require!(
+            amount <= ctx.accounts.miner_vault.amount,
+            InsufficientBalance
+        );
+
+        let stake = &mut *ctx.accounts;
+        stake.quarry.emergency_withdraw(&mut stake.miner, amount)?;
+        stake.return_tokens(amount)?;
+
+        emit!(EmergencyWithdrawEvent {
+            timestamp: Clock::get()?.unix_timestamp,
+            authority: stake.authority.key(),
+            weight: amount,
+            token: stake.token_account.mint,
         });
         Ok(())
     }
 
+    /// Stakes many NFTs into the [Miner] at once.
+    ///
+    /// Rewards are checkpointed once and the [Miner]'s balance grows by the total weight.
+    /// See [batch] for the layout of the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn stake_nfts<'info>(
+        ctx: Context<'_, '_, '_, 'info, UserStakeBatch<'info>>,
+        nfts: Vec<BatchStakeArgs>,
+    ) -> ProgramResult {
+        ctx.accounts.stake_nfts(ctx.remaining_accounts, &nfts)
+    }
+
+    /// Withdraws many NFTs from the [Miner] at once.
+    ///
+    /// Rewards are checkpointed once and the [Miner]'s balance shrinks by the total weight.
+    /// See [batch] for the layout of the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn withdraw_nfts<'info>(
+        ctx: Context<'_, '_, '_, 'info, UserStakeBatch<'info>>,
+        nfts: Vec<BatchWithdrawArgs>,
+    ) -> ProgramResult {
+        ctx.accounts.withdraw_nfts(ctx.remaining_accounts, &nfts)
+    }
+
     /// Claims rewards for the [Miner].
+    ///
+    /// Rewards of the [Quarry]'s [RewardStream]s are paid out too, if their accounts
+    /// are passed as remaining accounts. See [stream] for their layout.
+    #[access_control(ctx.accounts.validate())]
+    pub fn claim_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimRewards<'info>>,
+    ) -> ProgramResult {
+        let miner = &mut ctx.accounts.stake.miner;
+
+        let now = Clock::get()?.unix_timestamp;
+        let quarry = &mut ctx.accounts.stake.quarry;
+        quarry.update_rewards_and_miner(miner, &ctx.accounts.stake.rewarder, now)?;
+
+        ctx.accounts.calculate_and_claim_rewards()?;
+        ctx.accounts
+            .stake
+            .claim_reward_streams(ctx.remaining_accounts)?;
+
+        Ok(())
+    }
+
+    /// Sets whether anyone may harvest the rewards of the [Miner] into the
+    /// associated token account of its authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_allow_harvest(ctx: Context<SetAllowHarvest>, allow_harvest: bool) -> ProgramResult {
+        let miner = &mut ctx.accounts.miner;
+        miner.allow_harvest = allow_harvest;
+        Ok(())
+    }
+
+    /// Harvests the rewards of a [Miner] which allows harvesting.
+    /// Anyone can call this.
+    ///
+    /// This mints rewards, so it requires a [RewarderFunding::MintWrapper] [Rewarder].
+    /// [RewarderFunding::Vault] [Rewarder]s are harvested with [quarry_mine::harvest_from_vault].
+    #[access_control(ctx.accounts.validate())]
+    pub fn harvest(ctx: Context<Harvest>) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        let accounts = &mut *ctx.accounts;
+        accounts.rewards.harvest(
+            &mut accounts.miner,
+            &accounts.rewards_token_account,
+            current_ts,
+        )
+    }
+
+    /// Harvests the rewards of many [Miner]s which allow harvesting.
+    /// Anyone can call this.
+    ///
+    /// See [mod@harvest] for the layout of the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn harvest_many<'info>(
+        ctx: Context<'_, '_, '_, 'info, HarvestRewards<'info>>,
+    ) -> ProgramResult {
+        ctx.accounts.harvest_many(ctx.remaining_accounts)
+    }
+
+    /// Harvests the rewards of a [Miner] which allows harvesting from the rewards vault
+    /// of a [RewarderFunding::Vault] [Rewarder].
+    /// Anyone can call this.
+    ///
+    /// As with [quarry_mine::claim_rewards_from_vault], only what the vault holds is paid out.
+    #[access_control(ctx.accounts.validate())]
+    pub fn harvest_from_vault(ctx: Context<HarvestFromVault>) -> ProgramResult {
+        let current_ts = Clock::get()?.unix_timestamp;
+        let accounts = &mut *ctx.accounts;
+        accounts.rewards.harvest(
+            &mut accounts.miner,
+            &accounts.rewards_token_account,
+            current_ts,
+        )
+    }
+
+    /// Harvests the rewards of many [Miner]s which allow harvesting from the rewards vault
+    /// of a [RewarderFunding::Vault] [Rewarder].
+    /// Anyone can call this.
+    ///
+    /// See [mod@harvest] for the layout of the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn harvest_many_from_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, HarvestRewardsFromVault<'info>>,
+    ) -> ProgramResult {
+        ctx.accounts.harvest_many(ctx.remaining_accounts)
+    }
+
+    /// Claims rewards for the [Miner] from the rewards vault of a
+    /// [RewarderFunding::Vault] [Rewarder].
+    ///
+    /// Rewards of the [Quarry]'s [RewardStream]s are paid out too, if their accounts
+    /// are passed as remaining accounts. See [stream] for their layout.
     #[access_control(ctx.accounts.validate())]
-    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> ProgramResult {
+    pub fn claim_rewards_from_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimRewardsFromVault<'info>>,
+    ) -> ProgramResult {
         let miner = &mut ctx.accounts.stake.miner;
 
         let now = Clock::get()?.unix_timestamp;
@@ -387,6 +1387,9 @@ pub mod quarry_mine {
         quarry.update_rewards_and_miner(miner, &ctx.accounts.stake.rewarder, now)?;
 
         ctx.accounts.calculate_and_claim_rewards()?;
+        ctx.accounts
+            .stake
+            .claim_reward_streams(ctx.remaining_accounts)?;
 
         Ok(())
     }
@@ -395,44 +1398,102 @@ pub mod quarry_mine {
     /// Protocol Functions
     /// --------------------------------
 
-    /// Extracts fees to the Honey DAO.
-    /// This can be called by anyone.
+    /// Creates the protocol [Config], with the fee addresses of [addresses].
+    /// This may only be called by the [addresses::FEE_SETTER].
     #[access_control(ctx.accounts.validate())]
-    pub fn extract_fees(ctx: Context<ExtractFees>) -> ProgramResult {
-        let seeds = gen_rewarder_signer_seeds!(ctx.accounts.rewarder);
-        let signer_seeds = &[&seeds[..]];
-
-        msg!(
-            "Transferring {} amount of tokens to Quarry DAO {}",
-            ctx.accounts.claim_fee_token_account.amount,
-            ctx.accounts
-                .fee_to_token_account
-                .to_account_info()
-                .key
-                .to_string()
-        );
-        // Transfer the tokens to the DAO address.
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                token::Transfer {
-                    from: ctx.accounts.claim_fee_token_account.to_account_info(),
-                    to: ctx.accounts.fee_to_token_account.to_account_info(),
-                    authority: ctx.accounts.rewarder.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            ctx.accounts.claim_fee_token_account.amount,
-        )?;
+    pub fn init_config(ctx: Context<InitConfig>, bump: u8) -> ProgramResult {
+        let config = &mut ctx.accounts.config;
+        config.bump = bump;
+        config.authority = ctx.accounts.authority.key();
+        config.pending_authority = Pubkey::default();
+        config.fee_to = addresses::FEE_TO;
+        config.fee_setter = addresses::FEE_SETTER;
+
+        emit!(ConfigUpdateEvent {
+            fee_to: config.fee_to,
+            fee_setter: config.fee_setter,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
 
+    /// Sets the fee addresses of the [Config].
+    /// This may only be called by the [Config]::authority.
+    #[access_control(ctx.accounts.validate())]
+    pub fn set_fee_addresses(
+        ctx: Context<MutableConfigWithAuthority>,
+        fee_to: Pubkey,
+        fee_setter: Pubkey,
+    ) -> ProgramResult {
+        let config = &mut ctx.accounts.config;
+        config.fee_to = fee_to;
+        config.fee_setter = fee_setter;
+
+        emit!(ConfigUpdateEvent {
+            fee_to,
+            fee_setter,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Transfers the authority of the [Config], which must be accepted
+    /// with [quarry_mine::accept_config_authority].
+    #[access_control(ctx.accounts.validate())]
+    pub fn transfer_config_authority(
+        ctx: Context<MutableConfigWithAuthority>,
+        new_authority: Pubkey,
+    ) -> ProgramResult {
+        let config = &mut ctx.accounts.config;
+        config.pending_authority = new_authority;
+        Ok(())
+    }
+
+    /// Accepts the authority of the [Config].
+    #[access_control(ctx.accounts.validate())]
+    pub fn accept_config_authority(ctx: Context<AcceptConfigAuthority>) -> ProgramResult {
+        let config = &mut ctx.accounts.config;
+        config.authority = config.pending_authority;
+        config.pending_authority = Pubkey::default();
         Ok(())
     }
+
+    /// Extracts fees to the [Rewarder]'s [FeeRecipient]s and the Honey DAO.
+    /// This can be called by anyone.
+    ///
+    /// See [fee_split] for the layout of the remaining accounts.
+    #[access_control(ctx.accounts.validate())]
+    pub fn extract_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExtractFees<'info>>,
+    ) -> ProgramResult {
+        ctx.accounts.extract_fees(ctx.remaining_accounts)
+    }
 }
 
 // --------------------------------
 // PDA Structs
 // --------------------------------
 
+/// Protocol-wide configuration, governing where fees go and who sets them.
+#[account]
+#[derive(Copy, Default, Debug)]
+pub struct Config {
+    /// Bump seed for program address.
+    pub bump: u8,
+
+    /// Governance authority, who may update the [Config].
+    pub authority: Pubkey,
+    /// Pending authority which must accept the authority.
+    pub pending_authority: Pubkey,
+
+    /// Account authorized to take fees, with [quarry_mine::extract_fees].
+    pub fee_to: Pubkey,
+    /// Account authorized to set fees of a [Rewarder], with [quarry_mine::set_claim_fee].
+    pub fee_setter: Pubkey,
+}
+
 /// Controls token rewards distribution to all [Quarry]s.
 /// The [Rewarder] is also the [quarry_mint_wrapper::Minter] registered to the [quarry_mint_wrapper::MintWrapper].
 #[account]
@@ -463,19 +1524,219 @@ pub struct Rewarder {
 
     /// Claim fees are placed in this account.
     pub claim_fee_token_account: Pubkey,
-    /// Maximum amount of tokens to send to the Quarry DAO on each claim,
+    /// Amount of tokens to send to the Quarry DAO on each claim,
     /// in terms of milliBPS. 1,000 milliBPS = 1 BPS = 0.01%
-    /// This is stored on the [Rewarder] to ensure that the fee will
-    /// not exceed this in the future.
+    /// This may be changed by the [Config]::fee_setter with [quarry_mine::set_claim_fee],
+    /// up to [MAX_CLAIM_FEE_MILLIBPS].
     pub max_claim_fee_millibps: u64,
 
     /// Authority allowed to pause a [Rewarder].
     pub pause_authority: Pubkey,
     /// If true, all instructions on the [Rewarder] are paused other than [quarry_mine::unpause].
     pub is_paused: bool,
-}
 
-/// A pool which distributes tokens to its [Miner]s.
+    /// Scheduled changes of the [Rewarder]::annual_rewards_rate.
+    pub emission_schedule: EmissionSchedule,
+
+    /// How rewards are paid out.
+    pub funding: RewarderFunding,
+    /// Token account rewards are paid out of, for [RewarderFunding::Vault].
+    pub rewards_vault: Pubkey,
+
+    /// Recipients sharing the claim fees with the [Config]::fee_to.
+    /// Holds up to [fee_split::MAX_FEE_RECIPIENTS] recipients; unused recipients are zeroed.
+    pub fee_split: [FeeRecipient; 4],
+
+    /// Minimum number of seconds between proposing and executing a [QueuedChange].
+    /// If zero, parameters may be changed directly.
+    pub timelock_delay: i64,
+    /// Number of [QueuedChange]s ever proposed.
+    pub num_queued_changes: u64,
+}
+
+/// A change to the parameters of a [Rewarder], waiting for its timelock to elapse.
+#[account]
+#[derive(Copy, Debug)]
+pub struct QueuedChange {
+    /// [Rewarder] the change applies to.
+    pub rewarder: Pubkey,
+    /// Index of the change among the [Rewarder]'s [QueuedChange]s.
+    pub index: u64,
+    /// Bump seed for program address.
+    pub bump: u8,
+    /// Payer of the account, who receives its rent once it is executed or cancelled.
+    pub payer: Pubkey,
+    /// The change.
+    pub change: ParameterChange,
+    /// Earliest time the change may be executed.
+    pub eta: i64,
+}
+
+/// A change to the parameters of a [Rewarder] or its [Quarry]s.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ParameterChange {
+    /// Sets the [Rewarder]::annual_rewards_rate, as [quarry_mine::set_annual_rewards].
+    AnnualRewards {
+        /// New annual rewards rate.
+        new_rate: u64,
+    },
+    /// Sets the rewards share of a [Quarry], as [quarry_mine::set_rewards_share].
+    RewardsShare {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// New rewards share.
+        new_share: u64,
+    },
+    /// Sets the famine of a [Quarry], as [quarry_mine::set_famine].
+    Famine {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// New famine timestamp.
+        famine_ts: i64,
+    },
+    /// Replaces the [EmissionSchedule] of the [Rewarder], as [quarry_mine::set_emission_schedule].
+    EmissionSchedule {
+        /// New schedule, whose steps must all start after the change executes.
+        schedule: EmissionSchedule,
+    },
+    /// Adds a [RewardStream] to a [Quarry], as [quarry_mine::add_reward_stream].
+    RewardStream {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// Mint of the rewards token of the stream.
+        mint: Pubkey,
+        /// [TokenAccount] of `mint` owned by the [Rewarder] the stream is paid out of,
+        /// which must exist when the change executes.
+        vault: Pubkey,
+        /// Annual rewards rate of the stream.
+        annual_rewards_rate: u64,
+    },
+    /// Sets the annual rewards rate of a [RewardStream], as [quarry_mine::set_reward_stream_rate].
+    RewardStreamRate {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// Index of the [RewardStream] in the [Quarry]::reward_streams.
+        index: u8,
+        /// New annual rewards rate of the stream.
+        annual_rewards_rate: u64,
+    },
+    /// Sets when a [Quarry] starts distributing rewards, as [quarry_mine::set_start_ts].
+    StartTs {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// New start timestamp, which must be after the change executes.
+        start_ts: i64,
+        /// Whether [Miner]s may stake before `start_ts`.
+        allow_early_staking: bool,
+    },
+    /// Replaces the [LockupTier]s of a [Quarry], as [quarry_mine::set_lockup_tiers].
+    LockupTiers {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// New tiers; unused tiers are zeroed.
+        lockup_tiers: [LockupTier; 4],
+    },
+    /// Replaces the early unstake penalty schedule of a [Quarry],
+    /// as [quarry_mine::set_unstake_penalties].
+    UnstakePenalties {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// New penalty steps; unused steps are zeroed.
+        penalties: [UnstakePenalty; 4],
+        /// Where forfeited rewards go.
+        destination: PenaltyDestination,
+        /// Treasury receiving forfeited rewards, for [PenaltyDestination::Treasury].
+        treasury: Pubkey,
+    },
+    /// Sets the weights merkle root of a [Quarry], as [quarry_mine::set_weights_merkle_root].
+    WeightsMerkleRoot {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// New merkle root.
+        root: [u8; 32],
+    },
+    /// Sets weights in the [WeightTable] of a [Quarry], as [quarry_mine::set_nft_weights].
+    NftWeights {
+        /// [Quarry] updated.
+        quarry: Pubkey,
+        /// Weights to set; unused entries are zeroed.
+        weights: [NftWeight; 5],
+    },
+}
+
+/// A recipient of a share of the claim fees of a [Rewarder].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeRecipient {
+    /// Owner of the token account receiving fees.
+    pub recipient: Pubkey,
+    /// Share of the fees, in basis points.
+    pub bps: u64,
+}
+
+/// How a [Rewarder] pays out rewards.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum RewarderFunding {
+    /// Rewards are minted by the [quarry_mint_wrapper::MintWrapper], with [quarry_mine::claim_rewards].
+    #[default]
+    MintWrapper,
+    /// Rewards are transferred out of the [Rewarder]::rewards_vault,
+    /// with [quarry_mine::claim_rewards_from_vault].
+    Vault,
+}
+
+/// Scheduled changes of the annual rewards rate of a [Rewarder].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct EmissionSchedule {
+    /// Steps of the schedule, in order of `start_ts`.
+    /// Holds up to [emission::MAX_EMISSION_STEPS] steps; unused steps are zeroed.
+    pub steps: [EmissionStep; 16],
+}
+
+/// A step of an [EmissionSchedule].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct EmissionStep {
+    /// When the step starts.
+    pub start_ts: i64,
+    /// Annual rewards rate of the [Rewarder] from the start of the step.
+    pub annual_rate: u64,
+}
+
+/// How to build an [EmissionSchedule] in [quarry_mine::set_emission_schedule].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum EmissionPreset {
+    /// Explicit [EmissionStep]s.
+    Custom {
+        /// Steps of the schedule, in order of `start_ts`.
+        steps: Vec<EmissionStep>,
+    },
+    /// The rate decreases linearly from `initial_rate` to `final_rate`
+    /// over `num_steps` steps, one every `period` seconds.
+    LinearDecay {
+        /// Start of the first step.
+        start_ts: i64,
+        /// Seconds between steps.
+        period: i64,
+        /// Rate of the first step.
+        initial_rate: u64,
+        /// Rate of the last step.
+        final_rate: u64,
+        /// Number of steps.
+        num_steps: u8,
+    },
+    /// The rate halves every `period` seconds, starting at `initial_rate`.
+    Halving {
+        /// Start of the first step.
+        start_ts: i64,
+        /// Seconds between halvings.
+        period: i64,
+        /// Rate of the first step.
+        initial_rate: u64,
+        /// Number of steps.
+        num_steps: u8,
+    },
+}
+
+/// A pool which distributes tokens to its [Miner]s.
 #[account]
 #[derive(Copy, Default)]
 pub struct Quarry {
@@ -483,7 +1744,8 @@ pub struct Quarry {
     pub rewarder_key: Pubkey,
     /// Decimals on the token [Mint].
     pub token_mint_decimals: u8,
-    /// Candy Machine Update Authority
+    /// Candy Machine Update Authority.
+    /// Only enforced on staked NFTs for [NftGate::UpdateAuthority] quarries.
     pub nft_update_authority: Pubkey,
     /// Bump.
     pub bump: u8,
@@ -506,6 +1768,204 @@ pub struct Quarry {
     pub total_tokens_deposited: u64,
     /// Number of [Miner]s.
     pub num_miners: u64,
+
+    /// [WeightTable] assigning per-NFT staking weights.
+    /// If unset, every NFT is staked with a weight of [weight_table::DEFAULT_NFT_WEIGHT].
+    pub weight_table: Pubkey,
+    /// Merkle root of `(mint, weight)` pairs. If set, stakers must prove the weight of their NFT.
+    /// A [Quarry] may use either a [WeightTable] or a merkle root, but not both.
+    pub weights_merkle_root: [u8; 32],
+
+    /// Determines which NFTs may be staked into this [Quarry].
+    pub nft_gate: NftGate,
+    /// Key NFTs are gated by, depending on the [NftGate].
+    /// This is the collection mint for [NftGate::VerifiedCollection]
+    /// and the creator address for [NftGate::VerifiedCreator].
+    pub nft_gate_key: Pubkey,
+
+    /// Lockup periods stakers may choose from, boosting the weight of their NFTs.
+    /// Holds up to [lockup::MAX_LOCKUP_TIERS] tiers; unused tiers are zeroed.
+    pub lockup_tiers: [LockupTier; 4],
+
+    /// Penalties on the rewards of [Miner]s unstaking NFTs early.
+    /// Holds up to [penalty::MAX_UNSTAKE_PENALTIES] steps; unused steps are zeroed.
+    pub unstake_penalties: [UnstakePenalty; 4],
+    /// Where rewards forfeited by early unstakers go.
+    pub penalty_destination: PenaltyDestination,
+    /// Token account receiving forfeited rewards for [PenaltyDestination::Treasury].
+    pub penalty_treasury: Pubkey,
+    /// Forfeited rewards not yet minted to the [Quarry]::penalty_treasury.
+    pub forfeited_rewards: u64,
+
+    /// Kind of tokens staked into this [Quarry].
+    pub kind: QuarryKind,
+    /// Mint of the staked token of a [QuarryKind::Fungible] [Quarry].
+    pub token_mint_key: Pubkey,
+
+    /// Extra rewards paid out alongside the [Rewarder]'s.
+    /// Holds up to [stream::MAX_REWARD_STREAMS] streams; unused streams are zeroed.
+    pub reward_streams: [RewardStream; 3],
+
+    /// Timestamp when quarry rewards start
+    pub start_ts: i64,
+    /// If true, [Miner]s may stake before the [Quarry]::start_ts.
+    pub allow_early_staking: bool,
+}
+
+/// Extra rewards of a [Quarry], in a token other than the [Rewarder]::rewards_token_mint.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct RewardStream {
+    /// Mint of the rewards token.
+    pub mint: Pubkey,
+    /// [TokenAccount] owned by the [Rewarder] which rewards are paid out of.
+    pub vault: Pubkey,
+    /// Amount of rewards distributed to the [Quarry] per year.
+    pub annual_rewards_rate: u64,
+    /// Rewards per token stored in the stream.
+    pub rewards_per_token_stored: u128,
+}
+
+/// Kind of tokens staked into a [Quarry].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum QuarryKind {
+    /// NFTs admitted by the [NftGate], staked with [quarry_mine::stake_nft].
+    #[default]
+    Nft,
+    /// Any amount of [Quarry]::token_mint_key, staked with [quarry_mine::stake_tokens].
+    Fungible,
+}
+
+/// A step of the early unstake penalty schedule of a [Quarry].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct UnstakePenalty {
+    /// Unstaking an NFT less than this many seconds after staking it incurs the penalty.
+    pub duration: i64,
+    /// Share forfeited, in basis points, of the NFT's pro rata part of the [Miner]'s earned rewards.
+    pub penalty_bps: u64,
+}
+
+/// Where rewards forfeited by early unstakers go.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum PenaltyDestination {
+    /// Distributed to the [Miner]s remaining in the [Quarry].
+    #[default]
+    Stakers,
+    /// Minted to the [Quarry]::penalty_treasury.
+    Treasury,
+}
+
+/// A lockup period offered by a [Quarry].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct LockupTier {
+    /// Number of seconds the NFT is locked for.
+    pub duration: i64,
+    /// Multiplier applied to the weight of the NFT, in basis points.
+    /// 10,000 BPS leaves the weight unchanged.
+    pub multiplier_bps: u64,
+}
+
+/// Determines which NFTs may be staked into a [Quarry].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum NftGate {
+    /// The NFT's metadata update authority must be [Quarry]::nft_update_authority.
+    #[default]
+    UpdateAuthority,
+    /// The NFT must belong to the verified collection [Quarry]::nft_gate_key.
+    VerifiedCollection,
+    /// The NFT's creators must include [Quarry]::nft_gate_key, verified.
+    VerifiedCreator,
+}
+
+/// Assigns the weight each NFT mint is staked into a [Quarry] with.
+#[account]
+#[derive(Default, Debug)]
+pub struct WeightTable {
+    /// [Quarry] the weights apply to.
+    pub quarry: Pubkey,
+    /// Bump.
+    pub bump: u8,
+    /// Maximum number of entries the table can hold.
+    pub capacity: u32,
+    /// Weights of NFT mints, sorted by mint.
+    pub entries: Vec<NftWeight>,
+}
+
+/// Weight of a single NFT mint.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct NftWeight {
+    /// Mint of the NFT.
+    pub mint: Pubkey,
+    /// Amount added to [Miner]::balance when the NFT is staked.
+    pub weight: u64,
+}
+
+/// Proof of the weight of an NFT against [Quarry]::weights_merkle_root.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq)]
+pub struct WeightProof {
+    /// Weight of the NFT.
+    pub weight: u64,
+    /// Merkle proof of the `(mint, weight)` leaf.
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Arguments of an NFT staked by [quarry_mine::stake_nfts].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq)]
+pub struct BatchStakeArgs {
+    /// Bump of the NFT's metadata.
+    pub metadata_bump: u8,
+    /// Bump of the NFT's edition.
+    pub edition_bump: u8,
+    /// Bump of the [StakeReceipt] to create.
+    pub receipt_bump: u8,
+    /// Proof of the NFT's weight, if the [Quarry] has a weights merkle root.
+    pub weight_proof: Option<WeightProof>,
+    /// Index of the [LockupTier] to lock the NFT for, if any.
+    pub lockup_tier: Option<u8>,
+}
+
+/// Arguments of an NFT withdrawn by [quarry_mine::withdraw_nfts].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct BatchWithdrawArgs {
+    /// Bump of the NFT's metadata.
+    pub metadata_bump: u8,
+    /// Bump of the NFT's edition.
+    pub edition_bump: u8,
+}
+
+/// Records an NFT staked by a [Miner].
+///
+/// Created when the NFT is staked and closed when it is withdrawn, so the NFTs staked
+/// by a [Miner] or into a [Quarry] can be enumerated with `getProgramAccounts`.
+#[account]
+#[derive(Copy, Default, Debug)]
+pub struct StakeReceipt {
+    /// [Miner] which staked the NFT.
+    pub miner: Pubkey,
+    /// [Quarry] the NFT is staked into.
+    pub quarry: Pubkey,
+    /// Mint of the staked NFT.
+    pub mint: Pubkey,
+    /// Bump.
+    pub bump: u8,
+    /// Weight the NFT was staked with, including its lockup boost.
+    /// This is what gets subtracted from the [Miner]'s balance on withdrawal.
+    pub weight: u64,
+    /// When the NFT was staked.
+    pub stake_ts: i64,
+    /// When the NFT may be withdrawn.
+    pub unlock_ts: i64,
+    /// Where the NFT is held while staked.
+    pub custody: StakeCustody,
+}
+
+/// Where a staked NFT is held.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum StakeCustody {
+    /// Transferred to the NFT vault of the [Miner].
+    #[default]
+    Vault,
+    /// Left in the user's token account, frozen with the [Miner] as its delegate.
+    Frozen,
 }
 
 /// An account that has staked tokens into a [Quarry].
@@ -543,6 +2003,38 @@ pub struct Miner {
 
     /// Index of the [Miner].
     pub index: u64,
+
+    /// Checkpoints of the [Quarry]'s [RewardStream]s, by index.
+    pub reward_streams: [MinerRewardStream; 3],
+
+    /// If true, anyone may harvest the rewards of the [Miner] with [quarry_mine::harvest]
+    /// or [quarry_mine::harvest_from_vault].
+    pub allow_harvest: bool,
+
+    /// Pending authority which must accept the [Miner] with [quarry_mine::accept_miner_authority].
+    pub pending_authority: Pubkey,
+
+    /// Weight of the NFTs staked since [Miner]::penalty_start_ts, which may still be
+    /// unstaked with a penalty. Their share of the rewards is held back until then.
+    pub penalized_weight: u64,
+    /// When the first NFT of the [Miner]::penalized_weight was staked.
+    pub penalty_start_ts: i64,
+    /// When no NFT of the [Miner]::penalized_weight may be unstaked with a penalty anymore.
+    pub penalty_end_ts: i64,
+    /// Part of the [Miner]::rewards_earned held back for the [Miner]::penalized_weight.
+    pub penalized_rewards: u64,
+    /// Part of the rewards earned from each [RewardStream] held back for the
+    /// [Miner]::penalized_weight, by index.
+    pub penalized_stream_rewards: [u64; 3],
+}
+
+/// Rewards of a [Miner] from a [RewardStream].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct MinerRewardStream {
+    /// Checkpoint of the [RewardStream]'s rewards paid per staked token.
+    pub rewards_per_token_paid: u128,
+    /// Amount of stream tokens the [Miner] may claim.
+    pub rewards_earned: u64,
 }
 
 /// --------------------------------
@@ -593,6 +2085,113 @@ pub struct NewRewarder<'info> {
     pub claim_fee_token_account: Account<'info, TokenAccount>,
 }
 
+/// Accounts for [quarry_mine::new_vault_rewarder].
+#[derive(Accounts)]
+#[instruction(bump: u8, vault_bump: u8)]
+pub struct NewVaultRewarder<'info> {
+    /// Base. Arbitrary key.
+    pub base: Signer<'info>,
+
+    /// [Rewarder] of mines.
+    #[account(
+        init,
+        seeds = [
+            b"Rewarder".as_ref(),
+            base.key().to_bytes().as_ref()
+        ],
+        bump = bump,
+        payer = payer
+    )]
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// Initial authority of the rewarder.
+    pub authority: UncheckedAccount<'info>,
+
+    /// Rewards token mint.
+    pub rewards_token_mint: Account<'info, Mint>,
+
+    /// [TokenAccount] rewards are paid out of.
+    #[account(
+        init,
+        seeds = [
+            b"RewardsVault".as_ref(),
+            rewarder.key().to_bytes().as_ref()
+        ],
+        bump = vault_bump,
+        payer = payer,
+        token::mint = rewards_token_mint,
+        token::authority = rewarder
+    )]
+    pub rewards_vault: Account<'info, TokenAccount>,
+
+    /// Token account in which the rewards token fees are collected.
+    pub claim_fee_token_account: Account<'info, TokenAccount>,
+
+    /// Payer of the [Rewarder] initialization.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+
+    /// Rent sysvar.
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for [quarry_mine::fund_rewards_vault].
+#[derive(Accounts)]
+pub struct FundRewardsVault<'info> {
+    /// [Rewarder] to fund.
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// [Rewarder]::rewards_vault.
+    #[account(mut)]
+    pub rewards_vault: Account<'info, TokenAccount>,
+
+    /// Funder of the vault.
+    pub funder: Signer<'info>,
+
+    /// [TokenAccount] the funds come from.
+    #[account(mut)]
+    pub funder_token_account: Account<'info, TokenAccount>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for [quarry_mine::set_claim_fee].
+#[derive(Accounts)]
+pub struct SetClaimFee<'info> {
+    /// The [Config]::fee_setter.
+    pub fee_setter: Signer<'info>,
+
+    /// Protocol [Config].
+    #[account(seeds = [b"Config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// [Rewarder] updated.
+    #[account(mut)]
+    pub rewarder: Account<'info, Rewarder>,
+}
+
+/// Accounts for [quarry_mine::set_fee_split].
+#[derive(Accounts)]
+pub struct SetFeeSplit<'info> {
+    /// The [Config]::fee_setter.
+    pub fee_setter: Signer<'info>,
+
+    /// Protocol [Config].
+    #[account(seeds = [b"Config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
+    /// [Rewarder] updated.
+    #[account(mut)]
+    pub rewarder: Account<'info, Rewarder>,
+}
+
 /// Accounts for [quarry_mine::set_pause_authority].
 #[derive(Accounts)]
 pub struct SetPauseAuthority<'info> {
@@ -625,6 +2224,25 @@ pub struct AcceptAuthority<'info> {
     pub rewarder: Account<'info, Rewarder>,
 }
 
+/// Accounts for [quarry_mine::close_rewarder].
+#[derive(Accounts)]
+pub struct CloseRewarder<'info> {
+    /// Authority of the rewarder, receiving its rent.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// [Rewarder] closed.
+    #[account(mut, close = authority)]
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// [Rewarder]::claim_fee_token_account, closed.
+    #[account(mut)]
+    pub claim_fee_token_account: Account<'info, TokenAccount>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+}
+
 /// Read-only [Rewarder] that requires the authority to be a signer.
 #[derive(Accounts)]
 pub struct ReadOnlyRewarderWithAuthority<'info> {
@@ -642,6 +2260,80 @@ pub struct SetAnnualRewards<'info> {
     pub auth: MutableRewarderWithAuthority<'info>,
 }
 
+/// Accounts for [quarry_mine::set_emission_schedule].
+#[derive(Accounts)]
+pub struct SetEmissionSchedule<'info> {
+    /// [Rewarder],
+    pub auth: MutableRewarderWithAuthority<'info>,
+}
+
+/// Accounts for [quarry_mine::set_timelock_delay].
+#[derive(Accounts)]
+pub struct SetTimelockDelay<'info> {
+    /// [Rewarder],
+    pub auth: MutableRewarderWithAuthority<'info>,
+}
+
+/// Accounts for [quarry_mine::propose_change].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct ProposeChange<'info> {
+    /// [Rewarder],
+    pub auth: MutableRewarderWithAuthority<'info>,
+
+    /// [QueuedChange] to be created.
+    #[account(
+        init,
+        seeds = [
+            b"QueuedChange".as_ref(),
+            auth.rewarder.key().to_bytes().as_ref(),
+            auth.rewarder.num_queued_changes.to_le_bytes().as_ref()
+        ],
+        bump = bump,
+        payer = payer,
+        space = QueuedChange::SPACE
+    )]
+    pub queued_change: Box<Account<'info, QueuedChange>>,
+
+    /// Payer of the [QueuedChange].
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for [quarry_mine::execute_change].
+#[derive(Accounts)]
+pub struct ExecuteChange<'info> {
+    /// [Rewarder] of the change.
+    #[account(mut)]
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// [QueuedChange] executed.
+    #[account(mut, close = payer)]
+    pub queued_change: Box<Account<'info, QueuedChange>>,
+
+    /// Payer of the [QueuedChange].
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+/// Accounts for [quarry_mine::cancel_change].
+#[derive(Accounts)]
+pub struct CancelChange<'info> {
+    /// [Rewarder],
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [QueuedChange] cancelled.
+    #[account(mut, close = payer)]
+    pub queued_change: Box<Account<'info, QueuedChange>>,
+
+    /// Payer of the [QueuedChange].
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
 /// Mutable [Rewarder] that requires the authority to be a signer.
 #[derive(Accounts)]
 pub struct MutableRewarderWithAuthority<'info> {
@@ -657,15 +2349,17 @@ pub struct MutableRewarderWithAuthority<'info> {
 
 /// Accounts for [quarry_mine::create_quarry].
 #[derive(Accounts)]
-#[instruction(bump: u8)]
+#[instruction(bump: u8, nft_gate: NftGate, nft_gate_key: Pubkey)]
 pub struct CreateQuarry<'info> {
-    /// [Quarry].
+    /// [Quarry]. Gated [Quarry]s are also addressed by their `nft_gate_key`;
+    /// see [NftGate::quarry_seed].
     #[account(
         init,
         seeds = [
             b"Quarry".as_ref(),
             auth.rewarder.key().to_bytes().as_ref(),
-            nft_update_authority.key().to_bytes().as_ref()
+            nft_update_authority.key().to_bytes().as_ref(),
+            nft_gate.quarry_seed(&nft_gate_key)
         ],
         bump = bump,
         payer = payer
@@ -689,6 +2383,37 @@ pub struct CreateQuarry<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for [quarry_mine::create_fungible_quarry].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateFungibleQuarry<'info> {
+    /// [Quarry].
+    #[account(
+        init,
+        seeds = [
+            b"Quarry".as_ref(),
+            auth.rewarder.key().to_bytes().as_ref(),
+            token_mint.key().to_bytes().as_ref()
+        ],
+        bump = bump,
+        payer = payer
+    )]
+    pub quarry: Account<'info, Quarry>,
+
+    /// [Rewarder] authority.
+    pub auth: MutableRewarderWithAuthority<'info>,
+
+    /// Mint of the token to stake.
+    pub token_mint: Account<'info, Mint>,
+
+    /// Payer of [Quarry] creation.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
 /// Accounts for [quarry_mine::set_famine].
 #[derive(Accounts)]
 pub struct SetFamine<'info> {
@@ -700,29 +2425,245 @@ pub struct SetFamine<'info> {
     pub quarry: Account<'info, Quarry>,
 }
 
-/// Accounts for [quarry_mine::set_rewards_share].
+/// Accounts for [quarry_mine::close_quarry].
 #[derive(Accounts)]
-pub struct SetRewardsShare<'info> {
-    /// [Rewarder] of the [Quarry].
-    pub auth: MutableRewarderWithAuthority<'info>,
+pub struct CloseQuarry<'info> {
+    /// Authority of the rewarder, receiving the rent of the [Quarry].
+    #[account(mut)]
+    pub authority: Signer<'info>,
 
-    /// [Quarry] updated.
+    /// [Rewarder] of the [Quarry].
     #[account(mut)]
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// [Quarry] closed.
+    #[account(mut, close = authority)]
     pub quarry: Account<'info, Quarry>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
 }
 
-/// Accounts for [quarry_mine::update_quarry_rewards].
+/// Accounts for [quarry_mine::add_reward_stream].
 #[derive(Accounts)]
-pub struct UpdateQuarryRewards<'info> {
-    /// [Quarry].
+#[instruction(vault_bump: u8)]
+pub struct AddRewardStream<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
     #[account(mut)]
     pub quarry: Account<'info, Quarry>,
 
-    /// [Rewarder].
-    pub rewarder: Account<'info, Rewarder>,
-}
+    /// Mint of the rewards token of the stream.
+    pub mint: Account<'info, Mint>,
 
-/* Miner contexts */
+    /// [TokenAccount] the stream is paid out of.
+    #[account(
+        init,
+        seeds = [
+            b"RewardStreamVault".as_ref(),
+            quarry.key().to_bytes().as_ref(),
+            mint.key().to_bytes().as_ref()
+        ],
+        bump = vault_bump,
+        payer = payer,
+        token::mint = mint,
+        token::authority = auth.rewarder
+    )]
+    pub vault: Account<'info, TokenAccount>,
+
+    /// Payer of the vault initialization.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+
+    /// Rent sysvar.
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for [quarry_mine::set_reward_stream_rate].
+#[derive(Accounts)]
+pub struct SetRewardStreamRate<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::set_start_ts].
+#[derive(Accounts)]
+pub struct SetStartTs<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::set_rewards_share].
+#[derive(Accounts)]
+pub struct SetRewardsShare<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: MutableRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::update_quarry_rewards].
+#[derive(Accounts)]
+pub struct UpdateQuarryRewards<'info> {
+    /// [Quarry].
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// [Rewarder].
+    pub rewarder: Account<'info, Rewarder>,
+}
+
+/// Accounts for [quarry_mine::create_weight_table].
+#[derive(Accounts)]
+#[instruction(bump: u8, capacity: u32)]
+pub struct CreateWeightTable<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] to create a [WeightTable] for.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// [WeightTable] to be created.
+    #[account(
+        init,
+        seeds = [
+            b"WeightTable".as_ref(),
+            quarry.key().to_bytes().as_ref()
+        ],
+        bump = bump,
+        payer = payer,
+        space = WeightTable::space(capacity)
+    )]
+    pub weight_table: Account<'info, WeightTable>,
+
+    /// Payer of [WeightTable] creation.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for [quarry_mine::set_nft_weights].
+#[derive(Accounts)]
+pub struct SetNftWeights<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] of the [WeightTable].
+    pub quarry: Account<'info, Quarry>,
+
+    /// [WeightTable] updated.
+    #[account(mut)]
+    pub weight_table: Account<'info, WeightTable>,
+}
+
+/// Accounts for [quarry_mine::set_weights_merkle_root].
+#[derive(Accounts)]
+pub struct SetWeightsMerkleRoot<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::set_lockup_tiers].
+#[derive(Accounts)]
+pub struct SetLockupTiers<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::set_unstake_penalties].
+#[derive(Accounts)]
+pub struct SetUnstakePenalties<'info> {
+    /// [Rewarder] of the [Quarry].
+    pub auth: ReadOnlyRewarderWithAuthority<'info>,
+
+    /// [Quarry] updated.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::claim_forfeited_rewards].
+#[derive(Accounts)]
+pub struct ClaimForfeitedRewards<'info> {
+    /// Mint wrapper.
+    #[account(mut)]
+    pub mint_wrapper: Box<Account<'info, quarry_mint_wrapper::MintWrapper>>,
+    /// Mint wrapper program.
+    pub mint_wrapper_program: Program<'info, quarry_mint_wrapper::program::QuarryMintWrapper>,
+    /// [quarry_mint_wrapper::Minter] information.
+    #[account(mut)]
+    pub minter: Box<Account<'info, quarry_mint_wrapper::Minter>>,
+
+    /// Mint of the rewards token.
+    #[account(mut)]
+    pub rewards_token_mint: Account<'info, Mint>,
+
+    /// [Quarry]::penalty_treasury.
+    #[account(mut)]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+
+    /// [Rewarder] of the [Quarry].
+    pub rewarder: Box<Account<'info, Rewarder>>,
+
+    /// [Quarry] to claim forfeited rewards of.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for [quarry_mine::claim_forfeited_rewards_from_vault].
+#[derive(Accounts)]
+pub struct ClaimForfeitedRewardsFromVault<'info> {
+    /// [Rewarder]::rewards_vault.
+    #[account(mut)]
+    pub rewards_vault: Box<Account<'info, TokenAccount>>,
+
+    /// [Quarry]::penalty_treasury.
+    #[account(mut)]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+
+    /// [Rewarder] of the [Quarry].
+    pub rewarder: Box<Account<'info, Rewarder>>,
+
+    /// [Quarry] to claim forfeited rewards of.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+}
+
+/* Miner contexts */
 
 /// Accounts for [quarry_mine::create_miner].
 #[derive(Accounts)]
@@ -765,6 +2706,97 @@ pub struct CreateMiner<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+/// Accounts for [quarry_mine::close_miner].
+#[derive(Accounts)]
+pub struct CloseMiner<'info> {
+    /// Miner authority, receiving the rent of the [Miner].
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// [Miner] closed.
+    #[account(mut, close = authority)]
+    pub miner: Account<'info, Miner>,
+
+    /// [Quarry] of the [Miner].
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+}
+
+/// Accounts for [quarry_mine::close_miner_vault].
+#[derive(Accounts)]
+pub struct CloseMinerVault<'info> {
+    /// Miner authority, receiving the rent of the vault.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// [Miner] owning the vault.
+    pub miner: Account<'info, Miner>,
+
+    /// Empty [TokenAccount] owned by the [Miner].
+    #[account(mut)]
+    pub miner_vault: Account<'info, TokenAccount>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for [quarry_mine::transfer_miner_authority].
+#[derive(Accounts)]
+pub struct TransferMinerAuthority<'info> {
+    /// Miner authority.
+    pub authority: Signer<'info>,
+
+    /// [Miner] updated.
+    #[account(mut)]
+    pub miner: Account<'info, Miner>,
+}
+
+/// Accounts for [quarry_mine::accept_miner_authority].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct AcceptMinerAuthority<'info> {
+    /// Pending authority of the [Miner].
+    pub new_authority: Signer<'info>,
+
+    /// [Miner] of the new authority.
+    #[account(
+        init,
+        seeds = [
+            b"Miner".as_ref(),
+            quarry.key().to_bytes().as_ref(),
+            new_authority.key().to_bytes().as_ref()
+        ],
+        bump = bump,
+        payer = payer
+    )]
+    pub new_miner: Account<'info, Miner>,
+
+    /// [Miner] being transferred. Its rent goes to the previous authority.
+    #[account(mut, close = previous_authority)]
+    pub miner: Account<'info, Miner>,
+
+    /// Previous authority of the [Miner].
+    #[account(mut)]
+    pub previous_authority: UncheckedAccount<'info>,
+
+    /// [Quarry] of the [Miner].
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// [Rewarder] of the [Quarry].
+    pub rewarder: Box<Account<'info, Rewarder>>,
+
+    /// Payer of the new [Miner] and [StakeReceipt]s.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+
+    /// Token program.
+    pub token_program: Program<'info, Token>,
+}
+
 /// Staking accounts
 ///
 /// This accounts struct is always used in the context of the user authority
@@ -789,25 +2821,286 @@ pub struct UserStake<'info> {
 
     /// NFT Vault of the miner.
     #[account(mut)]
-    pub miner_nft_vault: Box<Account<'info, TokenAccount>>,
+    pub miner_nft_vault: Box<Account<'info, TokenAccount>>,
+
+    /// NFT Metadata
+    pub token_metadata: Account<'info, Metadata>,
+
+    /// NFT Master Edition or Edition.
+    pub token_edition: UncheckedAccount<'info>,
+
+    /// User's staked token account
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+
+    /// Rewarder
+    pub rewarder: Box<Account<'info, Rewarder>>,
+}
+
+/// Accounts for [quarry_mine::stake_nft].
+#[derive(Accounts)]
+#[instruction(amount: u8, metadata_bump: u8, edition_bump: u8, receipt_bump: u8)]
+pub struct StakeNft<'info> {
+    /// Staking accounts.
+    pub stake: UserStake<'info>,
+
+    /// [StakeReceipt] of the staked NFT.
+    #[account(
+        init,
+        seeds = [
+            b"StakeReceipt".as_ref(),
+            stake.miner.key().to_bytes().as_ref(),
+            stake.token_mint.key().to_bytes().as_ref()
+        ],
+        bump = receipt_bump,
+        payer = payer
+    )]
+    pub stake_receipt: Account<'info, StakeReceipt>,
+
+    /// Payer of [StakeReceipt] creation.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for [quarry_mine::withdraw_nft].
+#[derive(Accounts)]
+pub struct WithdrawNft<'info> {
+    /// Staking accounts.
+    pub stake: UserStake<'info>,
+
+    /// [StakeReceipt] of the withdrawn NFT.
+    #[account(mut, close = rent_receiver)]
+    pub stake_receipt: Account<'info, StakeReceipt>,
+
+    /// Receives the rent of the [StakeReceipt]. Must be the [Miner] authority.
+    #[account(mut)]
+    pub rent_receiver: UncheckedAccount<'info>,
+}
+
+/// Accounts for [quarry_mine::stake_tokens] and [quarry_mine::withdraw_tokens].
+///
+/// Validation should be extremely conservative.
+#[derive(Accounts, Clone)]
+pub struct UserStakeTokens<'info> {
+    /// Miner authority (i.e. the user).
+    pub authority: Signer<'info>,
+
+    /// Miner.
+    #[account(mut)]
+    pub miner: Account<'info, Miner>,
+
+    /// Quarry to stake into.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// Vault of the miner holding the staked tokens.
+    #[account(mut)]
+    pub miner_vault: Account<'info, TokenAccount>,
+
+    /// User's token account
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+
+    /// Rewarder
+    pub rewarder: Box<Account<'info, Rewarder>>,
+}
+
+/// Non-custodial staking accounts.
+///
+/// The NFT stays in the user's token account, which is frozen with the [Miner] as its delegate.
+/// Validation should be extremely conservative.
+#[derive(Accounts, Clone)]
+pub struct UserStakeFrozen<'info> {
+    /// Miner authority (i.e. the user).
+    pub authority: Signer<'info>,
+
+    /// Miner.
+    #[account(mut)]
+    pub miner: Account<'info, Miner>,
+
+    /// Quarry to claim from.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// NFT Mint that needs to be staked
+    pub token_mint: Box<Account<'info, Mint>>,
+
+    /// NFT Metadata
+    pub token_metadata: Account<'info, Metadata>,
+
+    /// NFT Master Edition or Edition. Freeze authority of the mint.
+    pub token_edition: UncheckedAccount<'info>,
+
+    /// User's token account holding the NFT, frozen while staked.
+    #[account(mut)]
+    pub token_account: Account<'info, TokenAccount>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+
+    /// Token metadata program, which freezes and thaws the NFT.
+    pub token_metadata_program: UncheckedAccount<'info>,
+
+    /// Rewarder
+    pub rewarder: Box<Account<'info, Rewarder>>,
+}
+
+/// Accounts for [quarry_mine::stake_nft_frozen].
+#[derive(Accounts)]
+#[instruction(metadata_bump: u8, edition_bump: u8, receipt_bump: u8)]
+pub struct StakeNftFrozen<'info> {
+    /// Staking accounts.
+    pub stake: UserStakeFrozen<'info>,
+
+    /// [StakeReceipt] of the staked NFT.
+    #[account(
+        init,
+        seeds = [
+            b"StakeReceipt".as_ref(),
+            stake.miner.key().to_bytes().as_ref(),
+            stake.token_mint.key().to_bytes().as_ref()
+        ],
+        bump = receipt_bump,
+        payer = payer
+    )]
+    pub stake_receipt: Account<'info, StakeReceipt>,
+
+    /// Payer of [StakeReceipt] creation.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for [quarry_mine::withdraw_nft_frozen].
+#[derive(Accounts)]
+pub struct WithdrawNftFrozen<'info> {
+    /// Staking accounts.
+    pub stake: UserStakeFrozen<'info>,
+
+    /// [StakeReceipt] of the withdrawn NFT.
+    #[account(mut, close = rent_receiver)]
+    pub stake_receipt: Account<'info, StakeReceipt>,
+
+    /// Receives the rent of the [StakeReceipt]. Must be the [Miner] authority.
+    #[account(mut)]
+    pub rent_receiver: UncheckedAccount<'info>,
+}
+
+/// Accounts for [quarry_mine::stake_nfts] and [quarry_mine::withdraw_nfts].
+///
+/// The accounts of each NFT are passed as remaining accounts; see [batch].
+#[derive(Accounts)]
+pub struct UserStakeBatch<'info> {
+    /// Miner authority (i.e. the user).
+    /// Pays for the [StakeReceipt]s and receives their rent back on withdrawal.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// Miner.
+    #[account(mut)]
+    pub miner: Account<'info, Miner>,
+
+    /// Quarry to stake into.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// Rewarder
+    pub rewarder: Box<Account<'info, Rewarder>>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+/// ClaimRewards accounts
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    /// Mint wrapper.
+    #[account(mut)]
+    pub mint_wrapper: Box<Account<'info, quarry_mint_wrapper::MintWrapper>>,
+    /// Mint wrapper program.
+    pub mint_wrapper_program: Program<'info, quarry_mint_wrapper::program::QuarryMintWrapper>,
+    /// [quarry_mint_wrapper::Minter] information.
+    #[account(mut)]
+    pub minter: Box<Account<'info, quarry_mint_wrapper::Minter>>,
+
+    /// Mint of the rewards token.
+    #[account(mut)]
+    pub rewards_token_mint: Account<'info, Mint>,
+
+    /// Account to claim rewards for.
+    #[account(mut)]
+    pub rewards_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Account to send claim fees to.
+    #[account(mut)]
+    pub claim_fee_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Claim accounts
+    pub stake: UserClaim<'info>,
+}
+
+/// Accounts for [quarry_mine::claim_rewards_from_vault].
+#[derive(Accounts)]
+pub struct ClaimRewardsFromVault<'info> {
+    /// [Rewarder]::rewards_vault.
+    #[account(mut)]
+    pub rewards_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Account to claim rewards for.
+    #[account(mut)]
+    pub rewards_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Account to send claim fees to.
+    #[account(mut)]
+    pub claim_fee_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Claim accounts
+    pub stake: UserClaim<'info>,
+}
+
+/// Accounts for [quarry_mine::set_allow_harvest].
+#[derive(Accounts)]
+pub struct SetAllowHarvest<'info> {
+    /// Miner authority.
+    pub authority: Signer<'info>,
+
+    /// [Miner] updated.
+    #[account(mut)]
+    pub miner: Account<'info, Miner>,
+}
 
-    /// NFT Metadata
-    pub token_metadata: Account<'info, Metadata>,
+/// Accounts for [quarry_mine::harvest].
+#[derive(Accounts)]
+pub struct Harvest<'info> {
+    /// Harvest accounts.
+    pub rewards: HarvestRewards<'info>,
 
-    /// User's staked token account
+    /// [Miner] to harvest.
     #[account(mut)]
-    pub token_account: Account<'info, TokenAccount>,
-
-    /// Token program
-    pub token_program: Program<'info, Token>,
+    pub miner: Account<'info, Miner>,
 
-    /// Rewarder
-    pub rewarder: Box<Account<'info, Rewarder>>,
+    /// Associated token account of the [Miner] authority, receiving the rewards.
+    #[account(mut)]
+    pub rewards_token_account: Account<'info, TokenAccount>,
 }
 
-/// ClaimRewards accounts
+/// Accounts for [quarry_mine::harvest_many], and shared by [quarry_mine::harvest].
 #[derive(Accounts)]
-pub struct ClaimRewards<'info> {
+pub struct HarvestRewards<'info> {
     /// Mint wrapper.
     #[account(mut)]
     pub mint_wrapper: Box<Account<'info, quarry_mint_wrapper::MintWrapper>>,
@@ -821,16 +3114,57 @@ pub struct ClaimRewards<'info> {
     #[account(mut)]
     pub rewards_token_mint: Account<'info, Mint>,
 
-    /// Account to claim rewards for.
+    /// Account to send claim fees to.
     #[account(mut)]
-    pub rewards_token_account: Box<Account<'info, TokenAccount>>,
+    pub claim_fee_token_account: Box<Account<'info, TokenAccount>>,
+
+    /// Quarry to harvest from.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// Rewarder
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for [quarry_mine::harvest_from_vault].
+#[derive(Accounts)]
+pub struct HarvestFromVault<'info> {
+    /// Harvest accounts.
+    pub rewards: HarvestRewardsFromVault<'info>,
+
+    /// [Miner] to harvest.
+    #[account(mut)]
+    pub miner: Account<'info, Miner>,
+
+    /// Associated token account of the [Miner] authority, receiving the rewards.
+    #[account(mut)]
+    pub rewards_token_account: Account<'info, TokenAccount>,
+}
+
+/// Accounts for [quarry_mine::harvest_many_from_vault], and shared by
+/// [quarry_mine::harvest_from_vault].
+#[derive(Accounts)]
+pub struct HarvestRewardsFromVault<'info> {
+    /// [Rewarder]::rewards_vault.
+    #[account(mut)]
+    pub rewards_vault: Box<Account<'info, TokenAccount>>,
 
     /// Account to send claim fees to.
     #[account(mut)]
     pub claim_fee_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// Claim accounts
-    pub stake: UserClaim<'info>,
+    /// Quarry to harvest from.
+    #[account(mut)]
+    pub quarry: Account<'info, Quarry>,
+
+    /// Rewarder
+    pub rewarder: Account<'info, Rewarder>,
+
+    /// Token program
+    pub token_program: Program<'info, Token>,
 }
 
 /// Claim accounts
@@ -861,6 +3195,10 @@ pub struct UserClaim<'info> {
 /// Accounts for [quarry_mine::extract_fees].
 #[derive(Accounts)]
 pub struct ExtractFees<'info> {
+    /// Protocol [Config].
+    #[account(seeds = [b"Config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+
     /// Rewarder to extract fees from.
     pub rewarder: Account<'info, Rewarder>,
 
@@ -868,7 +3206,7 @@ pub struct ExtractFees<'info> {
     #[account(mut)]
     pub claim_fee_token_account: Account<'info, TokenAccount>,
 
-    /// [TokenAccount] owned by the [addresses::FEE_TO].
+    /// [TokenAccount] owned by the [Config]::fee_to.
     /// Holds DAO claim fees.
     #[account(mut)]
     pub fee_to_token_account: Account<'info, TokenAccount>,
@@ -877,6 +3215,55 @@ pub struct ExtractFees<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+/// Accounts for [quarry_mine::init_config].
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitConfig<'info> {
+    /// The [addresses::FEE_SETTER].
+    pub admin: Signer<'info>,
+
+    /// Protocol [Config].
+    #[account(
+        init,
+        seeds = [b"Config".as_ref()],
+        bump = bump,
+        payer = payer
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Initial governance authority of the [Config].
+    pub authority: UncheckedAccount<'info>,
+
+    /// Payer of the [Config] initialization.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for [quarry_mine::set_fee_addresses] and [quarry_mine::transfer_config_authority].
+#[derive(Accounts)]
+pub struct MutableConfigWithAuthority<'info> {
+    /// [Config]::authority.
+    pub authority: Signer<'info>,
+
+    /// Protocol [Config].
+    #[account(mut, seeds = [b"Config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+/// Accounts for [quarry_mine::accept_config_authority].
+#[derive(Accounts)]
+pub struct AcceptConfigAuthority<'info> {
+    /// [Config]::pending_authority.
+    pub authority: Signer<'info>,
+
+    /// Protocol [Config].
+    #[account(mut, seeds = [b"Config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
 /// Accounts for [quarry_mine::pause] and [quarry_mine::unpause].
 #[derive(Accounts)]
 pub struct MutableRewarderWithPauseAuthority<'info> {
@@ -902,6 +3289,122 @@ pub struct NewRewarderEvent {
     pub timestamp: i64,
 }
 
+/// Emitted when the rewards vault of a [Rewarder] is topped up.
+#[event]
+pub struct RewardsVaultFundEvent {
+    /// [Rewarder] funded.
+    #[index]
+    pub rewarder: Pubkey,
+    /// Funder of the vault.
+    pub funder: Pubkey,
+    /// Amount of tokens deposited.
+    pub amount: u64,
+    /// Balance of the vault after the deposit.
+    pub vault_balance: u64,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when a [RewardStream] is added or its rate changes.
+#[event]
+pub struct RewardStreamUpdateEvent {
+    /// [Quarry] of the stream.
+    #[index]
+    pub quarry: Pubkey,
+    /// Index of the stream.
+    pub index: u8,
+    /// Mint of the rewards token of the stream.
+    pub rewards_token: Pubkey,
+    /// New annual rewards rate of the stream.
+    pub annual_rewards_rate: u64,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when [RewardStream] rewards are claimed.
+#[event]
+pub struct RewardStreamClaimEvent {
+    /// Authority claiming.
+    #[index]
+    pub authority: Pubkey,
+    /// [Quarry] of the stream.
+    #[index]
+    pub quarry: Pubkey,
+    /// Token received as rewards.
+    pub rewards_token: Pubkey,
+    /// Amount of rewards token received.
+    pub amount: u64,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when a [Miner] is transferred to a new authority.
+#[event]
+pub struct MinerAuthorityTransferEvent {
+    /// [Quarry] of the [Miner].
+    #[index]
+    pub quarry: Pubkey,
+    /// Previous authority.
+    pub previous_authority: Pubkey,
+    /// New authority.
+    pub authority: Pubkey,
+    /// New [Miner].
+    pub miner: Pubkey,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when the fee addresses of the [Config] change.
+#[event]
+pub struct ConfigUpdateEvent {
+    /// Account authorized to take fees.
+    pub fee_to: Pubkey,
+    /// Account authorized to set fees.
+    pub fee_setter: Pubkey,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when the [FeeRecipient]s of a [Rewarder] change.
+#[event]
+pub struct FeeSplitUpdateEvent {
+    /// [Rewarder] updated.
+    #[index]
+    pub rewarder: Pubkey,
+    /// New recipients.
+    pub recipients: Vec<FeeRecipient>,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when claim fees are extracted.
+#[event]
+pub struct FeesExtractEvent {
+    /// [Rewarder] the fees were extracted from.
+    #[index]
+    pub rewarder: Pubkey,
+    /// Total amount of fees extracted.
+    pub amount: u64,
+    /// Amount of fees sent to the [Config]::fee_to.
+    pub fee_to_amount: u64,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Emitted when the claim fee of a [Rewarder] changes.
+#[event]
+pub struct ClaimFeeUpdateEvent {
+    /// [Rewarder] updated.
+    #[index]
+    pub rewarder: Pubkey,
+    /// Previous claim fee, in milliBPS.
+    pub previous_claim_fee_millibps: u64,
+    /// New claim fee, in milliBPS.
+    pub max_claim_fee_millibps: u64,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
 /// Emitted when reward tokens are claimed.
 #[event]
 pub struct ClaimEvent {
@@ -931,7 +3434,11 @@ pub struct StakeEvent {
     #[index]
     pub token: Pubkey,
     /// Amount staked.
-    pub amount: u8,
+    pub amount: u64,
+    /// Weight added to the [Miner]'s balance.
+    pub weight: u64,
+    /// When the NFT may be withdrawn.
+    pub unlock_ts: i64,
     /// When the event took place.
     pub timestamp: i64,
 }
@@ -946,7 +3453,9 @@ pub struct WithdrawEvent {
     #[index]
     pub token: Pubkey,
     /// Amount withdrawn.
-    pub amount: u8,
+    pub amount: u64,
+    /// Weight removed from the [Miner]'s balance.
+    pub weight: u64,
     /// When the event took place.
     pub timestamp: i64,
 }
@@ -962,6 +3471,73 @@ pub struct RewarderAnnualRewardsUpdateEvent {
     pub timestamp: i64,
 }
 
+/// Triggered when the [EmissionSchedule] of a [Rewarder] is updated.
+#[event]
+pub struct EmissionScheduleUpdateEvent {
+    /// New steps of the schedule.
+    pub steps: Vec<EmissionStep>,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when the timelock delay of a [Rewarder] is updated.
+#[event]
+pub struct TimelockDelayUpdateEvent {
+    /// The [Rewarder].
+    #[index]
+    pub rewarder: Pubkey,
+    /// Previous delay.
+    pub previous_delay: i64,
+    /// New delay.
+    pub delay: i64,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when a [QueuedChange] is proposed.
+#[event]
+pub struct ChangeProposeEvent {
+    /// [Rewarder] of the change.
+    #[index]
+    pub rewarder: Pubkey,
+    /// The [QueuedChange].
+    pub queued_change: Pubkey,
+    /// The change.
+    pub change: ParameterChange,
+    /// Earliest time the change may be executed.
+    pub eta: i64,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when a [QueuedChange] is executed.
+#[event]
+pub struct ChangeExecuteEvent {
+    /// [Rewarder] of the change.
+    #[index]
+    pub rewarder: Pubkey,
+    /// The [QueuedChange].
+    pub queued_change: Pubkey,
+    /// The change.
+    pub change: ParameterChange,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when a [QueuedChange] is cancelled.
+#[event]
+pub struct ChangeCancelEvent {
+    /// [Rewarder] of the change.
+    #[index]
+    pub rewarder: Pubkey,
+    /// The [QueuedChange].
+    pub queued_change: Pubkey,
+    /// The change.
+    pub change: ParameterChange,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
 /// Triggered when a new miner is created.
 #[event]
 pub struct MinerCreateEvent {
@@ -975,11 +3551,152 @@ pub struct MinerCreateEvent {
     pub miner: Pubkey,
 }
 
+/// Triggered when an NFT or tokens are withdrawn while the [Rewarder] is paused.
+#[event]
+pub struct EmergencyWithdrawEvent {
+    /// Authority withdrawing.
+    #[index]
+    pub authority: Pubkey,
+    /// Mint of the withdrawn NFT or tokens.
+    #[index]
+    pub token: Pubkey,
+    /// Weight removed from the [Miner].
+    pub weight: u64,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Triggered when a miner is closed.
+#[event]
+pub struct MinerCloseEvent {
+    /// Authority of the miner.
+    #[index]
+    pub authority: Pubkey,
+    /// Quarry the miner was closed on.
+    #[index]
+    pub quarry: Pubkey,
+    /// The [Miner].
+    pub miner: Pubkey,
+}
+
+/// Triggered when a quarry is closed.
+#[event]
+pub struct QuarryCloseEvent {
+    /// [Rewarder] of the quarry.
+    #[index]
+    pub rewarder: Pubkey,
+    /// The [Quarry].
+    pub quarry: Pubkey,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
+/// Triggered when a rewarder is closed.
+#[event]
+pub struct RewarderCloseEvent {
+    /// The [Rewarder].
+    #[index]
+    pub rewarder: Pubkey,
+    /// When the event occurred.
+    pub timestamp: i64,
+}
+
 /// Triggered when a new quarry is created.
 #[event]
 pub struct QuarryCreateEvent {
+    /// Kind of tokens staked into the [Quarry].
+    pub kind: QuarryKind,
+    /// Mint staked into a [QuarryKind::Fungible] [Quarry].
+    pub token_mint: Pubkey,
     /// NFT Update Authority of the [Quarry] token
     pub nft_update_authority: Pubkey,
+    /// How NFTs are gated.
+    pub nft_gate: NftGate,
+    /// Key NFTs are gated by.
+    pub nft_gate_key: Pubkey,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when the weights of a [WeightTable] are updated.
+#[event]
+pub struct NftWeightsUpdateEvent {
+    /// [Quarry] the weights apply to.
+    #[index]
+    pub quarry: Pubkey,
+    /// Number of entries in the table after the update.
+    pub num_entries: u32,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when the weights merkle root of a [Quarry] is updated.
+#[event]
+pub struct WeightsMerkleRootUpdateEvent {
+    /// [Quarry] the weights apply to.
+    #[index]
+    pub quarry: Pubkey,
+    /// New merkle root.
+    pub root: [u8; 32],
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when the lockup tiers of a [Quarry] are updated.
+#[event]
+pub struct LockupTiersUpdateEvent {
+    /// [Quarry] the lockup tiers apply to.
+    #[index]
+    pub quarry: Pubkey,
+    /// New lockup tiers.
+    pub lockup_tiers: Vec<LockupTier>,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Triggered when the early unstake penalties of a [Quarry] are updated.
+#[event]
+pub struct UnstakePenaltiesUpdateEvent {
+    /// [Quarry] the penalties apply to.
+    #[index]
+    pub quarry: Pubkey,
+    /// New penalty schedule.
+    pub penalties: Vec<UnstakePenalty>,
+    /// Where forfeited rewards go.
+    pub destination: PenaltyDestination,
+    /// Treasury receiving forfeited rewards.
+    pub treasury: Pubkey,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Emitted when a [Miner] forfeits rewards by unstaking early.
+#[event]
+pub struct RewardsForfeitedEvent {
+    /// Authority withdrawing.
+    #[index]
+    pub authority: Pubkey,
+    /// [Quarry] withdrawn from.
+    #[index]
+    pub quarry: Pubkey,
+    /// Amount of rewards forfeited.
+    pub amount: u64,
+    /// Where the forfeited rewards went.
+    pub destination: PenaltyDestination,
+    /// When the event took place.
+    pub timestamp: i64,
+}
+
+/// Emitted when forfeited rewards are minted to a [Quarry]'s treasury.
+#[event]
+pub struct ForfeitedRewardsClaimEvent {
+    /// [Quarry] the rewards were forfeited in.
+    #[index]
+    pub quarry: Pubkey,
+    /// Treasury receiving the rewards.
+    pub treasury: Pubkey,
+    /// Amount of rewards paid to the treasury.
+    pub amount: u64,
     /// When the event took place.
     pub timestamp: i64,
 }
@@ -1026,4 +3743,94 @@ pub enum ErrorCode {
     Paused,
     #[msg("Rewards earned exceeded quarry's upper bound.")]
     UpperboundExceeded,
+    #[msg("NFT weight must be greater than zero.")]
+    InvalidNftWeight,
+    #[msg("Weight table is full.")]
+    WeightTableFull,
+    #[msg("Quarry already uses a different source of NFT weights.")]
+    WeightSourceConflict,
+    #[msg("A weight proof is required to stake into this quarry.")]
+    WeightProofRequired,
+    #[msg("Invalid NFT weight proof.")]
+    InvalidWeightProof,
+    #[msg("NFT is not part of the verified collection of the quarry.")]
+    CollectionNotVerified,
+    #[msg("NFT does not list the creator of the quarry as verified.")]
+    CreatorNotVerified,
+    #[msg(
+        "Mint is not an NFT: it must have a supply of 1, 0 decimals and a frozen mint authority."
+    )]
+    InvalidNftMint,
+    #[msg("Invalid NFT edition account.")]
+    InvalidNftEdition,
+    #[msg("Remaining accounts do not match the NFTs of the batch.")]
+    InvalidBatchAccounts,
+    #[msg("Invalid lockup tier.")]
+    InvalidLockupTier,
+    #[msg("NFT is locked and cannot be withdrawn before its unlock time.")]
+    NftLocked,
+    #[msg("Invalid unstake penalty.")]
+    InvalidUnstakePenalty,
+    #[msg("NFT was staked with a different custody mode.")]
+    StakeCustodyMismatch,
+    #[msg("Quarry does not accept this kind of token.")]
+    QuarryKindMismatch,
+    #[msg("Invalid emission schedule.")]
+    InvalidEmissionSchedule,
+    #[msg("Quarry has not started yet.")]
+    QuarryNotStarted,
+    #[msg("Quarry start must be in the future.")]
+    InvalidStartTs,
+    #[msg("Rewarder is not funded this way.")]
+    InvalidRewarderFunding,
+    #[msg("Rewards vault is empty.")]
+    RewardsVaultEmpty,
+    #[msg("Quarry already has a reward stream of this token.")]
+    RewardStreamExists,
+    #[msg("Quarry has too many reward streams.")]
+    TooManyRewardStreams,
+    #[msg("Invalid reward stream.")]
+    InvalidRewardStream,
+    #[msg("Invalid reward stream accounts.")]
+    InvalidRewardStreamAccounts,
+    #[msg("Invalid fee split.")]
+    InvalidFeeSplit,
+    #[msg("Invalid fee split accounts.")]
+    InvalidFeeSplitAccounts,
+    #[msg("Miner does not allow harvesting.")]
+    HarvestNotAllowed,
+    #[msg("Invalid harvest accounts.")]
+    InvalidHarvestAccounts,
+    #[msg("Invalid miner transfer accounts.")]
+    InvalidMinerTransferAccounts,
+    #[msg("Miner still holds tokens or unclaimed rewards.")]
+    MinerNotEmpty,
+    #[msg("Token account is not empty.")]
+    VaultNotEmpty,
+    #[msg("Quarry rewards have not ended.")]
+    QuarryNotFinished,
+    #[msg("Quarry still has miners.")]
+    QuarryHasMiners,
+    #[msg("Rewarder still has quarries.")]
+    RewarderHasQuarries,
+    #[msg("Rewarder is not paused.")]
+    NotPaused,
+    #[msg("Rewarder parameters must be changed through the timelock.")]
+    TimelockRequired,
+    #[msg("Timelock delay may not decrease.")]
+    InvalidTimelockDelay,
+    #[msg("Change may not execute before the timelock delay.")]
+    InvalidTimelockEta,
+    #[msg("Timelock has not elapsed.")]
+    TimelockNotElapsed,
+    #[msg("Invalid queued change accounts.")]
+    InvalidQueuedChangeAccounts,
+    #[msg("Every quarry of the rewarder must be passed, sorted by key.")]
+    InvalidQuarryCheckpointAccounts,
+    #[msg("Gated quarries require an NFT gate key.")]
+    InvalidNftGateKey,
+    #[msg("Claim fees must be extracted first.")]
+    FeesNotExtracted,
+    #[msg("NFTs are staked and withdrawn one at a time.")]
+    InvalidNftAmount,
 }
@@ -0,0 +1,155 @@
+//! Lockup periods which boost the weight of staked NFTs.
+
+use anchor_lang::prelude::*;
+use num_traits::cast::ToPrimitive;
+use vipers::unwrap_int;
+
+use crate::{LockupTier, Quarry, StakeReceipt};
+
+/// Maximum number of [LockupTier]s a [Quarry] may offer.
+pub const MAX_LOCKUP_TIERS: usize = 4;
+
+/// Multiplier of an NFT staked without a lockup, in basis points.
+pub const BASE_MULTIPLIER_BPS: u64 = 10_000;
+
+impl Quarry {
+    /// Replaces the [LockupTier]s of the [Quarry].
+    ///
+    /// Changes only apply to future stakes: NFTs already staked keep the weight
+    /// and unlock time recorded in their [StakeReceipt].
+    pub fn set_lockup_tiers(&mut self, tiers: &[LockupTier]) -> ProgramResult {
+        require!(tiers.len() <= MAX_LOCKUP_TIERS, InvalidLockupTier);
+        for tier in tiers {
+            require!(tier.duration > 0, InvalidLockupTier);
+            require!(
+                tier.multiplier_bps >= BASE_MULTIPLIER_BPS,
+                InvalidLockupTier
+            );
+        }
+
+        let mut lockup_tiers = [LockupTier::default(); MAX_LOCKUP_TIERS];
+        lockup_tiers[..tiers.len()].copy_from_slice(tiers);
+        self.lockup_tiers = lockup_tiers;
+        Ok(())
+    }
+
+    /// Applies a lockup tier to the weight of an NFT staked at `current_ts`.
+    ///
+    /// Returns the boosted weight and the time the NFT unlocks.
+    /// Staking without a lockup leaves the weight unchanged and unlocks immediately.
+    pub fn apply_lockup(
+        &self,
+        lockup_tier: Option<u8>,
+        weight: u64,
+        current_ts: i64,
+    ) -> Result<(u64, i64), ProgramError> {
+        let tier = match lockup_tier {
+            None => return Ok((weight, current_ts)),
+            Some(index) => self
+                .lockup_tiers
+                .get(index as usize)
+                .filter(|tier| tier.multiplier_bps != 0)
+                .ok_or(crate::ErrorCode::InvalidLockupTier)?,
+        };
+
+        let boosted_weight = unwrap_int!((weight as u128)
+            .checked_mul(tier.multiplier_bps.into())
+            .and_then(|boosted| boosted.checked_div(BASE_MULTIPLIER_BPS.into()))
+            .and_then(|boosted| boosted.to_u64()));
+        let unlock_ts = unwrap_int!(current_ts.checked_add(tier.duration));
+        Ok((boosted_weight, unlock_ts))
+    }
+}
+
+impl StakeReceipt {
+    /// Ensures the NFT is no longer locked.
+    pub fn assert_unlocked(&self, current_ts: i64) -> ProgramResult {
+        require!(current_ts >= self.unlock_ts, NftLocked);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use vipers::program_err;
+
+    const SECONDS_PER_DAY: i64 = 86_400;
+
+    fn tier(days: i64, multiplier_bps: u64) -> LockupTier {
+        LockupTier {
+            duration: days * SECONDS_PER_DAY,
+            multiplier_bps,
+        }
+    }
+
+    #[test]
+    fn test_set_lockup_tiers() {
+        let mut quarry = Quarry::default();
+        let tiers = [tier(30, 12_500), tier(90, 15_000), tier(180, 20_000)];
+        quarry.set_lockup_tiers(&tiers).unwrap();
+        assert_eq!(quarry.lockup_tiers[..3], tiers);
+        assert_eq!(quarry.lockup_tiers[3], LockupTier::default());
+
+        // clearing tiers
+        quarry.set_lockup_tiers(&[]).unwrap();
+        assert_eq!(
+            quarry.lockup_tiers,
+            [LockupTier::default(); MAX_LOCKUP_TIERS]
+        );
+
+        assert_eq!(
+            quarry.set_lockup_tiers(&[tier(30, 12_500); MAX_LOCKUP_TIERS + 1]),
+            program_err!(InvalidLockupTier)
+        );
+        // multipliers may not reduce the weight
+        assert_eq!(
+            quarry.set_lockup_tiers(&[tier(30, 9_999)]),
+            program_err!(InvalidLockupTier)
+        );
+        assert_eq!(
+            quarry.set_lockup_tiers(&[tier(0, 12_500)]),
+            program_err!(InvalidLockupTier)
+        );
+    }
+
+    #[test]
+    fn test_apply_lockup() {
+        let mut quarry = Quarry::default();
+        quarry
+            .set_lockup_tiers(&[tier(30, 12_500), tier(180, 20_000)])
+            .unwrap();
+        let now = 1_000;
+
+        assert_eq!(quarry.apply_lockup(None, 3, now).unwrap(), (3, now));
+        assert_eq!(
+            quarry.apply_lockup(Some(0), 4, now).unwrap(),
+            (5, now + 30 * SECONDS_PER_DAY)
+        );
+        assert_eq!(
+            quarry.apply_lockup(Some(1), 3, now).unwrap(),
+            (6, now + 180 * SECONDS_PER_DAY)
+        );
+        // unconfigured and out of range tiers
+        assert_eq!(
+            quarry.apply_lockup(Some(2), 3, now),
+            program_err!(InvalidLockupTier)
+        );
+        assert_eq!(
+            quarry.apply_lockup(Some(u8::MAX), 3, now),
+            program_err!(InvalidLockupTier)
+        );
+    }
+
+    #[test]
+    fn test_assert_unlocked() {
+        let receipt = StakeReceipt {
+            unlock_ts: 100,
+            ..Default::default()
+        };
+        assert_eq!(receipt.assert_unlocked(99), program_err!(NftLocked));
+        receipt.assert_unlocked(100).unwrap();
+        receipt.assert_unlocked(101).unwrap();
+    }
+}
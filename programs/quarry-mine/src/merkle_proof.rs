@@ -0,0 +1,63 @@
+//! Verification of merkle proofs of NFT weights.
+//!
+//! Leaves are `keccak256(mint || weight_le)`. Each pair of nodes is hashed in sorted order,
+//! so proofs do not need to encode whether a sibling is on the left or the right.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+
+/// Computes the leaf of a `(mint, weight)` pair.
+pub fn weight_leaf(mint: &Pubkey, weight: u64) -> [u8; 32] {
+    keccak::hashv(&[&mint.to_bytes(), &weight.to_le_bytes()]).0
+}
+
+/// Returns true if `leaf` is included in the tree with the given `root`.
+pub fn verify(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed_hash = leaf;
+    for proof_element in proof.iter() {
+        computed_hash = if computed_hash <= *proof_element {
+            keccak::hashv(&[&computed_hash, proof_element]).0
+        } else {
+            keccak::hashv(&[proof_element, &computed_hash]).0
+        };
+    }
+    computed_hash == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        if a <= b {
+            keccak::hashv(&[&a, &b]).0
+        } else {
+            keccak::hashv(&[&b, &a]).0
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let mints: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = mints
+            .iter()
+            .enumerate()
+            .map(|(i, mint)| weight_leaf(mint, i as u64 + 1))
+            .collect();
+        let left = hash_pair(leaves[0], leaves[1]);
+        let right = hash_pair(leaves[2], leaves[3]);
+        let root = hash_pair(left, right);
+
+        assert!(verify(&[leaves[3], left], root, leaves[2]));
+        assert!(verify(&[leaves[0], right], root, leaves[1]));
+
+        // wrong weight
+        assert!(!verify(
+            &[leaves[3], left],
+            root,
+            weight_leaf(&mints[2], 100)
+        ));
+        // wrong sibling
+        assert!(!verify(&[leaves[0], left], root, leaves[2]));
+    }
+}
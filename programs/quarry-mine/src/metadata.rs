@@ -1,18 +1,37 @@
-use anchor_lang::solana_program::program_error::ProgramError;
-use anchor_lang::solana_program::pubkey::Pubkey;
-use anchor_lang::AnchorDeserialize;
+use anchor_lang::prelude::*;
 use std::io::Write;
 use std::ops::Deref;
 
 pub use metaplex_token_metadata::ID;
 
 #[derive(Clone)]
-pub struct Metadata(metaplex_token_metadata::state::Metadata);
+pub struct Metadata {
+    inner: metaplex_token_metadata::state::Metadata,
+    /// Collection of the NFT. Only present in the newer metadata layout.
+    pub collection: Option<Collection>,
+}
+
+/// Collection an NFT belongs to.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Collection {
+    /// Whether the collection authority has verified the NFT as part of the collection.
+    pub verified: bool,
+    /// Mint of the collection NFT.
+    pub key: Pubkey,
+}
 
 impl Metadata {
     pub const LEN: usize = metaplex_token_metadata::state::MAX_METADATA_LEN;
 }
 
+/// Parses the fields the newer metadata layout appends after `edition_nonce`.
+///
+/// Accounts using the older layout are zero-padded, which reads as no collection.
+fn deserialize_collection(buf: &mut &[u8]) -> Option<Collection> {
+    let _token_standard: Option<u8> = AnchorDeserialize::deserialize(buf).ok()?;
+    AnchorDeserialize::deserialize(buf).ok()?
+}
+
 impl anchor_lang::AccountDeserialize for Metadata {
     fn try_deserialize(buf: &mut &[u8]) -> Result<Self, ProgramError> {
         Metadata::try_deserialize_unchecked(buf)
@@ -20,7 +39,10 @@ impl anchor_lang::AccountDeserialize for Metadata {
 
     fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self, ProgramError> {
         match metaplex_token_metadata::state::Metadata::deserialize(buf) {
-            Ok(data) => Ok(Metadata { 0: data }),
+            Ok(inner) => Ok(Metadata {
+                inner,
+                collection: deserialize_collection(buf),
+            }),
             Err(_) => Err(ProgramError::InvalidAccountData),
         }
     }
@@ -43,6 +65,60 @@ impl Deref for Metadata {
     type Target = metaplex_token_metadata::state::Metadata;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountDeserialize;
+    use metaplex_token_metadata::state::{Data, Key};
+
+    fn metadata_bytes() -> Vec<u8> {
+        metaplex_token_metadata::state::Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            data: Data {
+                name: "Lotus #1".to_string(),
+                symbol: "LOTUS".to_string(),
+                uri: "https://example.com/1.json".to_string(),
+                seller_fee_basis_points: 500,
+                creators: None,
+            },
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: Some(255),
+        }
+        .try_to_vec()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_deserialize_old_layout() {
+        let mut data = metadata_bytes();
+        data.resize(Metadata::LEN, 0);
+
+        let metadata = Metadata::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(metadata.data.symbol, "LOTUS");
+        assert_eq!(metadata.collection, None);
+    }
+
+    #[test]
+    fn test_deserialize_collection() {
+        let collection = Collection {
+            verified: true,
+            key: Pubkey::new_unique(),
+        };
+        let mut data = metadata_bytes();
+        // token standard: NonFungible
+        data.extend(Some(0_u8).try_to_vec().unwrap());
+        data.extend(Some(collection).try_to_vec().unwrap());
+        data.resize(Metadata::LEN, 0);
+
+        let metadata = Metadata::try_deserialize(&mut data.as_slice()).unwrap();
+        assert_eq!(metadata.collection, Some(collection));
     }
 }
@@ -0,0 +1,380 @@
+//! Verification of staked NFTs.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::Key;
+use anchor_spl::token::Mint;
+use metaplex_token_metadata::state::Key as MetadataKey;
+use vipers::{assert_keys_eq, assert_owner, unwrap_or_err};
+
+use crate::metadata::{self, Metadata};
+use crate::{Miner, NftGate, Quarry};
+
+/// Verifies that a mint is an NFT.
+///
+/// The mint must have a supply of 1 and 0 decimals, and its mint authority must be
+/// frozen behind its Metaplex edition (or removed), so no more units can ever be minted.
+pub fn assert_nft_mint(
+    mint_key: &Pubkey,
+    mint: &Mint,
+    edition: &AccountInfo,
+    edition_bump: u8,
+) -> ProgramResult {
+    require!(mint.decimals == 0, InvalidNftMint);
+    require!(mint.supply == 1, InvalidNftMint);
+
+    let expected_edition = Pubkey::create_program_address(
+        &[
+            metaplex_token_metadata::state::PREFIX.as_bytes(),
+            &metadata::ID.to_bytes(),
+            &mint_key.to_bytes(),
+            metaplex_token_metadata::state::EDITION.as_bytes(),
+            &[edition_bump],
+        ],
+        &metadata::ID,
+    )?;
+    assert_keys_eq!(*edition.key, expected_edition, "nft edition");
+    assert_owner!(*edition, metadata::ID, "nft edition");
+
+    let edition_key = edition.try_borrow_data()?.first().copied();
+    require!(
+        edition_key == Some(MetadataKey::MasterEditionV1 as u8)
+            || edition_key == Some(MetadataKey::MasterEditionV2 as u8)
+            || edition_key == Some(MetadataKey::EditionV1 as u8),
+        InvalidNftEdition
+    );
+
+    if let COption::Some(mint_authority) = mint.mint_authority {
+        require!(mint_authority == *edition.key, InvalidNftMint);
+    }
+
+    Ok(())
+}
+
+impl NftGate {
+    /// Seed of the [Quarry] program address identifying the gate.
+    ///
+    /// Gated [Quarry]s are addressed by their `nft_gate_key`, so a [crate::Rewarder] may have a
+    /// [Quarry] per collection or creator even if their NFTs share an update authority.
+    /// [NftGate::UpdateAuthority] [Quarry]s keep the address of their update authority alone.
+    pub fn quarry_seed<'a>(&self, nft_gate_key: &'a Pubkey) -> &'a [u8] {
+        match self {
+            NftGate::UpdateAuthority => &[],
+            NftGate::VerifiedCollection | NftGate::VerifiedCreator => nft_gate_key.as_ref(),
+        }
+    }
+}
+
+impl Quarry {
+    /// Verifies that an NFT may be staked into this [Quarry] according to its [NftGate].
+    pub fn assert_nft_gate(&self, miner: &Miner, token_metadata: &Metadata) -> ProgramResult {
+        match self.nft_gate {
+            NftGate::UpdateAuthority => {
+                assert_keys_eq!(
+                    token_metadata.update_authority,
+                    miner.nft_update_authority,
+                    "nft update authority",
+                );
+
+                assert_keys_eq!(
+                    token_metadata.update_authority,
+                    self.nft_update_authority,
+                    "nft update authority"
+                );
+            }
+            NftGate::VerifiedCollection => {
+                let collection = unwrap_or_err!(token_metadata.collection, CollectionNotVerified);
+                require!(collection.verified, CollectionNotVerified);
+                assert_keys_eq!(collection.key, self.nft_gate_key, "nft collection");
+            }
+            NftGate::VerifiedCreator => {
+                let creators =
+                    unwrap_or_err!(token_metadata.data.creators.as_ref(), CreatorNotVerified);
+                require!(
+                    creators
+                        .iter()
+                        .any(|creator| creator.verified && creator.address == self.nft_gate_key),
+                    CreatorNotVerified
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::metadata::Collection;
+    use anchor_lang::AccountDeserialize;
+    use metaplex_token_metadata::state::{Creator, Data, MasterEditionV2};
+    use spl_token::solana_program::program_pack::Pack;
+    use vipers::{program_err, VipersError};
+
+    /// Mock accounts as they would be owned by the token metadata program.
+    struct MockNft {
+        mint_key: Pubkey,
+        edition_key: Pubkey,
+        edition_bump: u8,
+        edition_owner: Pubkey,
+        edition_data: Vec<u8>,
+        lamports: u64,
+    }
+
+    impl MockNft {
+        fn new() -> Self {
+            let mint_key = Pubkey::new_unique();
+            let (edition_key, edition_bump) = Pubkey::find_program_address(
+                &[
+                    b"metadata",
+                    &metadata::ID.to_bytes(),
+                    &mint_key.to_bytes(),
+                    b"edition",
+                ],
+                &metadata::ID,
+            );
+            let edition_data = MasterEditionV2 {
+                key: MetadataKey::MasterEditionV2,
+                supply: 0,
+                max_supply: Some(0),
+            }
+            .try_to_vec()
+            .unwrap();
+            Self {
+                mint_key,
+                edition_key,
+                edition_bump,
+                edition_owner: metadata::ID,
+                edition_data,
+                lamports: 0,
+            }
+        }
+
+        fn mint(&self, supply: u64, decimals: u8, mint_authority: COption<Pubkey>) -> Mint {
+            let mut data = vec![0; spl_token::state::Mint::LEN];
+            spl_token::state::Mint {
+                mint_authority,
+                supply,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::Some(self.edition_key),
+            }
+            .pack_into_slice(&mut data);
+            Mint::try_deserialize_unchecked(&mut data.as_slice()).unwrap()
+        }
+
+        fn nft_mint(&self) -> Mint {
+            self.mint(1, 0, COption::Some(self.edition_key))
+        }
+
+        fn verify(&mut self, mint: &Mint) -> ProgramResult {
+            let edition = AccountInfo::new(
+                &self.edition_key,
+                false,
+                false,
+                &mut self.lamports,
+                &mut self.edition_data,
+                &self.edition_owner,
+                false,
+                0,
+            );
+            assert_nft_mint(&self.mint_key, mint, &edition, self.edition_bump)
+        }
+    }
+
+    fn mock_metadata(
+        update_authority: Pubkey,
+        creators: Option<Vec<Creator>>,
+        collection: Option<Collection>,
+    ) -> Metadata {
+        let mut data = metaplex_token_metadata::state::Metadata {
+            key: MetadataKey::MetadataV1,
+            update_authority,
+            mint: Pubkey::new_unique(),
+            data: Data {
+                name: "Lotus #1".to_string(),
+                symbol: "LOTUS".to_string(),
+                uri: "https://example.com/1.json".to_string(),
+                seller_fee_basis_points: 500,
+                creators,
+            },
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: Some(255),
+        }
+        .try_to_vec()
+        .unwrap();
+        data.extend(Some(0_u8).try_to_vec().unwrap());
+        data.extend(collection.try_to_vec().unwrap());
+        data.resize(Metadata::LEN, 0);
+        Metadata::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_accepts_nft() {
+        let mut nft = MockNft::new();
+        nft.verify(&nft.nft_mint()).unwrap();
+        // mint authority removed entirely
+        nft.verify(&nft.mint(1, 0, COption::None)).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_fungible_mints() {
+        let mut nft = MockNft::new();
+        let edition = COption::Some(nft.edition_key);
+        assert_eq!(
+            nft.verify(&nft.mint(1_000, 0, edition)),
+            program_err!(InvalidNftMint)
+        );
+        assert_eq!(
+            nft.verify(&nft.mint(1, 6, edition)),
+            program_err!(InvalidNftMint)
+        );
+        // supply could still be increased by the user
+        assert_eq!(
+            nft.verify(&nft.mint(1, 0, COption::Some(Pubkey::new_unique()))),
+            program_err!(InvalidNftMint)
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_edition() {
+        let mut nft = MockNft::new();
+        let mint = nft.nft_mint();
+
+        // edition of a different mint
+        let mut other = MockNft::new();
+        other.mint_key = nft.mint_key;
+        assert!(other.verify(&mint).is_err());
+
+        // not owned by the metadata program
+        nft.edition_owner = Pubkey::new_unique();
+        assert_eq!(nft.verify(&mint), Err(VipersError::OwnerMismatch.into()));
+        nft.edition_owner = metadata::ID;
+
+        // metadata account in place of the edition
+        nft.edition_data[0] = MetadataKey::MetadataV1 as u8;
+        assert_eq!(nft.verify(&mint), program_err!(InvalidNftEdition));
+    }
+
+    #[test]
+    fn test_update_authority_gate() {
+        let update_authority = Pubkey::new_unique();
+        let quarry = Quarry {
+            nft_update_authority: update_authority,
+            ..Default::default()
+        };
+        let miner = Miner {
+            nft_update_authority: update_authority,
+            ..Default::default()
+        };
+
+        quarry
+            .assert_nft_gate(&miner, &mock_metadata(update_authority, None, None))
+            .unwrap();
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &mock_metadata(Pubkey::new_unique(), None, None)),
+            Err(VipersError::KeyMismatch.into())
+        );
+    }
+
+    #[test]
+    fn test_verified_collection_gate() {
+        let collection_mint = Pubkey::new_unique();
+        let quarry = Quarry {
+            nft_gate: NftGate::VerifiedCollection,
+            nft_gate_key: collection_mint,
+            ..Default::default()
+        };
+        let miner = Miner::default();
+        let collection = |verified, key| {
+            mock_metadata(
+                Pubkey::new_unique(),
+                None,
+                Some(Collection { verified, key }),
+            )
+        };
+
+        quarry
+            .assert_nft_gate(&miner, &collection(true, collection_mint))
+            .unwrap();
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &collection(false, collection_mint)),
+            program_err!(CollectionNotVerified)
+        );
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &collection(true, Pubkey::new_unique())),
+            Err(VipersError::KeyMismatch.into())
+        );
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &mock_metadata(Pubkey::new_unique(), None, None)),
+            program_err!(CollectionNotVerified)
+        );
+    }
+
+    #[test]
+    fn test_quarry_seed() {
+        let nft_gate_key = Pubkey::new_unique();
+        assert!(NftGate::UpdateAuthority
+            .quarry_seed(&nft_gate_key)
+            .is_empty());
+        assert_eq!(
+            NftGate::VerifiedCollection.quarry_seed(&nft_gate_key),
+            nft_gate_key.as_ref()
+        );
+        assert_eq!(
+            NftGate::VerifiedCreator.quarry_seed(&nft_gate_key),
+            nft_gate_key.as_ref()
+        );
+    }
+
+    #[test]
+    fn test_verified_creator_gate() {
+        let creator = Pubkey::new_unique();
+        let quarry = Quarry {
+            nft_gate: NftGate::VerifiedCreator,
+            nft_gate_key: creator,
+            ..Default::default()
+        };
+        let miner = Miner::default();
+        let creators = |verified, first| {
+            let gate_creator = Creator {
+                address: creator,
+                verified,
+                share: 100,
+            };
+            let other_creator = Creator {
+                address: Pubkey::new_unique(),
+                verified: true,
+                share: 0,
+            };
+            let creators = if first {
+                vec![gate_creator, other_creator]
+            } else {
+                vec![other_creator, gate_creator]
+            };
+            mock_metadata(Pubkey::new_unique(), Some(creators), None)
+        };
+
+        quarry
+            .assert_nft_gate(&miner, &creators(true, true))
+            .unwrap();
+        // e.g. Candy Machine mints, which list the machine as the first creator
+        quarry
+            .assert_nft_gate(&miner, &creators(true, false))
+            .unwrap();
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &creators(false, true)),
+            program_err!(CreatorNotVerified)
+        );
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &creators(false, false)),
+            program_err!(CreatorNotVerified)
+        );
+        assert_eq!(
+            quarry.assert_nft_gate(&miner, &mock_metadata(Pubkey::new_unique(), None, None)),
+            program_err!(CreatorNotVerified)
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! Calculates token distribution rates.
 
-use crate::{Miner, Quarry};
+use crate::{EmissionStep, Miner, Quarry};
 use anchor_lang::{
     prelude::{msg, ProgramError, ProgramResult},
     require,
@@ -19,6 +19,8 @@ pub const PRECISION_MULTIPLIER: u128 = u64::MAX as u128;
 /// Calculator for amount of tokens to pay out.
 #[derive(Debug)]
 pub struct Payroll {
+    /// Timestamp of when rewards should start.
+    pub start_ts: i64,
     /// Timestamp of when rewards should end.
     pub famine_ts: i64,
     /// Timestamp of the last update.
@@ -33,6 +35,9 @@ pub struct Payroll {
 
     /// Total number of tokens deposited into the [Quarry].
     pub total_tokens_deposited: u64,
+
+    /// Changes of the `annual_rewards_rate` after the last checkpoint, in order.
+    pub emission_steps: Vec<EmissionStep>,
 }
 
 impl From<Quarry> for Payroll {
@@ -45,6 +50,7 @@ impl From<Quarry> for Payroll {
             quarry.rewards_per_token_stored,
             quarry.total_tokens_deposited,
         )
+        .with_start_ts(quarry.start_ts)
     }
 }
 
@@ -58,11 +64,26 @@ impl Payroll {
         total_tokens_deposited: u64,
     ) -> Self {
         Self {
+            start_ts: 0,
             famine_ts,
             last_checkpoint_ts,
             annual_rewards_rate,
             rewards_per_token_stored,
             total_tokens_deposited,
+            emission_steps: vec![],
+        }
+    }
+
+    /// Accrues no rewards before `start_ts`.
+    pub fn with_start_ts(self, start_ts: i64) -> Self {
+        Self { start_ts, ..self }
+    }
+
+    /// Changes the `annual_rewards_rate` at each [EmissionStep] after the last checkpoint.
+    pub fn with_emission_steps(self, emission_steps: Vec<EmissionStep>) -> Self {
+        Self {
+            emission_steps,
+            ..self
         }
     }
 
@@ -72,11 +93,10 @@ impl Payroll {
         if self.total_tokens_deposited == 0 {
             Some(self.rewards_per_token_stored)
         } else {
-            let time_worked = self.compute_time_worked(current_ts)?;
+            let emissions = self.compute_emissions(current_ts)?;
 
-            let reward = U192::from(time_worked)
+            let reward = emissions
                 .checked_mul(PRECISION_MULTIPLIER.into())?
-                .checked_mul(self.annual_rewards_rate.into())?
                 .checked_div(SECONDS_PER_YEAR.into())?
                 .checked_div(self.total_tokens_deposited.into())?;
 
@@ -146,10 +166,8 @@ impl Payroll {
         current_ts: i64,
         rewards_per_token_paid: u128,
     ) -> Option<U192> {
-        let time_worked = self.compute_time_worked(current_ts)?;
-
-        let quarry_rewards_accrued = U192::from(time_worked)
-            .checked_mul(self.annual_rewards_rate.into())?
+        let quarry_rewards_accrued = self
+            .compute_emissions(current_ts)?
             .checked_div(SECONDS_PER_YEAR.into())?;
 
         let net_rewards_per_token = self
@@ -198,13 +216,31 @@ impl Payroll {
         cmp::min(current_ts, self.famine_ts)
     }
 
-    /// Calculates the amount of seconds the [Payroll] should have applied rewards for.
-    fn compute_time_worked(&self, current_ts: i64) -> Option<i64> {
-        Some(cmp::max(
-            0,
-            self.last_time_reward_applicable(current_ts)
-                .checked_sub(self.last_checkpoint_ts)?,
-        ))
+    /// Calculates the annual rewards rate integrated over each second the [Payroll]
+    /// should have applied rewards for, switching rates at each [EmissionStep].
+    fn compute_emissions(&self, current_ts: i64) -> Option<U192> {
+        let end_ts = self.last_time_reward_applicable(current_ts);
+
+        let mut emissions = U192::zero();
+        let mut rate = self.annual_rewards_rate;
+        let mut period_start_ts = cmp::max(self.last_checkpoint_ts, self.start_ts);
+        for step in &self.emission_steps {
+            if step.start_ts <= period_start_ts {
+                // the step took effect before rewards started
+                rate = step.annual_rate;
+                continue;
+            }
+            if step.start_ts >= end_ts {
+                break;
+            }
+            let period = step.start_ts.checked_sub(period_start_ts)?;
+            emissions = emissions.checked_add(U192::from(period).checked_mul(rate.into())?)?;
+            rate = step.annual_rate;
+            period_start_ts = step.start_ts;
+        }
+
+        let time_worked = cmp::max(0, end_ts.checked_sub(period_start_ts)?);
+        emissions.checked_add(U192::from(time_worked).checked_mul(rate.into())?)
     }
 }
 
@@ -376,6 +412,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rewards_earned_across_emission_steps() {
+        let total_tokens_deposited = 1_000;
+        let payroll = Payroll::new(
+            i64::MAX,
+            0,
+            SECONDS_PER_YEAR as u64,
+            0,
+            total_tokens_deposited,
+        )
+        .with_emission_steps(vec![
+            EmissionStep {
+                start_ts: 100,
+                annual_rate: 0,
+            },
+            EmissionStep {
+                start_ts: 300,
+                annual_rate: 2 * SECONDS_PER_YEAR as u64,
+            },
+        ]);
+
+        // 100s at the initial rate, 200s at zero, 100s at twice the initial rate
+        let rewards_earned = payroll
+            .calculate_rewards_earned(400, total_tokens_deposited, 0, 0)
+            .unwrap();
+        assert_eq!(rewards_earned, 299);
+
+        // steps after the end of the period are ignored
+        let rewards_earned = payroll
+            .calculate_rewards_earned(200, total_tokens_deposited, 0, 0)
+            .unwrap();
+        assert_eq!(rewards_earned, 99);
+    }
+
     proptest! {
         #[test]
         fn test_wpt_with_zero_annual_rewards_rate(
@@ -0,0 +1,836 @@
+//! Penalties on rewards for unstaking NFTs early.
+//!
+//! While a staked NFT may still be unstaked with a penalty, its share of the rewards of
+//! its [Miner], including those of [crate::RewardStream]s, is held back from claims.
+//! Unstaking it early forfeits its penalty on the rewards held back for it; once its
+//! penalty period ends, they may be claimed.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token;
+use num_traits::cast::ToPrimitive;
+use std::cmp;
+use vipers::unwrap_int;
+
+use crate::payroll::PRECISION_MULTIPLIER;
+use crate::stream::MAX_REWARD_STREAMS;
+use crate::{
+    ClaimForfeitedRewards, ClaimForfeitedRewardsFromVault, ForfeitedRewardsClaimEvent, Miner,
+    PenaltyDestination, Quarry, RewardsForfeitedEvent, UnstakePenalty,
+};
+
+/// Maximum number of [UnstakePenalty] steps a [Quarry] may have.
+pub const MAX_UNSTAKE_PENALTIES: usize = 4;
+
+/// Basis points of a penalty forfeiting all rewards.
+pub const MAX_PENALTY_BPS: u64 = 10_000;
+
+impl Quarry {
+    /// Replaces the early unstake penalty schedule of the [Quarry].
+    ///
+    /// Forfeited rewards go to `treasury` if `destination` is [PenaltyDestination::Treasury].
+    pub fn set_unstake_penalties(
+        &mut self,
+        penalties: &[UnstakePenalty],
+        destination: PenaltyDestination,
+        treasury: Pubkey,
+    ) -> ProgramResult {
+        require!(
+            penalties.len() <= MAX_UNSTAKE_PENALTIES,
+            InvalidUnstakePenalty
+        );
+        for penalty in penalties {
+            require!(penalty.duration > 0, InvalidUnstakePenalty);
+            require!(
+                penalty.penalty_bps > 0 && penalty.penalty_bps <= MAX_PENALTY_BPS,
+                InvalidUnstakePenalty
+            );
+        }
+        if destination == PenaltyDestination::Treasury {
+            require!(treasury != Pubkey::default(), InvalidUnstakePenalty);
+        }
+
+        let mut unstake_penalties = [UnstakePenalty::default(); MAX_UNSTAKE_PENALTIES];
+        unstake_penalties[..penalties.len()].copy_from_slice(penalties);
+        self.unstake_penalties = unstake_penalties;
+        self.penalty_destination = destination;
+        self.penalty_treasury = treasury;
+        Ok(())
+    }
+
+    /// Computes the penalty, in basis points, of unstaking an NFT staked at `stake_ts`.
+    ///
+    /// If several steps of the schedule apply, the largest penalty is used.
+    pub fn unstake_penalty_bps(&self, stake_ts: i64, current_ts: i64) -> u64 {
+        let staked_duration = current_ts.saturating_sub(stake_ts);
+        self.unstake_penalties
+            .iter()
+            .filter(|penalty| staked_duration < penalty.duration)
+            .map(|penalty| penalty.penalty_bps)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Longest time after staking an NFT during which unstaking it incurs a penalty.
+    pub fn max_unstake_penalty_duration(&self) -> i64 {
+        self.unstake_penalties
+            .iter()
+            .map(|penalty| penalty.duration)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Adds `weight` staked by the [Miner] at `current_ts` to its [Miner]::penalized_weight,
+    /// holding back its share of the rewards until it may be unstaked without a penalty.
+    ///
+    /// This must be called after the [Miner] has been checkpointed.
+    pub fn add_penalized_weight(
+        &self,
+        miner: &mut Miner,
+        weight: u64,
+        current_ts: i64,
+    ) -> ProgramResult {
+        let duration = self.max_unstake_penalty_duration();
+        if miner.penalized_weight == 0 {
+            if duration == 0 {
+                return Ok(());
+            }
+            miner.penalty_start_ts = current_ts;
+        }
+        // While any weight is penalized, every NFT staked since the start is penalized,
+        // so that withdrawals can tell from the stake time of an NFT.
+        miner.penalized_weight = unwrap_int!(miner.penalized_weight.checked_add(weight));
+        miner.penalty_end_ts = cmp::max(
+            miner.penalty_end_ts,
+            unwrap_int!(current_ts.checked_add(duration)),
+        );
+        Ok(())
+    }
+
+    /// Forfeits the penalties of withdrawn NFTs on the [Miner]'s earned rewards,
+    /// including those of the [Quarry]'s [crate::RewardStream]s.
+    ///
+    /// Each NFT forfeits its penalty on its pro rata share of the rewards: for NFTs of the
+    /// [Miner]::penalized_weight, of the rewards held back for it; for other NFTs, of the rest.
+    /// `staked_weight` is the balance of the [Miner] before the withdrawal.
+    ///
+    /// Forfeited [crate::RewardStream] rewards go to the remaining [Miner]s for
+    /// [PenaltyDestination::Stakers], and otherwise stay in the vault of the stream.
+    ///
+    /// This must be called after the [Miner] has been checkpointed and its
+    /// withdrawn weight removed from the [Quarry].
+    /// Returns the amount of rewards forfeited.
+    pub fn forfeit_rewards(
+        &mut self,
+        miner: &mut Miner,
+        withdrawn: &WithdrawnNfts,
+        staked_weight: u64,
+    ) -> Result<u64, ProgramError> {
+        let penalized_weight = miner.penalized_weight;
+        miner.penalized_weight =
+            unwrap_int!(penalized_weight.checked_sub(withdrawn.penalized_weight));
+
+        for (index, stream) in self.reward_streams.iter_mut().enumerate() {
+            if !stream.is_active() {
+                continue;
+            }
+            let miner_stream = &mut miner.reward_streams[index];
+            let penalized_rewards = &mut miner.penalized_stream_rewards[index];
+            let forfeited = withdrawn.forfeit(
+                &mut miner_stream.rewards_earned,
+                penalized_rewards,
+                penalized_weight,
+                staked_weight,
+            )?;
+            if forfeited > 0
+                && self.penalty_destination == PenaltyDestination::Stakers
+                && self.total_tokens_deposited > 0
+            {
+                let rewards_per_token = unwrap_int!((forfeited as u128)
+                    .checked_mul(PRECISION_MULTIPLIER)
+                    .and_then(|r| r.checked_div(self.total_tokens_deposited.into())));
+                stream.rewards_per_token_stored = unwrap_int!(stream
+                    .rewards_per_token_stored
+                    .checked_add(rewards_per_token));
+            }
+        }
+
+        let forfeited = withdrawn.forfeit(
+            &mut miner.rewards_earned,
+            &mut miner.penalized_rewards,
+            penalized_weight,
+            staked_weight,
+        )?;
+        if forfeited == 0 {
+            return Ok(0);
+        }
+
+        match self.penalty_destination {
+            PenaltyDestination::Stakers => {
+                // With nobody left to receive them, forfeited rewards are never minted.
+                if self.total_tokens_deposited > 0 {
+                    let rewards_per_token = unwrap_int!((forfeited as u128)
+                        .checked_mul(PRECISION_MULTIPLIER)
+                        .and_then(|r| r.checked_div(self.total_tokens_deposited.into())));
+                    self.rewards_per_token_stored =
+                        unwrap_int!(self.rewards_per_token_stored.checked_add(rewards_per_token));
+                }
+            }
+            PenaltyDestination::Treasury => {
+                self.forfeited_rewards = unwrap_int!(self.forfeited_rewards.checked_add(forfeited));
+            }
+        }
+
+        Ok(forfeited)
+    }
+}
+
+impl Miner {
+    /// Holds back the share of the rewards earned since the last checkpoint which was
+    /// earned by the [Miner]::penalized_weight, or releases all held back rewards once
+    /// the [Miner]::penalty_end_ts has passed.
+    ///
+    /// This must be called when the [Miner] is checkpointed, before its balance changes.
+    pub fn hold_back_penalized_rewards(
+        &mut self,
+        previous_rewards_earned: u64,
+        previous_stream_rewards_earned: &[u64],
+        current_ts: i64,
+    ) -> ProgramResult {
+        if self.penalized_weight == 0 {
+            return Ok(());
+        }
+        if current_ts >= self.penalty_end_ts {
+            self.penalized_weight = 0;
+            self.penalized_rewards = 0;
+            self.penalized_stream_rewards = [0; MAX_REWARD_STREAMS];
+            return Ok(());
+        }
+
+        let new_rewards = unwrap_int!(self.rewards_earned.checked_sub(previous_rewards_earned));
+        let penalized_share = self.penalized_share(new_rewards)?;
+        self.penalized_rewards = unwrap_int!(self.penalized_rewards.checked_add(penalized_share));
+        for (index, previous) in previous_stream_rewards_earned.iter().enumerate() {
+            let new_rewards = unwrap_int!(self.reward_streams[index]
+                .rewards_earned
+                .checked_sub(*previous));
+            let penalized_share = self.penalized_share(new_rewards)?;
+            self.penalized_stream_rewards[index] =
+                unwrap_int!(self.penalized_stream_rewards[index].checked_add(penalized_share));
+        }
+        Ok(())
+    }
+
+    /// Computes the share of `rewards` earned by the [Miner]::penalized_weight.
+    fn penalized_share(&self, rewards: u64) -> Result<u64, ProgramError> {
+        Ok(unwrap_int!((rewards as u128)
+            .checked_mul(self.penalized_weight.into())
+            .and_then(|r| r.checked_div(self.balance.into()))
+            .and_then(|r| r.to_u64())))
+    }
+
+    /// Returns true if an NFT staked at `stake_ts` counts towards the [Miner]::penalized_weight.
+    pub fn is_penalized(&self, stake_ts: i64) -> bool {
+        self.penalized_weight > 0 && stake_ts >= self.penalty_start_ts
+    }
+
+    /// Removes an NFT staked at `stake_ts` from the [Miner]::penalized_weight without
+    /// forfeiting anything, releasing its share of the held back rewards.
+    pub fn release_penalized_weight(&mut self, weight: u64, stake_ts: i64) -> ProgramResult {
+        if !self.is_penalized(stake_ts) {
+            return Ok(());
+        }
+        let withdrawn = WithdrawnNfts {
+            penalized_weight: weight,
+            ..Default::default()
+        };
+        let penalized_weight = self.penalized_weight;
+        let balance = self.balance;
+        self.penalized_weight = unwrap_int!(penalized_weight.checked_sub(weight));
+        withdrawn.forfeit(
+            &mut self.rewards_earned,
+            &mut self.penalized_rewards,
+            penalized_weight,
+            balance,
+        )?;
+        for (stream, penalized_rewards) in self
+            .reward_streams
+            .iter_mut()
+            .zip(self.penalized_stream_rewards.iter_mut())
+        {
+            withdrawn.forfeit(
+                &mut stream.rewards_earned,
+                penalized_rewards,
+                penalized_weight,
+                balance,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Rewards of the [Miner] which may be claimed now.
+    pub fn claimable_rewards(&self) -> Result<u64, ProgramError> {
+        Ok(unwrap_int!(self
+            .rewards_earned
+            .checked_sub(self.penalized_rewards)))
+    }
+
+    /// Rewards of the [Miner] from the [crate::RewardStream] at `index` which may be claimed now.
+    pub fn claimable_stream_rewards(&self, index: usize) -> Result<u64, ProgramError> {
+        Ok(unwrap_int!(self.reward_streams[index]
+            .rewards_earned
+            .checked_sub(self.penalized_stream_rewards[index])))
+    }
+}
+
+/// NFTs withdrawn together from a [Miner], with their penalties.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WithdrawnNfts {
+    /// Weight of the withdrawn NFTs counting towards the [Miner]::penalized_weight.
+    penalized_weight: u64,
+    /// Sum of the weight of each of those NFTs times its penalty.
+    penalized_weighted_penalty_bps: u128,
+    /// Weight of the other withdrawn NFTs.
+    other_weight: u64,
+    /// Sum of the weight of each of the other NFTs times its penalty.
+    other_weighted_penalty_bps: u128,
+}
+
+impl WithdrawnNfts {
+    /// Adds an NFT of `weight` staked at `stake_ts`, withdrawn from `miner` at `current_ts`.
+    ///
+    /// This must be called after the [Miner] has been checkpointed.
+    pub fn add(
+        &mut self,
+        quarry: &Quarry,
+        miner: &Miner,
+        weight: u64,
+        stake_ts: i64,
+        current_ts: i64,
+    ) -> ProgramResult {
+        let weighted_penalty_bps =
+            u128::from(weight) * u128::from(quarry.unstake_penalty_bps(stake_ts, current_ts));
+        if miner.is_penalized(stake_ts) {
+            self.penalized_weight = unwrap_int!(self.penalized_weight.checked_add(weight));
+            self.penalized_weighted_penalty_bps = unwrap_int!(self
+                .penalized_weighted_penalty_bps
+                .checked_add(weighted_penalty_bps));
+        } else {
+            self.other_weight = unwrap_int!(self.other_weight.checked_add(weight));
+            self.other_weighted_penalty_bps = unwrap_int!(self
+                .other_weighted_penalty_bps
+                .checked_add(weighted_penalty_bps));
+        }
+        Ok(())
+    }
+
+    /// Total weight of the withdrawn NFTs.
+    pub fn weight(&self) -> Result<u64, ProgramError> {
+        Ok(unwrap_int!(self
+            .penalized_weight
+            .checked_add(self.other_weight)))
+    }
+
+    /// Forfeits the penalties of the NFTs out of `rewards_earned`, of which `penalized_rewards`
+    /// were held back for `penalized_weight` out of `staked_weight`, and releases their share
+    /// of the `penalized_rewards`. Returns the amount forfeited.
+    fn forfeit(
+        &self,
+        rewards_earned: &mut u64,
+        penalized_rewards: &mut u64,
+        penalized_weight: u64,
+        staked_weight: u64,
+    ) -> Result<u64, ProgramError> {
+        let held_back = u128::from(*penalized_rewards);
+        let other_rewards = u128::from(unwrap_int!(rewards_earned.checked_sub(*penalized_rewards)));
+
+        let mut forfeited: u64 = 0;
+        if self.penalized_weight > 0 {
+            forfeited = unwrap_int!(held_back
+                .checked_mul(self.penalized_weighted_penalty_bps)
+                .and_then(|f| f.checked_div(MAX_PENALTY_BPS.into()))
+                .and_then(|f| f.checked_div(penalized_weight.into()))
+                .and_then(|f| f.to_u64()));
+            let released = unwrap_int!(held_back
+                .checked_mul(self.penalized_weight.into())
+                .and_then(|r| r.checked_div(penalized_weight.into()))
+                .and_then(|r| r.to_u64()));
+            *penalized_rewards = unwrap_int!(penalized_rewards.checked_sub(released));
+        }
+        if self.other_weight > 0 {
+            let other_weight = unwrap_int!(staked_weight.checked_sub(penalized_weight));
+            let other_forfeited = unwrap_int!(other_rewards
+                .checked_mul(self.other_weighted_penalty_bps)
+                .and_then(|f| f.checked_div(MAX_PENALTY_BPS.into()))
+                .and_then(|f| f.checked_div(other_weight.into()))
+                .and_then(|f| f.to_u64()));
+            forfeited = unwrap_int!(forfeited.checked_add(other_forfeited));
+        }
+        *rewards_earned = unwrap_int!(rewards_earned.checked_sub(forfeited));
+        Ok(forfeited)
+    }
+}
+
+/// Forfeits the rewards of a [Miner] which unstaked the `withdrawn` NFTs early from a [Quarry].
+pub fn apply_unstake_penalty(
+    quarry: &mut Account<Quarry>,
+    miner: &mut Miner,
+    authority: Pubkey,
+    withdrawn: &WithdrawnNfts,
+    current_ts: i64,
+) -> ProgramResult {
+    let staked_weight = unwrap_int!(miner.balance.checked_add(withdrawn.weight()?));
+    let forfeited = quarry.forfeit_rewards(miner, withdrawn, staked_weight)?;
+    if forfeited > 0 {
+        emit!(RewardsForfeitedEvent {
+            authority,
+            quarry: quarry.key(),
+            amount: forfeited,
+            destination: quarry.penalty_destination,
+            timestamp: current_ts,
+        });
+    }
+    Ok(())
+}
+
+impl<'info> ClaimForfeitedRewards<'info> {
+    /// Mints the forfeited rewards of the [Quarry] to its treasury.
+    pub fn claim_forfeited_rewards(&mut self) -> ProgramResult {
+        let amount = self.quarry.forfeited_rewards;
+        if amount == 0 {
+            // nothing forfeited -- skip all logic
+            return Ok(());
+        }
+        self.quarry.forfeited_rewards = 0;
+
+        let seeds = gen_rewarder_signer_seeds!(self.rewarder);
+        let signer_seeds = &[&seeds[..]];
+
+        quarry_mint_wrapper::cpi::perform_mint(
+            CpiContext::new_with_signer(
+                self.mint_wrapper_program.to_account_info(),
+                quarry_mint_wrapper::cpi::accounts::PerformMint {
+                    mint_wrapper: self.mint_wrapper.to_account_info(),
+                    minter_authority: self.rewarder.to_account_info(),
+                    token_mint: self.rewards_token_mint.to_account_info(),
+                    destination: self.treasury.to_account_info(),
+                    minter: self.minter.to_account_info(),
+                    token_program: self.token_program.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        emit!(ForfeitedRewardsClaimEvent {
+            quarry: self.quarry.key(),
+            treasury: self.treasury.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+impl<'info> ClaimForfeitedRewardsFromVault<'info> {
+    /// Transfers the forfeited rewards of the [Quarry] from the rewards vault to its treasury.
+    ///
+    /// If the vault holds less than the rewards forfeited, it is emptied and the
+    /// remainder stays on the [Quarry] until the vault is topped up.
+    pub fn claim_forfeited_rewards(&mut self) -> ProgramResult {
+        let forfeited_rewards = self.quarry.forfeited_rewards;
+        if forfeited_rewards == 0 {
+            // nothing forfeited -- skip all logic
+            return Ok(());
+        }
+        require!(self.rewards_vault.amount > 0, RewardsVaultEmpty);
+
+        let amount = cmp::min(forfeited_rewards, self.rewards_vault.amount);
+        self.quarry.forfeited_rewards = unwrap_int!(forfeited_rewards.checked_sub(amount));
+
+        let seeds = gen_rewarder_signer_seeds!(self.rewarder);
+        let signer_seeds = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.rewards_vault.to_account_info(),
+                    to: self.treasury.to_account_info(),
+                    authority: self.rewarder.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+
+        emit!(ForfeitedRewardsClaimEvent {
+            quarry: self.quarry.key(),
+            treasury: self.treasury.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::quarry::StakeAction;
+    use crate::Rewarder;
+    use vipers::program_err;
+
+    const SECONDS_PER_DAY: i64 = 86_400;
+    const SECONDS_PER_YEAR: u64 = 86_400 * 365;
+
+    fn penalty(days: i64, penalty_bps: u64) -> UnstakePenalty {
+        UnstakePenalty {
+            duration: days * SECONDS_PER_DAY,
+            penalty_bps,
+        }
+    }
+
+    /// NFTs of `weight` withdrawn by a [Miner] without held back rewards.
+    fn withdrawn(weight: u64, weighted_penalty_bps: u128) -> WithdrawnNfts {
+        WithdrawnNfts {
+            other_weight: weight,
+            other_weighted_penalty_bps: weighted_penalty_bps,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_set_unstake_penalties() {
+        let mut quarry = Quarry::default();
+        let treasury = Pubkey::new_unique();
+        quarry
+            .set_unstake_penalties(
+                &[penalty(7, 5_000), penalty(30, 2_000)],
+                PenaltyDestination::Treasury,
+                treasury,
+            )
+            .unwrap();
+        assert_eq!(quarry.penalty_treasury, treasury);
+        assert_eq!(quarry.unstake_penalties[2], UnstakePenalty::default());
+
+        assert_eq!(
+            quarry.set_unstake_penalties(
+                &[penalty(7, 10_001)],
+                PenaltyDestination::Stakers,
+                treasury
+            ),
+            program_err!(InvalidUnstakePenalty)
+        );
+        assert_eq!(
+            quarry.set_unstake_penalties(
+                &[penalty(0, 5_000)],
+                PenaltyDestination::Stakers,
+                treasury
+            ),
+            program_err!(InvalidUnstakePenalty)
+        );
+        assert_eq!(
+            quarry.set_unstake_penalties(
+                &[penalty(7, 5_000); MAX_UNSTAKE_PENALTIES + 1],
+                PenaltyDestination::Stakers,
+                treasury
+            ),
+            program_err!(InvalidUnstakePenalty)
+        );
+        assert_eq!(
+            quarry.set_unstake_penalties(&[], PenaltyDestination::Treasury, Pubkey::default()),
+            program_err!(InvalidUnstakePenalty)
+        );
+    }
+
+    #[test]
+    fn test_unstake_penalty_bps() {
+        let mut quarry = Quarry::default();
+        assert_eq!(quarry.unstake_penalty_bps(0, 0), 0);
+
+        quarry
+            .set_unstake_penalties(
+                &[penalty(30, 2_000), penalty(7, 5_000)],
+                PenaltyDestination::Stakers,
+                Pubkey::default(),
+            )
+            .unwrap();
+        let stake_ts = 1_000;
+        assert_eq!(quarry.unstake_penalty_bps(stake_ts, stake_ts), 5_000);
+        assert_eq!(
+            quarry.unstake_penalty_bps(stake_ts, stake_ts + 7 * SECONDS_PER_DAY),
+            2_000
+        );
+        assert_eq!(
+            quarry.unstake_penalty_bps(stake_ts, stake_ts + 30 * SECONDS_PER_DAY),
+            0
+        );
+    }
+
+    #[test]
+    fn test_forfeit_to_treasury() {
+        let mut quarry = Quarry {
+            penalty_destination: PenaltyDestination::Treasury,
+            ..Default::default()
+        };
+        let mut miner = Miner {
+            rewards_earned: 1_001,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            quarry
+                .forfeit_rewards(&mut miner, &withdrawn(1, 5_000), 1)
+                .unwrap(),
+            500
+        );
+        assert_eq!(miner.rewards_earned, 501);
+        assert_eq!(quarry.forfeited_rewards, 500);
+        assert_eq!(
+            quarry
+                .forfeit_rewards(&mut miner, &withdrawn(1, 0), 1)
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            quarry
+                .forfeit_rewards(&mut miner, &withdrawn(1, MAX_PENALTY_BPS.into()), 1)
+                .unwrap(),
+            501
+        );
+        assert_eq!(miner.rewards_earned, 0);
+        assert_eq!(quarry.forfeited_rewards, 1_001);
+    }
+
+    #[test]
+    fn test_forfeit_share_of_withdrawn_nfts() {
+        let mut quarry = Quarry::default();
+        quarry
+            .set_unstake_penalties(
+                &[penalty(7, 5_000)],
+                PenaltyDestination::Treasury,
+                Pubkey::new_unique(),
+            )
+            .unwrap();
+        let mut miner = Miner {
+            rewards_earned: 1_000,
+            ..Default::default()
+        };
+
+        // an NFT of weight 1 out of 4 forfeits half of its quarter of the rewards
+        let mut early = WithdrawnNfts::default();
+        early.add(&quarry, &miner, 1, 0, 0).unwrap();
+        assert_eq!(early, withdrawn(1, 5_000));
+        assert_eq!(quarry.forfeit_rewards(&mut miner, &early, 4).unwrap(), 125);
+
+        // withdrawing NFTs one by one forfeits as much as withdrawing them at once
+        let mut one_by_one = Miner {
+            rewards_earned: 1_000,
+            ..Default::default()
+        };
+        let mut at_once = one_by_one;
+        let mut late = WithdrawnNfts::default();
+        late.add(&quarry, &one_by_one, 1, 0, 7 * SECONDS_PER_DAY)
+            .unwrap();
+        assert_eq!(late, withdrawn(1, 0));
+        let forfeited = quarry.forfeit_rewards(&mut one_by_one, &early, 2).unwrap()
+            + quarry.forfeit_rewards(&mut one_by_one, &late, 1).unwrap();
+        let mut both = early;
+        both.add(&quarry, &at_once, 1, 0, 7 * SECONDS_PER_DAY)
+            .unwrap();
+        assert_eq!(
+            quarry.forfeit_rewards(&mut at_once, &both, 2).unwrap(),
+            forfeited
+        );
+        assert_eq!(one_by_one.rewards_earned, at_once.rewards_earned);
+    }
+
+    #[test]
+    fn test_forfeit_to_stakers() {
+        let rewarder = Rewarder::default();
+        let mut quarry = Quarry::default();
+        let mut leaver = Miner::default();
+        let mut stayer = Miner::default();
+
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, &mut leaver, 1)
+            .unwrap();
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, &mut stayer, 3)
+            .unwrap();
+
+        leaver.rewards_earned = 1_000_000;
+        quarry
+            .process_stake_action_internal(StakeAction::Withdraw, 0, &rewarder, &mut leaver, 1)
+            .unwrap();
+        assert_eq!(
+            quarry
+                .forfeit_rewards(&mut leaver, &withdrawn(1, 2_500), 1)
+                .unwrap(),
+            250_000
+        );
+        assert_eq!(leaver.rewards_earned, 750_000);
+        assert_eq!(quarry.forfeited_rewards, 0);
+
+        // the remaining staker receives everything forfeited, less rounding
+        quarry
+            .update_rewards_and_miner(&mut stayer, &rewarder, 0)
+            .unwrap();
+        assert!(stayer.rewards_earned <= 250_000);
+        assert!(stayer.rewards_earned >= 250_000 - 1);
+    }
+
+    #[test]
+    fn test_claim_rewards_of_nfts_past_penalty() {
+        let rewarder = Rewarder {
+            annual_rewards_rate: SECONDS_PER_YEAR,
+            total_rewards_shares: 1,
+            ..Default::default()
+        };
+        let mut quarry = Quarry {
+            famine_ts: i64::MAX,
+            rewards_share: 1,
+            ..Default::default()
+        };
+        quarry
+            .set_unstake_penalties(
+                &[penalty(7, 5_000)],
+                PenaltyDestination::Treasury,
+                Pubkey::new_unique(),
+            )
+            .unwrap();
+        let mut miner = Miner::default();
+
+        // stake A, then B once A is past its penalty period
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, &mut miner, 1)
+            .unwrap();
+        let b_stake_ts = 8 * SECONDS_PER_DAY;
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, b_stake_ts, &rewarder, &mut miner, 1)
+            .unwrap();
+        let rewards_of_a = miner.rewards_earned;
+        assert!(rewards_of_a > 0);
+        assert_eq!(miner.claimable_rewards().unwrap(), rewards_of_a);
+
+        // only B's half of the new rewards is held back
+        let current_ts = b_stake_ts + SECONDS_PER_DAY;
+        quarry
+            .update_rewards_and_miner(&mut miner, &rewarder, current_ts)
+            .unwrap();
+        let new_rewards = miner.rewards_earned - rewards_of_a;
+        assert_eq!(miner.penalized_rewards, new_rewards / 2);
+        assert_eq!(
+            miner.claimable_rewards().unwrap(),
+            rewards_of_a + new_rewards - new_rewards / 2
+        );
+
+        // unstaking B early forfeits half of its held back rewards
+        quarry
+            .process_stake_action_internal(
+                StakeAction::Withdraw,
+                current_ts,
+                &rewarder,
+                &mut miner,
+                1,
+            )
+            .unwrap();
+        let mut withdrawn = WithdrawnNfts::default();
+        withdrawn
+            .add(&quarry, &miner, 1, b_stake_ts, current_ts)
+            .unwrap();
+        assert_eq!(
+            quarry.forfeit_rewards(&mut miner, &withdrawn, 2).unwrap(),
+            new_rewards / 4
+        );
+        assert_eq!(miner.penalized_weight, 0);
+        assert_eq!(miner.penalized_rewards, 0);
+        assert_eq!(miner.claimable_rewards().unwrap(), miner.rewards_earned);
+    }
+
+    #[test]
+    fn test_forfeit_held_back_stream_rewards() {
+        let rewarder = Rewarder::default();
+        let mut quarry = Quarry {
+            famine_ts: i64::MAX,
+            ..Default::default()
+        };
+        quarry
+            .add_reward_stream(Pubkey::new_unique(), Pubkey::new_unique(), SECONDS_PER_YEAR)
+            .unwrap();
+        quarry
+            .set_unstake_penalties(
+                &[penalty(7, 5_000)],
+                PenaltyDestination::Stakers,
+                Pubkey::default(),
+            )
+            .unwrap();
+        let mut leaver = Miner::default();
+        let mut stayer = Miner::default();
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, &mut leaver, 1)
+            .unwrap();
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, &mut stayer, 1)
+            .unwrap();
+
+        // stream rewards are held back during the penalty period
+        quarry
+            .process_stake_action_internal(
+                StakeAction::Withdraw,
+                SECONDS_PER_DAY,
+                &rewarder,
+                &mut leaver,
+                1,
+            )
+            .unwrap();
+        let stream_rewards = leaver.reward_streams[0].rewards_earned;
+        assert!(stream_rewards > 0);
+        assert_eq!(leaver.penalized_stream_rewards[0], stream_rewards);
+        assert_eq!(leaver.claimable_stream_rewards(0).unwrap(), 0);
+
+        // unstaking early forfeits half of them to the remaining staker
+        let mut withdrawn = WithdrawnNfts::default();
+        withdrawn
+            .add(&quarry, &leaver, 1, 0, SECONDS_PER_DAY)
+            .unwrap();
+        quarry.forfeit_rewards(&mut leaver, &withdrawn, 1).unwrap();
+        let forfeited = stream_rewards / 2;
+        assert_eq!(
+            leaver.reward_streams[0].rewards_earned,
+            stream_rewards - forfeited
+        );
+        assert_eq!(
+            leaver.claimable_stream_rewards(0).unwrap(),
+            stream_rewards - forfeited
+        );
+
+        quarry
+            .update_rewards_and_miner(&mut stayer, &rewarder, SECONDS_PER_DAY)
+            .unwrap();
+        assert_eq!(
+            stayer.reward_streams[0].rewards_earned,
+            stream_rewards + forfeited
+        );
+    }
+
+    #[test]
+    fn test_forfeit_without_stakers() {
+        let mut quarry = Quarry::default();
+        let mut miner = Miner {
+            rewards_earned: 100,
+            ..Default::default()
+        };
+        assert_eq!(
+            quarry
+                .forfeit_rewards(&mut miner, &withdrawn(1, 5_000), 1)
+                .unwrap(),
+            50
+        );
+        assert_eq!(quarry.rewards_per_token_stored, 0);
+        assert_eq!(quarry.forfeited_rewards, 0);
+    }
+}
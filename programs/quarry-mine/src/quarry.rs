@@ -15,6 +15,13 @@ pub enum StakeAction {
 }
 
 impl Quarry {
+    /// Creates the [Payroll] of the [Quarry], following the emission schedule of the [Rewarder].
+    pub fn payroll(&self, rewarder: &Rewarder) -> Result<Payroll, ProgramError> {
+        let emission_steps =
+            rewarder.compute_quarry_emission_steps(self.rewards_share, self.last_update_ts)?;
+        Ok(Payroll::from(*self).with_emission_steps(emission_steps))
+    }
+
     /// Updates the quarry by synchronizing its rewards rate with the rewarder.
     pub fn update_rewards_internal(
         &mut self,
@@ -22,16 +29,54 @@ impl Quarry {
         rewarder: &Rewarder,
         payroll: &Payroll,
     ) -> ProgramResult {
+        self.update_reward_streams(current_ts)?;
+
         let updated_rewards_per_token_stored = payroll.calculate_reward_per_token(current_ts)?;
         // Update quarry struct
         self.rewards_per_token_stored = updated_rewards_per_token_stored;
         self.annual_rewards_rate =
-            rewarder.compute_quarry_annual_rewards_rate(self.rewards_share)?;
+            rewarder.compute_quarry_annual_rewards_rate_at(self.rewards_share, current_ts)?;
         self.last_update_ts = payroll.last_time_reward_applicable(current_ts);
 
         Ok(())
     }
 
+    /// Sets when the [Quarry] starts distributing rewards, and whether [Miner]s may stake
+    /// before then, settling rewards accrued under the previous start first.
+    pub fn update_start_ts(
+        &mut self,
+        rewarder: &Rewarder,
+        start_ts: i64,
+        allow_early_staking: bool,
+        current_ts: i64,
+    ) -> ProgramResult {
+        require!(start_ts > current_ts, InvalidStartTs);
+
+        let payroll = self.payroll(rewarder)?;
+        self.update_rewards_internal(current_ts, rewarder, &payroll)?;
+
+        self.start_ts = start_ts;
+        self.allow_early_staking = allow_early_staking;
+        Ok(())
+    }
+
+    /// Sets the [Quarry]::famine_ts, settling rewards accrued under the previous famine first.
+    pub fn update_famine_ts(
+        &mut self,
+        rewarder: &Rewarder,
+        famine_ts: i64,
+        current_ts: i64,
+    ) -> ProgramResult {
+        let payroll = self.payroll(rewarder)?;
+        self.update_rewards_internal(current_ts, rewarder, &payroll)?;
+
+        // rewards are settled until now, even if the previous famine has passed,
+        // so a later famine does not pay out the time since the previous one
+        self.last_update_ts = current_ts;
+        self.famine_ts = famine_ts;
+        Ok(())
+    }
+
     /// Updates the quarry and miner with the latest info.
     /// <https://github.com/Synthetixio/synthetix/blob/aeee6b2c82588681e1f99202663346098d1866ac/contracts/StakingRewards.sol#L158>
     pub fn update_rewards_and_miner(
@@ -40,8 +85,16 @@ impl Quarry {
         rewarder: &Rewarder,
         current_ts: i64,
     ) -> ProgramResult {
-        let payroll: Payroll = (*self).into();
+        let payroll = self.payroll(rewarder)?;
+        let stream_payrolls = self.reward_stream_payrolls();
+        let previous_rewards_earned = miner.rewards_earned;
+        let previous_stream_rewards_earned: Vec<u64> = miner
+            .reward_streams
+            .iter()
+            .map(|stream| stream.rewards_earned)
+            .collect();
         self.update_rewards_internal(current_ts, rewarder, &payroll)?;
+        self.update_miner_reward_streams(miner, &stream_payrolls, current_ts)?;
 
         let updated_rewards_earned = unwrap_int!(payroll
             .calculate_rewards_earned(
@@ -57,7 +110,11 @@ impl Quarry {
         miner.rewards_earned = updated_rewards_earned;
         miner.rewards_per_token_paid = self.rewards_per_token_stored;
 
-        Ok(())
+        miner.hold_back_penalized_rewards(
+            previous_rewards_earned,
+            &previous_stream_rewards_earned,
+            current_ts,
+        )
     }
 
     /// Processes a [StakeAction] for a [Miner],
@@ -72,6 +129,11 @@ impl Quarry {
         self.update_rewards_and_miner(miner, lord, current_ts)?;
         match action {
             StakeAction::Stake => {
+                require!(
+                    self.allow_early_staking || current_ts >= self.start_ts,
+                    QuarryNotStarted
+                );
+                self.add_penalized_weight(miner, amount, current_ts)?;
                 miner.balance = unwrap_int!(miner.balance.checked_add(amount));
                 self.total_tokens_deposited =
                     unwrap_int!(self.total_tokens_deposited.checked_add(amount));
@@ -91,7 +153,11 @@ impl Quarry {
 #[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
-    use crate::{payroll::PRECISION_MULTIPLIER, quarry::StakeAction};
+    use crate::{
+        payroll::{PRECISION_MULTIPLIER, SECONDS_PER_YEAR},
+        quarry::StakeAction,
+    };
+    use vipers::program_err;
 
     const SECONDS_PER_DAY: u64 = 86_400;
     const _DEFAULT_TOKEN_DECIMALS: u8 = 0;
@@ -382,4 +448,83 @@ mod tests {
             expected_miner_two_rewards_earned
         );
     }
+
+    #[test]
+    fn test_stake_before_start() {
+        let quarry = &mut Quarry {
+            famine_ts: i64::MAX,
+            start_ts: 1_000,
+            rewards_share: 1,
+            ..Default::default()
+        };
+        let rewarder = Rewarder {
+            annual_rewards_rate: to_unit(365),
+            total_rewards_shares: 1,
+            ..Default::default()
+        };
+        let miner = &mut Miner::default();
+
+        assert_eq!(
+            quarry.process_stake_action_internal(StakeAction::Stake, 0, &rewarder, miner, 1),
+            program_err!(QuarryNotStarted)
+        );
+
+        quarry.allow_early_staking = true;
+        sim_stake(
+            0,
+            &rewarder,
+            quarry,
+            &mut MinerVault { balance: 0 },
+            miner,
+            1,
+        );
+
+        // nothing accrues before the start
+        assert_eq!(
+            sim_claim(
+                1_000,
+                &rewarder,
+                quarry,
+                &mut MinerVault { balance: 0 },
+                miner
+            ),
+            0
+        );
+        assert!(
+            sim_claim(
+                1_000 + SECONDS_PER_DAY as i64,
+                &rewarder,
+                quarry,
+                &mut MinerVault { balance: 0 },
+                miner
+            ) > 0
+        );
+    }
+
+    #[test]
+    fn test_update_famine_ts_settles_quarry() {
+        // the quarry earns 1 token per second until 100
+        let quarry = &mut Quarry {
+            famine_ts: 100,
+            rewards_share: 1,
+            ..Default::default()
+        };
+        let rewarder = Rewarder {
+            annual_rewards_rate: SECONDS_PER_YEAR as u64,
+            total_rewards_shares: 1,
+            ..Default::default()
+        };
+        let vault = &mut MinerVault { balance: 0 };
+        let miner = &mut Miner::default();
+        sim_stake(0, &rewarder, quarry, vault, miner, 1_000);
+
+        // lifting the famine at 200 does not pay out the time since the previous famine;
+        // rewards may be off by precision errors
+        quarry.update_famine_ts(&rewarder, i64::MAX, 200).unwrap();
+        assert_eq!(sim_claim(300, &rewarder, quarry, vault, miner), 199);
+
+        // moving the famine to before 400 at 400 keeps the rewards accrued until then
+        quarry.update_famine_ts(&rewarder, 350, 400).unwrap();
+        assert_eq!(sim_claim(500, &rewarder, quarry, vault, miner), 99);
+    }
 }
@@ -8,16 +8,25 @@ use vipers::unwrap_int;
 
 use crate::ClaimEvent;
 use crate::ClaimRewards;
+use crate::Quarry;
+use crate::QuarryRewardsUpdateEvent;
 use crate::Rewarder;
+use crate::RewarderAnnualRewardsUpdateEvent;
+use crate::MAX_ANNUAL_REWARDS_RATE;
+use crate::MAX_CLAIM_FEE_MILLIBPS;
 
 impl Rewarder {
     /// Computes the amount of rewards a [crate::Quarry] should receive, annualized.
     /// This should be run only after `total_rewards_shares` has been set.
-    /// Do not call this directly. Use `compute_quarry_annual_rewards_rate`.
-    fn compute_quarry_annual_rewards_rate_unsafe(&self, quarry_rewards_share: u64) -> Option<u64> {
+    /// Do not call this directly. Use `compute_quarry_rewards_rate`.
+    fn compute_quarry_annual_rewards_rate_unsafe(
+        &self,
+        annual_rewards_rate: u64,
+        quarry_rewards_share: u64,
+    ) -> Option<u64> {
         // quarry rewards share -> 10
         // 10,950,000 * 10 / 20 ->
-        (self.annual_rewards_rate as u128)
+        (annual_rewards_rate as u128)
             .checked_mul(quarry_rewards_share as u128)?
             .checked_div(self.total_rewards_shares as u128)?
             .to_u64()
@@ -28,6 +37,16 @@ impl Rewarder {
     pub fn compute_quarry_annual_rewards_rate(
         &self,
         quarry_rewards_share: u64, // 10
+    ) -> Result<u64, ProgramError> {
+        self.compute_quarry_rewards_rate(self.annual_rewards_rate, quarry_rewards_share)
+    }
+
+    /// Computes the share of a [crate::Quarry] of an annual rewards rate of the [Rewarder].
+    /// This should be run only after `total_rewards_shares` has been set.
+    pub fn compute_quarry_rewards_rate(
+        &self,
+        annual_rewards_rate: u64,
+        quarry_rewards_share: u64,
     ) -> Result<u64, ProgramError> {
         msg!("2. Checking for Invalid reward share");
         require!(
@@ -37,43 +56,115 @@ impl Rewarder {
 
         // no rewards if:
         if self.total_rewards_shares == 0 // no shares
-            || self.annual_rewards_rate == 0 // rewards rate is zero
+            || annual_rewards_rate == 0 // rewards rate is zero
             || quarry_rewards_share == 0
         // quarry has no share
         {
             return Ok(0);
         }
 
-        let rate: u64 =
-            unwrap_int!(self.compute_quarry_annual_rewards_rate_unsafe(quarry_rewards_share));
+        let rate: u64 = unwrap_int!(self
+            .compute_quarry_annual_rewards_rate_unsafe(annual_rewards_rate, quarry_rewards_share));
 
         Ok(rate)
     }
+
+    /// Sets the annual rewards rate of the [Rewarder], which may not exceed [MAX_ANNUAL_REWARDS_RATE].
+    ///
+    /// This changes the rates of every [Quarry], which should be checkpointed first;
+    /// see [Rewarder::update_annual_rewards_rate].
+    pub fn set_annual_rewards_rate(&mut self, new_rate: u64, current_ts: i64) -> ProgramResult {
+        require!(
+            new_rate <= MAX_ANNUAL_REWARDS_RATE,
+            MaxAnnualRewardsRateExceeded
+        );
+        let previous_rate = self.annual_rewards_rate;
+        self.annual_rewards_rate = new_rate;
+
+        emit!(RewarderAnnualRewardsUpdateEvent {
+            previous_rate,
+            new_rate,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the rewards share of a [Quarry] of the [Rewarder].
+    ///
+    /// This changes the rates of every [Quarry], which should be checkpointed first;
+    /// see [Rewarder::update_rewards_share].
+    pub fn set_quarry_rewards_share(
+        &mut self,
+        quarry: &mut Quarry,
+        new_share: u64,
+        current_ts: i64,
+    ) -> ProgramResult {
+        // Rewarders annual rate -> 10,950,000
+        // Rewarders total reward share -> 0
+        // Quarry's reward share -> 0
+
+        // New share -> 10
+
+        // New value of rewarders total share -> 20
+
+        self.total_rewards_shares = unwrap_int!(self
+            .total_rewards_shares
+            .checked_add(new_share)
+            .and_then(|v| v.checked_sub(quarry.rewards_share)));
+
+        quarry.annual_rewards_rate =
+            self.compute_quarry_annual_rewards_rate_at(new_share, current_ts)?;
+        quarry.rewards_share = new_share;
+
+        emit!(QuarryRewardsUpdateEvent {
+            nft_update_authority: quarry.nft_update_authority,
+            annual_rewards_rate: quarry.annual_rewards_rate,
+            rewards_share: quarry.rewards_share,
+            timestamp: current_ts,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the claim fee of the [Rewarder], which may not exceed [MAX_CLAIM_FEE_MILLIBPS].
+    pub fn set_claim_fee(&mut self, max_claim_fee_millibps: u64) -> ProgramResult {
+        require!(
+            max_claim_fee_millibps <= MAX_CLAIM_FEE_MILLIBPS,
+            InvalidMaxClaimFee
+        );
+        self.max_claim_fee_millibps = max_claim_fee_millibps;
+        Ok(())
+    }
+
+    /// Computes the claim fee taken out of `amount_claimable`.
+    pub fn compute_claim_fee(&self, amount_claimable: u64) -> Result<u64, ProgramError> {
+        let max_claim_fee_millibps = self.max_claim_fee_millibps;
+        require!(max_claim_fee_millibps < 10_000 * 1_000, InvalidMaxClaimFee);
+        Ok(unwrap_int!((amount_claimable as u128)
+            .checked_mul(max_claim_fee_millibps.into())
+            .and_then(|f| f.checked_div((10_000 * 1_000) as u128))
+            .and_then(|f| f.to_u64())))
+    }
 }
 
 impl<'info> ClaimRewards<'info> {
     /// Calculates rewards and claims them.
     pub fn calculate_and_claim_rewards(&mut self) -> ProgramResult {
         let miner = &mut self.stake.miner;
-        let amount_claimable = miner.rewards_earned;
+        let amount_claimable = miner.claimable_rewards()?;
         if amount_claimable == 0 {
             // 0 claimable -- skip all logic
             return Ok(());
         }
 
         // Calculate rewards
-        let max_claim_fee_millibps = self.stake.rewarder.max_claim_fee_millibps;
-        msg!("5. Checking for max claim fee");
-        require!(max_claim_fee_millibps < 10_000 * 1_000, InvalidMaxClaimFee);
-        let max_claim_fee = unwrap_int!((amount_claimable as u128)
-            .checked_mul(max_claim_fee_millibps.into())
-            .and_then(|f| f.checked_div((10_000 * 1_000) as u128))
-            .and_then(|f| f.to_u64()));
+        let max_claim_fee = self.stake.rewarder.compute_claim_fee(amount_claimable)?;
 
         let amount_claimable_minus_fees = unwrap_int!(amount_claimable.checked_sub(max_claim_fee));
 
-        // Claim all rewards.
-        miner.rewards_earned = 0;
+        // Claim all rewards not held back for unstake penalties.
+        miner.rewards_earned = unwrap_int!(miner.rewards_earned.checked_sub(amount_claimable));
 
         // Setup remaining variables
         self.mint_claimed_tokens(amount_claimable_minus_fees)?;
@@ -203,6 +294,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compute_claim_fee() {
+        let mut rewarder = Rewarder {
+            max_claim_fee_millibps: crate::DEFAULT_CLAIM_FEE_MILLIBPS,
+            ..Default::default()
+        };
+        assert_eq!(rewarder.compute_claim_fee(1_000_000), Ok(100));
+        assert_eq!(rewarder.compute_claim_fee(9_999), Ok(0));
+
+        rewarder.max_claim_fee_millibps = 10_000 * 1_000;
+        assert_eq!(
+            rewarder.compute_claim_fee(1_000_000),
+            program_err!(InvalidMaxClaimFee)
+        );
+    }
+
+    #[test]
+    fn test_set_claim_fee() {
+        let mut rewarder = Rewarder::default();
+        rewarder.set_claim_fee(MAX_CLAIM_FEE_MILLIBPS).unwrap();
+        assert_eq!(rewarder.max_claim_fee_millibps, MAX_CLAIM_FEE_MILLIBPS);
+        rewarder.set_claim_fee(0).unwrap();
+        assert_eq!(rewarder.compute_claim_fee(1_000_000), Ok(0));
+
+        assert_eq!(
+            rewarder.set_claim_fee(MAX_CLAIM_FEE_MILLIBPS + 1),
+            program_err!(InvalidMaxClaimFee)
+        );
+        assert_eq!(rewarder.max_claim_fee_millibps, 0);
+    }
+
     #[test]
     fn test_compute_quarry_rewards_rate_with_multiple_quarries_fixed() {
         let rewarder = &mut Rewarder::default();
@@ -0,0 +1,300 @@
+//! Extra reward streams, which let partners co-incentivize a [Quarry] with their own token.
+//!
+//! Each [RewardStream] accrues alongside the [Quarry]'s own rewards, through a [Payroll] at
+//! the stream's rate, and is paid out of a vault owned by the [crate::Rewarder]. Anyone may
+//! top up a stream by transferring tokens to its vault.
+//!
+//! When claiming, the vault and destination token account of each active stream are passed
+//! as remaining accounts, [RewardStream::NUM_CLAIM_ACCOUNTS] per stream, in the order of the
+//! [Quarry]::reward_streams. If no remaining accounts are passed, stream rewards stay on the
+//! [Miner] until a later claim.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, TokenAccount};
+use num_traits::ToPrimitive;
+use std::cmp;
+use vipers::{assert_keys_eq, unwrap_int};
+
+use crate::payroll::Payroll;
+use crate::{
+    Miner, Quarry, RewardStream, RewardStreamClaimEvent, Rewarder, UserClaim,
+    MAX_ANNUAL_REWARDS_RATE,
+};
+
+/// Maximum number of [RewardStream]s of a [Quarry].
+pub const MAX_REWARD_STREAMS: usize = 3;
+
+impl RewardStream {
+    /// Number of remaining accounts passed per stream when claiming.
+    pub const NUM_CLAIM_ACCOUNTS: usize = 2;
+
+    /// Returns true if the stream has been added to its [Quarry].
+    pub fn is_active(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+}
+
+impl Quarry {
+    /// Adds a [RewardStream] to the [Quarry], returning its index.
+    pub fn add_reward_stream(
+        &mut self,
+        mint: Pubkey,
+        vault: Pubkey,
+        annual_rewards_rate: u64,
+    ) -> Result<usize, ProgramError> {
+        require!(
+            annual_rewards_rate <= MAX_ANNUAL_REWARDS_RATE,
+            MaxAnnualRewardsRateExceeded
+        );
+        require!(
+            !self.reward_streams.iter().any(|stream| stream.mint == mint),
+            RewardStreamExists
+        );
+        let index = self
+            .reward_streams
+            .iter()
+            .position(|stream| !stream.is_active())
+            .ok_or(crate::ErrorCode::TooManyRewardStreams)?;
+
+        self.reward_streams[index] = RewardStream {
+            mint,
+            vault,
+            annual_rewards_rate,
+            rewards_per_token_stored: 0,
+        };
+        Ok(index)
+    }
+
+    /// Sets the annual rewards rate of the [RewardStream] at `index`, settling the
+    /// [Quarry] first so the new rate only applies from now on.
+    /// Returns the mint of the stream.
+    pub fn update_reward_stream_rate(
+        &mut self,
+        rewarder: &Rewarder,
+        index: u8,
+        annual_rewards_rate: u64,
+        current_ts: i64,
+    ) -> Result<Pubkey, ProgramError> {
+        require!(
+            annual_rewards_rate <= MAX_ANNUAL_REWARDS_RATE,
+            MaxAnnualRewardsRateExceeded
+        );
+
+        let payroll = self.payroll(rewarder)?;
+        self.update_rewards_internal(current_ts, rewarder, &payroll)?;
+
+        let stream = self
+            .reward_streams
+            .get_mut(index as usize)
+            .filter(|stream| stream.is_active())
+            .ok_or(crate::ErrorCode::InvalidRewardStream)?;
+        stream.annual_rewards_rate = annual_rewards_rate;
+        Ok(stream.mint)
+    }
+
+    /// Creates the [Payroll]s of the [RewardStream]s of the [Quarry].
+    pub fn reward_stream_payrolls(&self) -> Vec<Payroll> {
+        self.reward_streams
+            .iter()
+            .map(|stream| {
+                Payroll::new(
+                    self.famine_ts,
+                    self.last_update_ts,
+                    stream.annual_rewards_rate,
+                    stream.rewards_per_token_stored,
+                    self.total_tokens_deposited,
+                )
+                .with_start_ts(self.start_ts)
+            })
+            .collect()
+    }
+
+    /// Updates the `rewards_per_token_stored` of every [RewardStream].
+    ///
+    /// This must be called before the [Quarry]::last_update_ts is moved.
+    pub fn update_reward_streams(&mut self, current_ts: i64) -> ProgramResult {
+        let payrolls = self.reward_stream_payrolls();
+        for (stream, payroll) in self.reward_streams.iter_mut().zip(payrolls) {
+            if stream.is_active() {
+                stream.rewards_per_token_stored = payroll.calculate_reward_per_token(current_ts)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the rewards a [Miner] earned from every [RewardStream], using the [Payroll]s
+    /// from before the [Quarry] was updated.
+    pub fn update_miner_reward_streams(
+        &self,
+        miner: &mut Miner,
+        payrolls: &[Payroll],
+        current_ts: i64,
+    ) -> ProgramResult {
+        for ((stream, payroll), miner_stream) in self
+            .reward_streams
+            .iter()
+            .zip(payrolls)
+            .zip(miner.reward_streams.iter_mut())
+        {
+            if !stream.is_active() {
+                continue;
+            }
+            miner_stream.rewards_earned = unwrap_int!(payroll
+                .calculate_rewards_earned(
+                    current_ts,
+                    miner.balance,
+                    miner_stream.rewards_per_token_paid,
+                    miner_stream.rewards_earned,
+                )?
+                .to_u64());
+            miner_stream.rewards_per_token_paid = stream.rewards_per_token_stored;
+        }
+        Ok(())
+    }
+}
+
+impl<'info> UserClaim<'info> {
+    /// Pays out the rewards the [Miner] earned from every [RewardStream].
+    ///
+    /// If a vault holds less than the rewards earned, it is emptied and the
+    /// remainder stays on the [Miner] until the vault is topped up.
+    pub fn claim_reward_streams(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> ProgramResult {
+        if remaining_accounts.is_empty() {
+            // stream rewards stay on the miner
+            return Ok(());
+        }
+
+        let streams: Vec<(usize, RewardStream)> = self
+            .quarry
+            .reward_streams
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, stream)| stream.is_active())
+            .collect();
+        require!(
+            remaining_accounts.len()
+                == unwrap_int!(streams.len().checked_mul(RewardStream::NUM_CLAIM_ACCOUNTS)),
+            InvalidRewardStreamAccounts
+        );
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        for ((index, stream), accounts) in streams
+            .into_iter()
+            .zip(remaining_accounts.chunks_exact(RewardStream::NUM_CLAIM_ACCOUNTS))
+        {
+            let vault: Account<TokenAccount> = Account::try_from(&accounts[0])?;
+            let destination: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+            assert_keys_eq!(vault, stream.vault, "reward stream vault");
+            assert_keys_eq!(destination.mint, stream.mint, "destination.mint");
+
+            let amount = cmp::min(self.miner.claimable_stream_rewards(index)?, vault.amount);
+            let miner_stream = &mut self.miner.reward_streams[index];
+            if amount == 0 {
+                continue;
+            }
+            miner_stream.rewards_earned =
+                unwrap_int!(miner_stream.rewards_earned.checked_sub(amount));
+
+            let seeds = gen_rewarder_signer_seeds!(self.rewarder);
+            let signer_seeds = &[&seeds[..]];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    token::Transfer {
+                        from: vault.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: self.rewarder.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                amount,
+            )?;
+
+            emit!(RewardStreamClaimEvent {
+                authority: self.authority.key(),
+                quarry: self.quarry.key(),
+                rewards_token: stream.mint,
+                amount,
+                timestamp,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::quarry::StakeAction;
+    use crate::Rewarder;
+    use vipers::program_err;
+
+    const SECONDS_PER_YEAR: u64 = 86_400 * 365;
+
+    #[test]
+    fn test_add_reward_stream() {
+        let mut quarry = Quarry::default();
+        let mints: Vec<Pubkey> = (0..=MAX_REWARD_STREAMS)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+
+        for (index, mint) in mints.iter().take(MAX_REWARD_STREAMS).enumerate() {
+            assert_eq!(
+                quarry.add_reward_stream(*mint, Pubkey::new_unique(), 1),
+                Ok(index)
+            );
+        }
+        assert_eq!(
+            quarry.add_reward_stream(mints[0], Pubkey::new_unique(), 1),
+            program_err!(RewardStreamExists)
+        );
+        assert_eq!(
+            quarry.add_reward_stream(mints[MAX_REWARD_STREAMS], Pubkey::new_unique(), 1),
+            program_err!(TooManyRewardStreams)
+        );
+    }
+
+    #[test]
+    fn test_reward_streams_accrue_with_quarry() {
+        let mut quarry = Quarry {
+            famine_ts: i64::MAX,
+            rewards_share: 1,
+            ..Default::default()
+        };
+        let rewarder = Rewarder {
+            annual_rewards_rate: SECONDS_PER_YEAR,
+            total_rewards_shares: 1,
+            ..Default::default()
+        };
+        quarry
+            .add_reward_stream(
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                2 * SECONDS_PER_YEAR,
+            )
+            .unwrap();
+
+        let mut miner = Miner::default();
+        quarry
+            .process_stake_action_internal(StakeAction::Stake, 0, &rewarder, &mut miner, 1_000)
+            .unwrap();
+        quarry
+            .update_rewards_and_miner(&mut miner, &rewarder, 100)
+            .unwrap();
+
+        // rewards may be off by precision errors
+        assert_eq!(miner.rewards_earned, 99);
+        assert_eq!(miner.reward_streams[0].rewards_earned, 200);
+        assert_eq!(
+            miner.reward_streams[0].rewards_per_token_paid,
+            quarry.reward_streams[0].rewards_per_token_stored
+        );
+        assert_eq!(miner.reward_streams[1].rewards_earned, 0);
+    }
+}
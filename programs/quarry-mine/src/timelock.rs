@@ -0,0 +1,311 @@
+//! Timelocked changes of [Rewarder] parameters.
+//!
+//! Once a [Rewarder] has a [Rewarder]::timelock_delay, its authority can no longer change the
+//! parameters of the [ParameterChange] variants directly. Changes are proposed as
+//! [QueuedChange]s instead, which anyone may execute once their `eta` has passed, and which
+//! the authority may cancel until then. The delay may only ever be increased.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+use vipers::{assert_keys_eq, unwrap_int, unwrap_or_err};
+
+use crate::checkpoint::{exit_quarries, load_quarries};
+use crate::emission::MAX_EMISSION_STEPS;
+use crate::weight_table::load_weight_table;
+use crate::{
+    EmissionSchedule, EmissionStep, ExecuteChange, ParameterChange, Quarry, QuarryKind,
+    QueuedChange, Rewarder,
+};
+
+impl QueuedChange {
+    /// Number of bytes a [QueuedChange] occupies.
+    pub const SPACE: usize =
+        // discriminator + rewarder + index + bump + payer + eta
+        8 + 32 + 8 + 1 + 32 + 8
+        // largest variant of the change: tag + emission steps
+        + 1 + MAX_EMISSION_STEPS * (8 + 8);
+}
+
+impl Rewarder {
+    /// Sets the [Rewarder]::timelock_delay, which may not decrease.
+    pub fn set_timelock_delay(&mut self, delay: i64) -> ProgramResult {
+        require!(delay >= self.timelock_delay, InvalidTimelockDelay);
+        self.timelock_delay = delay;
+        Ok(())
+    }
+
+    /// Ensures parameters may be changed without going through a [QueuedChange].
+    pub fn assert_not_timelocked(&self) -> ProgramResult {
+        require!(self.timelock_delay == 0, TimelockRequired);
+        Ok(())
+    }
+
+    /// Ensures a change proposed at `current_ts` may not execute before `eta`,
+    /// nor before the [Rewarder]::timelock_delay has elapsed.
+    pub fn assert_valid_eta(&self, eta: i64, current_ts: i64) -> ProgramResult {
+        let earliest_eta = unwrap_int!(current_ts.checked_add(self.timelock_delay));
+        require!(eta >= earliest_eta, InvalidTimelockEta);
+        Ok(())
+    }
+}
+
+impl<'info> ExecuteChange<'info> {
+    /// Applies the [QueuedChange].
+    ///
+    /// The remaining accounts are, depending on the change:
+    ///
+    /// - for [ParameterChange::AnnualRewards], every [Quarry] of the [Rewarder], as in [crate::checkpoint];
+    /// - for [ParameterChange::RewardsShare], the [Quarry], then every other [Quarry] of the [Rewarder];
+    /// - for [ParameterChange::EmissionSchedule], every [Quarry] of the [Rewarder];
+    /// - for [ParameterChange::RewardStream], the [Quarry], then the vault of the stream;
+    /// - for [ParameterChange::NftWeights], the [Quarry], then its [crate::WeightTable];
+    /// - for every other change, the [Quarry].
+    pub fn execute(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        current_ts: i64,
+    ) -> ProgramResult {
+        require!(current_ts >= self.queued_change.eta, TimelockNotElapsed);
+
+        match self.queued_change.change {
+            ParameterChange::AnnualRewards { new_rate } => {
+                let mut quarries = load_quarries(&self.rewarder, remaining_accounts, None)?;
+                let mut quarry_refs: Vec<&mut Quarry> =
+                    quarries.iter_mut().map(|quarry| &mut **quarry).collect();
+                self.rewarder
+                    .update_annual_rewards_rate(&mut quarry_refs, new_rate, current_ts)?;
+                exit_quarries(&quarries)
+            }
+            ParameterChange::RewardsShare { quarry, new_share } => {
+                require!(!remaining_accounts.is_empty(), InvalidQueuedChangeAccounts);
+                let (quarry_info, other_quarry_infos) = remaining_accounts.split_at(1);
+                let mut quarry = self.load_quarry(&quarry_info[0], quarry)?;
+                let mut other_quarries =
+                    load_quarries(&self.rewarder, other_quarry_infos, Some(quarry.key()))?;
+                let mut other_quarry_refs: Vec<&mut Quarry> = other_quarries
+                    .iter_mut()
+                    .map(|quarry| &mut **quarry)
+                    .collect();
+                self.rewarder.update_rewards_share(
+                    &mut quarry,
+                    &mut other_quarry_refs,
+                    new_share,
+                    current_ts,
+                )?;
+                exit_quarries(&other_quarries)?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::Famine { quarry, famine_ts } => {
+                let mut quarry = self.load_only_quarry(remaining_accounts, quarry)?;
+                quarry.update_famine_ts(&self.rewarder, famine_ts, current_ts)?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::EmissionSchedule { schedule } => {
+                let steps: Vec<EmissionStep> = schedule.steps().copied().collect();
+                let schedule = EmissionSchedule::new(&steps, current_ts)?;
+                let mut quarries = load_quarries(&self.rewarder, remaining_accounts, None)?;
+                let mut quarry_refs: Vec<&mut Quarry> =
+                    quarries.iter_mut().map(|quarry| &mut **quarry).collect();
+                self.rewarder
+                    .update_emission_schedule(&mut quarry_refs, schedule, current_ts)?;
+                exit_quarries(&quarries)
+            }
+            ParameterChange::RewardStream {
+                quarry,
+                mint,
+                vault,
+                annual_rewards_rate,
+            } => {
+                require!(remaining_accounts.len() == 2, InvalidQueuedChangeAccounts);
+                let mut quarry = self.load_quarry(&remaining_accounts[0], quarry)?;
+                let vault_account: Account<TokenAccount> =
+                    Account::try_from(&remaining_accounts[1])?;
+                assert_keys_eq!(vault_account, vault, "reward stream vault");
+                assert_keys_eq!(vault_account.mint, mint, "reward stream mint");
+                assert_keys_eq!(
+                    vault_account.owner,
+                    self.rewarder,
+                    "reward stream vault owner"
+                );
+
+                // checkpoint the quarry, so the stream only accrues from now on
+                let payroll = quarry.payroll(&self.rewarder)?;
+                quarry.update_rewards_internal(current_ts, &self.rewarder, &payroll)?;
+                quarry.add_reward_stream(mint, vault, annual_rewards_rate)?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::RewardStreamRate {
+                quarry,
+                index,
+                annual_rewards_rate,
+            } => {
+                let mut quarry = self.load_only_quarry(remaining_accounts, quarry)?;
+                quarry.update_reward_stream_rate(
+                    &self.rewarder,
+                    index,
+                    annual_rewards_rate,
+                    current_ts,
+                )?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::StartTs {
+                quarry,
+                start_ts,
+                allow_early_staking,
+            } => {
+                let mut quarry = self.load_only_quarry(remaining_accounts, quarry)?;
+                quarry.update_start_ts(
+                    &self.rewarder,
+                    start_ts,
+                    allow_early_staking,
+                    current_ts,
+                )?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::LockupTiers {
+                quarry,
+                lockup_tiers,
+            } => {
+                let mut quarry = self.load_only_quarry(remaining_accounts, quarry)?;
+                require!(quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+                let lockup_tiers: Vec<_> = lockup_tiers
+                    .iter()
+                    .copied()
+                    .filter(|tier| tier.duration != 0)
+                    .collect();
+                quarry.set_lockup_tiers(&lockup_tiers)?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::UnstakePenalties {
+                quarry,
+                penalties,
+                destination,
+                treasury,
+            } => {
+                let mut quarry = self.load_only_quarry(remaining_accounts, quarry)?;
+                require!(quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+                let penalties: Vec<_> = penalties
+                    .iter()
+                    .copied()
+                    .filter(|penalty| penalty.duration != 0)
+                    .collect();
+                quarry.set_unstake_penalties(&penalties, destination, treasury)?;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::WeightsMerkleRoot { quarry, root } => {
+                let mut quarry = self.load_only_quarry(remaining_accounts, quarry)?;
+                require!(quarry.kind == QuarryKind::Nft, QuarryKindMismatch);
+                require!(
+                    quarry.weight_table == Pubkey::default(),
+                    WeightSourceConflict
+                );
+                quarry.weights_merkle_root = root;
+                quarry.exit(&crate::ID)
+            }
+            ParameterChange::NftWeights { quarry, weights } => {
+                require!(remaining_accounts.len() == 2, InvalidQueuedChangeAccounts);
+                require!(
+                    remaining_accounts[1].is_writable,
+                    InvalidQueuedChangeAccounts
+                );
+                let quarry = self.load_quarry(&remaining_accounts[0], quarry)?;
+                let mut weight_table = unwrap_or_err!(
+                    load_weight_table(&quarry, Some(&remaining_accounts[1]))?,
+                    InvalidQueuedChangeAccounts
+                );
+                for weight in weights.iter().filter(|weight| weight.weight != 0) {
+                    weight_table.insert(*weight)?;
+                }
+                weight_table.exit(&crate::ID)
+            }
+        }
+    }
+
+    /// Loads the [Quarry] of a change, which must be the only remaining account.
+    fn load_only_quarry(
+        &self,
+        remaining_accounts: &[AccountInfo<'info>],
+        quarry_key: Pubkey,
+    ) -> Result<Account<'info, Quarry>, ProgramError> {
+        require!(remaining_accounts.len() == 1, InvalidQueuedChangeAccounts);
+        self.load_quarry(&remaining_accounts[0], quarry_key)
+    }
+
+    /// Loads the [Quarry] of a change.
+    fn load_quarry(
+        &self,
+        quarry_info: &AccountInfo<'info>,
+        quarry_key: Pubkey,
+    ) -> Result<Account<'info, Quarry>, ProgramError> {
+        require!(quarry_info.is_writable, InvalidQueuedChangeAccounts);
+        let quarry: Account<Quarry> = Account::try_from(quarry_info)?;
+        assert_keys_eq!(quarry, quarry_key, "quarry");
+        assert_keys_eq!(quarry.rewarder_key, self.rewarder, "rewarder");
+        Ok(quarry)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::NftWeight;
+    use vipers::program_err;
+
+    #[test]
+    fn test_set_timelock_delay() {
+        let mut rewarder = Rewarder::default();
+        assert_eq!(rewarder.assert_not_timelocked(), Ok(()));
+
+        rewarder.set_timelock_delay(100).unwrap();
+        assert_eq!(
+            rewarder.assert_not_timelocked(),
+            program_err!(TimelockRequired)
+        );
+        assert_eq!(
+            rewarder.set_timelock_delay(99),
+            program_err!(InvalidTimelockDelay)
+        );
+        assert_eq!(rewarder.set_timelock_delay(100), Ok(()));
+    }
+
+    #[test]
+    fn test_assert_valid_eta() {
+        let rewarder = Rewarder {
+            timelock_delay: 100,
+            ..Default::default()
+        };
+        assert_eq!(rewarder.assert_valid_eta(1_100, 1_000), Ok(()));
+        assert_eq!(
+            rewarder.assert_valid_eta(1_099, 1_000),
+            program_err!(InvalidTimelockEta)
+        );
+    }
+
+    #[test]
+    fn test_queued_change_space() {
+        let queued_change = QueuedChange {
+            rewarder: Pubkey::new_unique(),
+            index: 0,
+            bump: 0,
+            payer: Pubkey::new_unique(),
+            change: ParameterChange::EmissionSchedule {
+                schedule: EmissionSchedule::default(),
+            },
+            eta: 0,
+        };
+        assert_eq!(
+            8 + queued_change.try_to_vec().unwrap().len(),
+            QueuedChange::SPACE
+        );
+
+        let queued_change = QueuedChange {
+            change: ParameterChange::NftWeights {
+                quarry: Pubkey::new_unique(),
+                weights: [NftWeight::default(); 5],
+            },
+            ..queued_change
+        };
+        assert!(8 + queued_change.try_to_vec().unwrap().len() <= QueuedChange::SPACE);
+    }
+}
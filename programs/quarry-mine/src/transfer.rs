@@ -0,0 +1,195 @@
+//! Transferring a [Miner] position to a new authority.
+//!
+//! A [Miner] is a program address of its authority, so the position moves into a fresh
+//! [Miner] of the new authority, and the old one is closed. Staked tokens move between the
+//! vaults of the two [Miner]s and are passed as remaining accounts:
+//!
+//! - for [QuarryKind::Fungible] quarries, the vault of the old [Miner], then the vault of the new one;
+//! - for [QuarryKind::Nft] quarries, [NUM_NFT_TRANSFER_ACCOUNTS] accounts per staked NFT: its mint,
+//!   the vaults of the old and new [Miner]s, then the old and new [StakeReceipt]s.
+//!
+//! Every staked token must be moved. NFTs staked with [StakeCustody::Frozen] never leave their
+//! owner's wallet, so they must be withdrawn before the position is transferred.
+
+use anchor_lang::prelude::*;
+use anchor_lang::AccountsClose;
+use anchor_spl::token::{self, Mint, TokenAccount};
+use vipers::{assert_ata, assert_keys_eq, unwrap_int};
+
+use crate::account_validators::validate_miner_nft_vault;
+use crate::batch::init_stake_receipt;
+use crate::{AcceptMinerAuthority, Miner, QuarryKind, StakeCustody, StakeReceipt};
+
+/// Number of remaining accounts passed per staked NFT.
+pub const NUM_NFT_TRANSFER_ACCOUNTS: usize = 5;
+
+impl<'info> AcceptMinerAuthority<'info> {
+    /// Moves the position of the old [Miner] into the new [Miner].
+    pub fn transfer_position(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+        receipt_bumps: &[u8],
+        current_ts: i64,
+    ) -> ProgramResult {
+        self.quarry
+            .update_rewards_and_miner(&mut self.miner, &self.rewarder, current_ts)?;
+
+        match self.quarry.kind {
+            QuarryKind::Fungible => self.transfer_tokens(remaining_accounts)?,
+            QuarryKind::Nft => self.transfer_nfts(remaining_accounts, receipt_bumps)?,
+        }
+
+        let miner = &self.miner;
+        let new_miner = &mut self.new_miner;
+        new_miner.quarry_key = miner.quarry_key;
+        new_miner.nft_update_authority = miner.nft_update_authority;
+        new_miner.rewards_earned = miner.rewards_earned;
+        new_miner.rewards_per_token_paid = miner.rewards_per_token_paid;
+        new_miner.balance = miner.balance;
+        new_miner.index = miner.index;
+        new_miner.reward_streams = miner.reward_streams;
+        new_miner.penalized_weight = miner.penalized_weight;
+        new_miner.penalty_start_ts = miner.penalty_start_ts;
+        new_miner.penalty_end_ts = miner.penalty_end_ts;
+        new_miner.penalized_rewards = miner.penalized_rewards;
+        new_miner.penalized_stream_rewards = miner.penalized_stream_rewards;
+        Ok(())
+    }
+
+    /// Moves the staked tokens of a [QuarryKind::Fungible] [Miner].
+    fn transfer_tokens(&self, remaining_accounts: &[AccountInfo<'info>]) -> ProgramResult {
+        require!(remaining_accounts.len() == 2, InvalidMinerTransferAccounts);
+        let miner_vault: Account<TokenAccount> = Account::try_from(&remaining_accounts[0])?;
+        let new_miner_vault: Account<TokenAccount> = Account::try_from(&remaining_accounts[1])?;
+        assert_ata!(
+            miner_vault,
+            self.miner,
+            self.quarry.token_mint_key,
+            "miner vault"
+        );
+        assert_ata!(
+            new_miner_vault,
+            self.new_miner,
+            self.quarry.token_mint_key,
+            "new miner vault"
+        );
+        require!(
+            miner_vault.amount >= self.miner.balance,
+            InvalidMinerTransferAccounts
+        );
+
+        self.move_vault(&miner_vault, &new_miner_vault, miner_vault.amount)
+    }
+
+    /// Moves the NFTs staked into a [QuarryKind::Nft] [Miner], with their [StakeReceipt]s.
+    fn transfer_nfts(
+        &self,
+        remaining_accounts: &[AccountInfo<'info>],
+        receipt_bumps: &[u8],
+    ) -> ProgramResult {
+        require!(
+            remaining_accounts.len()
+                == unwrap_int!(receipt_bumps.len().checked_mul(NUM_NFT_TRANSFER_ACCOUNTS)),
+            InvalidMinerTransferAccounts
+        );
+
+        let mut total_weight: u64 = 0;
+        for (accounts, receipt_bump) in remaining_accounts
+            .chunks_exact(NUM_NFT_TRANSFER_ACCOUNTS)
+            .zip(receipt_bumps)
+        {
+            let token_mint: Account<Mint> = Account::try_from(&accounts[0])?;
+            let miner_nft_vault: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+            let new_miner_nft_vault: Account<TokenAccount> = Account::try_from(&accounts[2])?;
+            validate_miner_nft_vault(&self.miner, &token_mint, &miner_nft_vault)?;
+            validate_miner_nft_vault(&self.new_miner, &token_mint, &new_miner_nft_vault)?;
+
+            let stake_receipt: Account<StakeReceipt> = Account::try_from(&accounts[3])?;
+            assert_keys_eq!(stake_receipt.miner, self.miner, "receipt miner");
+            assert_keys_eq!(stake_receipt.mint, token_mint, "receipt mint");
+            require!(
+                stake_receipt.custody == StakeCustody::Vault,
+                StakeCustodyMismatch
+            );
+            total_weight = unwrap_int!(total_weight.checked_add(stake_receipt.weight));
+
+            self.move_vault(&miner_nft_vault, &new_miner_nft_vault, 1)?;
+
+            // The NFT keeps its weight, stake time and lockup.
+            init_stake_receipt(
+                &self.payer.to_account_info(),
+                &accounts[4],
+                &self.system_program.to_account_info(),
+                &StakeReceipt {
+                    miner: self.new_miner.key(),
+                    bump: *receipt_bump,
+                    ..*stake_receipt
+                },
+            )?;
+            stake_receipt.close(self.previous_authority.to_account_info())?;
+        }
+
+        require!(
+            total_weight == self.miner.balance,
+            InvalidMinerTransferAccounts
+        );
+        Ok(())
+    }
+
+    /// Moves `amount` tokens from a vault of the old [Miner] to a vault of the new [Miner],
+    /// then closes the old vault.
+    fn move_vault(
+        &self,
+        miner_vault: &Account<'info, TokenAccount>,
+        new_miner_vault: &Account<'info, TokenAccount>,
+        amount: u64,
+    ) -> ProgramResult {
+        let seeds = gen_miner_signer_seeds!(self.miner);
+        let signer_seeds = &[&seeds[..]];
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: miner_vault.to_account_info(),
+                    to: new_miner_vault.to_account_info(),
+                    authority: self.miner.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            amount,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            token::CloseAccount {
+                account: miner_vault.to_account_info(),
+                destination: self.previous_authority.to_account_info(),
+                authority: self.miner.to_account_info(),
+            },
+            signer_seeds,
+        ))
+    }
+}
+
+impl Miner {
+    /// Returns true if `authority` may accept the authority of the [Miner].
+    pub fn is_pending_authority(&self, authority: &Pubkey) -> bool {
+        self.pending_authority != Pubkey::default() && self.pending_authority == *authority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pending_authority() {
+        let mut miner = Miner::default();
+        assert!(!miner.is_pending_authority(&Pubkey::default()));
+
+        let new_authority = Pubkey::new_unique();
+        miner.pending_authority = new_authority;
+        assert!(miner.is_pending_authority(&new_authority));
+        assert!(!miner.is_pending_authority(&Pubkey::new_unique()));
+    }
+}
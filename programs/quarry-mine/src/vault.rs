@@ -0,0 +1,140 @@
+//! Pre-funded rewards vaults, for [crate::Rewarder]s which cannot hand the mint authority
+//! of their rewards token to a [quarry_mint_wrapper::MintWrapper].
+//!
+//! Rewards are transferred out of a token account owned by the [crate::Rewarder],
+//! which anyone may top up with [crate::quarry_mine::fund_rewards_vault].
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, TokenAccount};
+use std::cmp;
+use vipers::unwrap_int;
+
+use crate::{
+    ClaimEvent, ClaimRewardsFromVault, FundRewardsVault, Miner, Rewarder, RewardsVaultFundEvent,
+};
+
+impl<'info> ClaimRewardsFromVault<'info> {
+    /// Calculates rewards and claims them from the rewards vault.
+    pub fn calculate_and_claim_rewards(&mut self) -> ProgramResult {
+        let rewards_claimable = self.stake.miner.claimable_rewards()?;
+        if rewards_claimable == 0 {
+            // 0 claimable -- skip all logic
+            return Ok(());
+        }
+        require!(self.rewards_vault.amount > 0, RewardsVaultEmpty);
+
+        let (amount_claimable_minus_fees, claim_fee) = pay_from_vault(
+            &self.stake.rewarder,
+            &mut self.rewards_vault,
+            &mut self.stake.miner,
+            rewards_claimable,
+            self.rewards_token_account.to_account_info(),
+            self.claim_fee_token_account.to_account_info(),
+            self.stake.token_program.to_account_info(),
+        )?;
+
+        emit!(ClaimEvent {
+            authority: self.stake.authority.key(),
+            staked_token: self.stake.quarry.nft_update_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+            rewards_token: self.rewards_vault.mint,
+            amount: amount_claimable_minus_fees,
+            fees: claim_fee,
+        });
+
+        Ok(())
+    }
+}
+
+/// Pays `rewards_claimable` of the rewards of a [Miner] out of the rewards vault,
+/// less the claim fee.
+///
+/// If the vault holds less than `rewards_claimable`, it is emptied and the
+/// remainder stays on the [Miner] until the vault is topped up.
+///
+/// Returns the rewards paid into `rewards_token_account` and the claim fee.
+pub fn pay_from_vault<'info>(
+    rewarder: &Account<'info, Rewarder>,
+    rewards_vault: &mut Account<'info, TokenAccount>,
+    miner: &mut Miner,
+    rewards_claimable: u64,
+    rewards_token_account: AccountInfo<'info>,
+    claim_fee_token_account: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+) -> Result<(u64, u64), ProgramError> {
+    let amount_claimable = cmp::min(rewards_claimable, rewards_vault.amount);
+    let claim_fee = rewarder.compute_claim_fee(amount_claimable)?;
+    let amount_claimable_minus_fees = unwrap_int!(amount_claimable.checked_sub(claim_fee));
+
+    miner.rewards_earned = unwrap_int!(miner.rewards_earned.checked_sub(amount_claimable));
+
+    transfer_from_vault(
+        rewarder,
+        rewards_vault,
+        rewards_token_account,
+        token_program.clone(),
+        amount_claimable_minus_fees,
+    )?;
+    transfer_from_vault(
+        rewarder,
+        rewards_vault,
+        claim_fee_token_account,
+        token_program,
+        claim_fee,
+    )?;
+    rewards_vault.reload()?;
+
+    Ok((amount_claimable_minus_fees, claim_fee))
+}
+
+fn transfer_from_vault<'info>(
+    rewarder: &Account<'info, Rewarder>,
+    rewards_vault: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> ProgramResult {
+    let seeds = gen_rewarder_signer_seeds!(rewarder);
+    let signer_seeds = &[&seeds[..]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program,
+            token::Transfer {
+                from: rewards_vault.to_account_info(),
+                to: destination,
+                authority: rewarder.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )
+}
+
+impl<'info> FundRewardsVault<'info> {
+    /// Transfers tokens from the funder into the rewards vault.
+    pub fn fund_rewards_vault(&mut self, amount: u64) -> ProgramResult {
+        token::transfer(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                token::Transfer {
+                    from: self.funder_token_account.to_account_info(),
+                    to: self.rewards_vault.to_account_info(),
+                    authority: self.funder.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        self.rewards_vault.reload()?;
+
+        emit!(RewardsVaultFundEvent {
+            rewarder: self.rewarder.key(),
+            funder: self.funder.key(),
+            amount,
+            vault_balance: self.rewards_vault.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+//! Per-NFT reward weights.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Key;
+use vipers::assert_keys_eq;
+
+use crate::merkle_proof;
+use crate::{NftWeight, Quarry, UserStake, WeightProof, WeightTable};
+
+/// Weight of an NFT which has no entry in the [WeightTable] of its [crate::Quarry].
+pub const DEFAULT_NFT_WEIGHT: u64 = 1;
+
+impl WeightTable {
+    /// Number of bytes a [WeightTable] holding `capacity` entries occupies.
+    pub fn space(capacity: u32) -> usize {
+        // discriminator + quarry + bump + capacity + vec length prefix
+        8 + 32 + 1 + 4 + 4 + (capacity as usize) * NftWeight::LEN
+    }
+
+    /// Gets the weight of the given NFT mint.
+    pub fn weight_of(&self, mint: &Pubkey) -> u64 {
+        match self.entries.binary_search_by_key(mint, |entry| entry.mint) {
+            Ok(index) => self.entries[index].weight,
+            Err(_) => DEFAULT_NFT_WEIGHT,
+        }
+    }
+
+    /// Inserts or updates a weight in the table, keeping the entries sorted by mint.
+    ///
+    /// Updates only apply to future stakes: NFTs already staked are withdrawn
+    /// with the weight recorded in their [crate::StakeReceipt].
+    pub fn insert(&mut self, new_weight: NftWeight) -> ProgramResult {
+        require!(new_weight.weight > 0, InvalidNftWeight);
+        match self
+            .entries
+            .binary_search_by_key(&new_weight.mint, |entry| entry.mint)
+        {
+            Ok(index) => {
+                self.entries[index].weight = new_weight.weight;
+            }
+            Err(index) => {
+                require!(self.entries.len() < self.capacity as usize, WeightTableFull);
+                self.entries.insert(index, new_weight);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NftWeight {
+    /// Number of bytes a serialized [NftWeight] occupies.
+    pub const LEN: usize = 32 + 8;
+}
+
+impl<'info> UserStake<'info> {
+    /// Computes the weight the staked NFT adds to the [crate::Miner]'s balance.
+    ///
+    /// If the [crate::Quarry] has a weights merkle root, the weight is taken from the `weight_proof`.
+    /// If the [crate::Quarry] has a [WeightTable], it must be passed as the first
+    /// remaining account.
+    pub fn nft_weight(
+        &self,
+        weight_proof: Option<&WeightProof>,
+        remaining_accounts: &[AccountInfo],
+    ) -> Result<u64, ProgramError> {
+        let weight_table = load_weight_table(&self.quarry, remaining_accounts.first())?;
+        nft_weight(
+            &self.quarry,
+            &self.token_mint.key(),
+            weight_proof,
+            weight_table.as_deref(),
+        )
+    }
+}
+
+/// Loads the [WeightTable] of a [Quarry], if it has one.
+pub fn load_weight_table<'info>(
+    quarry: &Account<Quarry>,
+    weight_table: Option<&AccountInfo<'info>>,
+) -> Result<Option<Account<'info, WeightTable>>, ProgramError> {
+    if quarry.weight_table == Pubkey::default() {
+        return Ok(None);
+    }
+
+    let weight_table_info = weight_table.ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let weight_table: Account<WeightTable> = Account::try_from(weight_table_info)?;
+    assert_keys_eq!(weight_table, quarry.weight_table, "weight table");
+    assert_keys_eq!(weight_table.quarry, quarry.key(), "weight table quarry");
+
+    Ok(Some(weight_table))
+}
+
+/// Computes the weight of an NFT staked into a [Quarry].
+///
+/// `weight_table` must be the [WeightTable] of the [Quarry], as loaded by [load_weight_table].
+pub fn nft_weight(
+    quarry: &Quarry,
+    mint: &Pubkey,
+    weight_proof: Option<&WeightProof>,
+    weight_table: Option<&WeightTable>,
+) -> Result<u64, ProgramError> {
+    if quarry.weights_merkle_root != [0; 32] {
+        let weight_proof = weight_proof.ok_or(crate::ErrorCode::WeightProofRequired)?;
+        require!(weight_proof.weight > 0, InvalidNftWeight);
+        let leaf = merkle_proof::weight_leaf(mint, weight_proof.weight);
+        require!(
+            merkle_proof::verify(&weight_proof.proof, quarry.weights_merkle_root, leaf),
+            InvalidWeightProof
+        );
+        return Ok(weight_proof.weight);
+    }
+
+    Ok(weight_table.map_or(DEFAULT_NFT_WEIGHT, |weight_table| {
+        weight_table.weight_of(mint)
+    }))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use vipers::program_err;
+
+    fn weight(mint: Pubkey, weight: u64) -> NftWeight {
+        NftWeight { mint, weight }
+    }
+
+    #[test]
+    fn test_weight_of_defaults_for_unknown_mint() {
+        let mut table = WeightTable {
+            capacity: 2,
+            ..Default::default()
+        };
+        let legendary = Pubkey::new_unique();
+        table.insert(weight(legendary, 10)).unwrap();
+
+        assert_eq!(table.weight_of(&legendary), 10);
+        assert_eq!(table.weight_of(&Pubkey::new_unique()), DEFAULT_NFT_WEIGHT);
+    }
+
+    #[test]
+    fn test_insert_keeps_entries_sorted() {
+        let mut table = WeightTable {
+            capacity: 16,
+            ..Default::default()
+        };
+        for i in 0..16 {
+            table.insert(weight(Pubkey::new_unique(), i + 1)).unwrap();
+        }
+        assert!(table.entries.windows(2).all(|w| w[0].mint < w[1].mint));
+        for entry in table.entries.clone() {
+            assert_eq!(table.weight_of(&entry.mint), entry.weight);
+        }
+    }
+
+    #[test]
+    fn test_insert_updates_and_capacity() {
+        let mut table = WeightTable {
+            capacity: 1,
+            ..Default::default()
+        };
+        let mint = Pubkey::new_unique();
+
+        assert_eq!(
+            table.insert(weight(mint, 0)),
+            program_err!(InvalidNftWeight)
+        );
+        table.insert(weight(mint, 5)).unwrap();
+        // updating an existing mint does not take up capacity
+        table.insert(weight(mint, 6)).unwrap();
+        assert_eq!(table.weight_of(&mint), 6);
+        assert_eq!(
+            table.insert(weight(Pubkey::new_unique(), 1)),
+            program_err!(WeightTableFull)
+        );
+    }
+
+    #[test]
+    fn test_nft_weight_sources() {
+        let mint = Pubkey::new_unique();
+        let mut table = WeightTable {
+            capacity: 1,
+            ..Default::default()
+        };
+        table.insert(weight(mint, 7)).unwrap();
+
+        let mut quarry = Quarry::default();
+        assert_eq!(
+            nft_weight(&quarry, &mint, None, None).unwrap(),
+            DEFAULT_NFT_WEIGHT
+        );
+        assert_eq!(nft_weight(&quarry, &mint, None, Some(&table)).unwrap(), 7);
+
+        let leaf = merkle_proof::weight_leaf(&mint, 3);
+        quarry.weights_merkle_root = leaf;
+        let proof = WeightProof {
+            weight: 3,
+            proof: vec![],
+        };
+        assert_eq!(nft_weight(&quarry, &mint, Some(&proof), None).unwrap(), 3);
+        assert_eq!(
+            nft_weight(&quarry, &mint, None, None),
+            program_err!(WeightProofRequired)
+        );
+        let forged = WeightProof {
+            weight: 30,
+            proof: vec![],
+        };
+        assert_eq!(
+            nft_weight(&quarry, &mint, Some(&forged), None),
+            program_err!(InvalidWeightProof)
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    CreateOperator, DelegateCreateQuarry, DelegateSetAnnualRewards, DelegateSetRewardsShare,
-    SetRole, WithDelegate,
+    CreateOperator, DelegateCreateFungibleQuarry, DelegateCreateQuarry, DelegateSetAnnualRewards,
+    DelegateSetRewardsShare, SetRole, WithDelegate,
 };
 use anchor_lang::prelude::*;
 use vipers::{assert_keys_eq, validate::Validate};
@@ -47,6 +47,17 @@ impl<'info> Validate<'info> for DelegateCreateQuarry<'info> {
     }
 }
 
+impl<'info> Validate<'info> for DelegateCreateFungibleQuarry<'info> {
+    fn validate(&self) -> ProgramResult {
+        self.with_delegate.validate()?;
+        require!(
+            self.with_delegate.operator.quarry_creator == self.with_delegate.delegate.key(),
+            Unauthorized
+        );
+        Ok(())
+    }
+}
+
 impl<'info> Validate<'info> for DelegateSetRewardsShare<'info> {
     fn validate(&self) -> ProgramResult {
         self.with_delegate.validate()?;
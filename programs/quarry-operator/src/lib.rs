@@ -3,7 +3,7 @@
 #![allow(rustdoc::missing_doc_code_examples)]
 
 use anchor_lang::prelude::*;
-use quarry_mine::{Quarry, Rewarder};
+use quarry_mine::{NftGate, Quarry, Rewarder};
 use vipers::unwrap_int;
 use vipers::validate::Validate;
 
@@ -82,9 +82,10 @@ pub mod quarry_operator {
     }
 
     /// Calls [quarry_mine::quarry_mine::set_annual_rewards].
+    /// The [quarry_mine::Quarry]s to checkpoint are forwarded from the remaining accounts.
     #[access_control(ctx.accounts.validate())]
-    pub fn delegate_set_annual_rewards(
-        ctx: Context<DelegateSetAnnualRewards>,
+    pub fn delegate_set_annual_rewards<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegateSetAnnualRewards<'info>>,
         new_rate: u64,
     ) -> ProgramResult {
         let operator = &ctx.accounts.with_delegate.operator;
@@ -99,7 +100,8 @@ pub mod quarry_operator {
                     auth: ctx.accounts.with_delegate.to_auth_accounts(),
                 },
                 signer_seeds,
-            ),
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
             new_rate,
         )?;
         Ok(())
@@ -107,7 +109,12 @@ pub mod quarry_operator {
 
     /// Calls [quarry_mine::quarry_mine::create_quarry].
     #[access_control(ctx.accounts.validate())]
-    pub fn delegate_create_quarry(ctx: Context<DelegateCreateQuarry>, bump: u8) -> ProgramResult {
+    pub fn delegate_create_quarry(
+        ctx: Context<DelegateCreateQuarry>,
+        bump: u8,
+        nft_gate: NftGate,
+        nft_gate_key: Pubkey,
+    ) -> ProgramResult {
         let operator = &ctx.accounts.with_delegate.operator;
         let signer_seeds: &[&[&[u8]]] = &[gen_operator_signer_seeds!(operator)];
         quarry_mine::cpi::create_quarry(
@@ -127,14 +134,45 @@ pub mod quarry_operator {
                 signer_seeds,
             ),
             bump,
+            nft_gate,
+            nft_gate_key,
+        )?;
+        Ok(())
+    }
+
+    /// Calls [quarry_mine::quarry_mine::create_fungible_quarry].
+    #[access_control(ctx.accounts.validate())]
+    pub fn delegate_create_fungible_quarry(
+        ctx: Context<DelegateCreateFungibleQuarry>,
+        bump: u8,
+    ) -> ProgramResult {
+        let operator = &ctx.accounts.with_delegate.operator;
+        let signer_seeds: &[&[&[u8]]] = &[gen_operator_signer_seeds!(operator)];
+        quarry_mine::cpi::create_fungible_quarry(
+            CpiContext::new_with_signer(
+                ctx.accounts
+                    .with_delegate
+                    .quarry_mine_program
+                    .to_account_info(),
+                quarry_mine::cpi::accounts::CreateFungibleQuarry {
+                    quarry: ctx.accounts.quarry.to_account_info(),
+                    auth: ctx.accounts.with_delegate.to_auth_accounts(),
+                    token_mint: ctx.accounts.token_mint.to_account_info(),
+                    payer: ctx.accounts.payer.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            bump,
         )?;
         Ok(())
     }
 
     /// Calls [quarry_mine::quarry_mine::set_rewards_share].
+    /// The other [quarry_mine::Quarry]s to checkpoint are forwarded from the remaining accounts.
     #[access_control(ctx.accounts.validate())]
-    pub fn delegate_set_rewards_share(
-        ctx: Context<DelegateSetRewardsShare>,
+    pub fn delegate_set_rewards_share<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegateSetRewardsShare<'info>>,
         new_share: u64,
     ) -> ProgramResult {
         let operator = &ctx.accounts.with_delegate.operator;
@@ -150,7 +188,8 @@ pub mod quarry_operator {
                     quarry: ctx.accounts.quarry.to_account_info(),
                 },
                 signer_seeds,
-            ),
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
             new_share,
         )?;
         Ok(())
@@ -270,6 +309,25 @@ pub struct DelegateCreateQuarry<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for [crate::quarry_operator::delegate_create_fungible_quarry].
+#[derive(Accounts)]
+pub struct DelegateCreateFungibleQuarry<'info> {
+    /// Delegate accounts.
+    pub with_delegate: WithDelegate<'info>,
+    /// [Quarry] to create.
+    #[account(mut)]
+    pub quarry: UncheckedAccount<'info>,
+    /// Mint of the token to stake.
+    pub token_mint: Box<Account<'info, anchor_spl::token::Mint>>,
+
+    /// Payer of [Quarry] creation.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
 /// Accounts for [crate::quarry_operator::delegate_set_rewards_share].
 #[derive(Accounts)]
 pub struct DelegateSetRewardsShare<'info> {
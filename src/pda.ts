@@ -79,7 +79,9 @@ export async function getRewarderPDA(
 
 export async function getQuarryPDA(
   programId: PublicKey = new PublicKey(PROGRAM_IDS["mine"]),
-  updateAuthority: PublicKey = NFT_UPDATE_AUTHORITY
+  updateAuthority: PublicKey = NFT_UPDATE_AUTHORITY,
+  // collection mint or creator of a gated quarry
+  nftGateKey?: PublicKey
 ) {
   const rewarderPDA = await fs.readJSON(
     `${__dirname}/pubkeys/rewarderPDA.json`,
@@ -93,6 +95,7 @@ export async function getQuarryPDA(
       Buffer.from("Quarry"),
       new PublicKey(rewarderPDA).toBytes(),
       updateAuthority.toBytes(),
+      ...(nftGateKey ? [nftGateKey.toBytes()] : []),
     ],
     programId
   );
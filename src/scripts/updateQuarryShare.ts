@@ -10,6 +10,7 @@ import { PublicKey, Transaction } from "@solana/web3.js";
 import BN from "bn.js";
 
 const {
+  account: programAccount,
   instruction: programInstruction,
   provider: { connection: SOLANA_CONNECTION, wallet },
 } = getAnchorProgram(QuarryMineJSON, "mine");
@@ -87,6 +88,15 @@ const PAYER = wallet.publicKey;
     },
   ];
 
+  // Every other quarry of the rewarder is settled at its previous rate, sorted by key
+  const rewarderQuarries = (
+    await programAccount.quarry.all([
+      { memcmp: { offset: 8, bytes: rewarderPDA.toBase58() } },
+    ])
+  )
+    .map((quarry) => quarry.publicKey)
+    .sort((a, b) => a.toBuffer().compare(b.toBuffer()));
+
   for (let quarry of QUARRIES) {
     console.log(
       "Setting Quarry Rewards share of:",
@@ -103,6 +113,9 @@ const PAYER = wallet.publicKey;
           },
           quarry: quarry.pda,
         },
+        remainingAccounts: rewarderQuarries
+          .filter((pubkey) => !pubkey.equals(quarry.pda))
+          .map((pubkey) => ({ pubkey, isSigner: false, isWritable: true })),
       }
     );
 
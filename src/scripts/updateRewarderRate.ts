@@ -4,6 +4,7 @@ import rewarderPDA from "../pubkeys/rewarderPDA.json";
 import { Transaction } from "@solana/web3.js";
 
 const {
+  account: programAccount,
   instruction: programInstruction,
   provider: { wallet, connection: SOLANA_CONNECTION },
 } = getAnchorProgram(QuarryMineJSON, "mine");
@@ -17,6 +18,15 @@ const REWARDER_AUTHORITY = wallet.publicKey;
       ANNUAL_REWARDS_RATE.toNumber()
     );
 
+    // Every quarry of the rewarder is settled at the previous rate, sorted by key
+    const quarries = await programAccount.quarry.all([
+      { memcmp: { offset: 8, bytes: rewarderPDA } },
+    ]);
+    const remainingAccounts = quarries
+      .map((quarry) => quarry.publicKey)
+      .sort((a, b) => a.toBuffer().compare(b.toBuffer()))
+      .map((pubkey) => ({ pubkey, isSigner: false, isWritable: true }));
+
     const setAnnualRewardsIx = programInstruction.setAnnualRewards(
       ANNUAL_REWARDS_RATE,
       {
@@ -26,6 +36,7 @@ const REWARDER_AUTHORITY = wallet.publicKey;
             rewarder: rewarderPDA,
           },
         },
+        remainingAccounts,
       }
     );
 